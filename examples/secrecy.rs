@@ -13,10 +13,7 @@ pub struct Settings {
 
 impl Settings {
 	pub fn connection_string(&self) -> Secret<String> {
-		Secret::new(format!(
-			"{}",
-			self.password.expose_secret(), // 需要显示声明，才能获取password的值
-		))
+		Secret::new(self.password.expose_secret().to_string())
 	}
 }
 
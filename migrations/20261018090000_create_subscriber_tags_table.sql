@@ -0,0 +1,11 @@
+-- Add migration script here
+
+CREATE TABLE
+    subscriber_tags (
+        subscriber_id uuid NOT NULL REFERENCES subscriptions (id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        tagged_at timestamptz NOT NULL,
+        PRIMARY KEY (subscriber_id, tag)
+    );
+
+CREATE INDEX subscriber_tags_tag_idx ON subscriber_tags (tag);
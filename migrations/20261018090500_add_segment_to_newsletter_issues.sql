@@ -0,0 +1,16 @@
+-- Add migration script here
+
+-- The segment an issue is delivered to.
+-- Empty tag lists and NULL dates mean "no filter".
+
+ALTER TABLE newsletter_issues
+ADD
+    COLUMN segment_include_tags TEXT [] NOT NULL DEFAULT '{}';
+
+ALTER TABLE newsletter_issues
+ADD
+    COLUMN segment_exclude_tags TEXT [] NOT NULL DEFAULT '{}';
+
+ALTER TABLE newsletter_issues ADD COLUMN segment_subscribed_after timestamptz NULL;
+
+ALTER TABLE newsletter_issues ADD COLUMN segment_subscribed_before timestamptz NULL;
@@ -0,0 +1,6 @@
+-- Add migration script here
+
+-- Either 'html' (HTML with a plain text fallback) or 'plain_text'.
+ALTER TABLE subscriptions
+ADD
+    COLUMN email_format TEXT NOT NULL DEFAULT 'html';
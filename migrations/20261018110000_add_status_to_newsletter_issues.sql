@@ -0,0 +1,20 @@
+-- Issues now start as drafts and are only delivered once explicitly sent.
+ALTER TABLE newsletter_issues ADD COLUMN status TEXT NULL;
+ALTER TABLE newsletter_issues ADD COLUMN created_at timestamptz NOT NULL DEFAULT now();
+
+-- Issues published before the lifecycle existed are either still in the
+-- delivery queue or fully delivered.
+UPDATE newsletter_issues i
+SET status = CASE
+    WHEN EXISTS (
+        SELECT 1 FROM issue_delivery_queue q
+        WHERE q.newsletter_issue_id = i.newsletter_issue_id
+    ) THEN 'sending'
+    ELSE 'sent'
+END;
+ALTER TABLE newsletter_issues ALTER COLUMN status SET NOT NULL;
+
+-- Drafts have not been published yet.
+ALTER TABLE newsletter_issues
+    ALTER COLUMN published_at DROP NOT NULL,
+    ALTER COLUMN published_at TYPE timestamptz USING published_at::timestamptz;
@@ -0,0 +1,2 @@
+-- How many queued deliveries were dropped when an issue was cancelled.
+ALTER TABLE newsletter_issues ADD COLUMN cancelled_deliveries INT NULL;
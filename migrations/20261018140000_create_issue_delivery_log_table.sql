@@ -0,0 +1,11 @@
+-- One row per delivery attempted by the worker.
+CREATE TABLE issue_delivery_log (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_email TEXT NOT NULL,
+    -- 'sent', 'failed' or 'skipped_invalid_email'
+    outcome TEXT NOT NULL,
+    error TEXT NULL,
+    attempted_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (newsletter_issue_id, subscriber_email)
+);
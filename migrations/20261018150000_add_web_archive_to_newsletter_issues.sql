@@ -0,0 +1,3 @@
+-- Public issues are listed in the web archive, at /issues/{slug}.
+ALTER TABLE newsletter_issues ADD COLUMN is_public BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE newsletter_issues ADD COLUMN slug TEXT NULL UNIQUE;
@@ -0,0 +1,3 @@
+-- The Markdown source of issues authored in Markdown.
+-- `text_content` and `html_content` are rendered from it.
+ALTER TABLE newsletter_issues ADD COLUMN markdown_content TEXT NULL;
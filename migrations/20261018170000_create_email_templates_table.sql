@@ -0,0 +1,11 @@
+-- Layouts shared by issues, with a `{{ content }}` slot for the issue itself.
+CREATE TABLE email_templates(
+   template_id uuid NOT NULL,
+   name TEXT NOT NULL UNIQUE,
+   html_layout TEXT NOT NULL,
+   text_layout TEXT NOT NULL,
+   updated_at timestamptz NOT NULL DEFAULT now(),
+   PRIMARY KEY(template_id)
+);
+ALTER TABLE newsletter_issues
+    ADD COLUMN template_id uuid NULL REFERENCES email_templates (template_id);
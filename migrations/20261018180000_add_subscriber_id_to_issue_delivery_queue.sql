@@ -0,0 +1,11 @@
+-- Deliveries point to the subscriber, so that the worker can personalise them.
+ALTER TABLE issue_delivery_queue
+    ADD COLUMN subscriber_id uuid NULL REFERENCES subscriptions (id);
+UPDATE issue_delivery_queue q
+    SET subscriber_id = s.id
+    FROM subscriptions s
+    WHERE s.email = q.subscriber_email;
+-- Nobody can receive deliveries queued for addresses that are no longer
+-- in the subscriptions table.
+DELETE FROM issue_delivery_queue WHERE subscriber_id IS NULL;
+ALTER TABLE issue_delivery_queue ALTER COLUMN subscriber_id SET NOT NULL;
@@ -0,0 +1,9 @@
+-- The customised confirmation email, if any. There is at most one row.
+CREATE TABLE confirmation_email(
+   id BOOLEAN NOT NULL DEFAULT TRUE CHECK (id),
+   subject TEXT NOT NULL,
+   html_body TEXT NOT NULL,
+   text_body TEXT NOT NULL,
+   updated_at timestamptz NOT NULL DEFAULT now(),
+   PRIMARY KEY(id)
+);
@@ -0,0 +1,17 @@
+-- The welcome sequence: emails sent to new subscribers a number of days
+-- after they confirm their subscription.
+CREATE TABLE welcome_emails(
+   welcome_email_id uuid NOT NULL,
+   delay_days INTEGER NOT NULL CHECK (delay_days >= 0),
+   subject TEXT NOT NULL,
+   html_content TEXT NOT NULL,
+   text_content TEXT NOT NULL,
+   PRIMARY KEY(welcome_email_id)
+);
+CREATE TABLE welcome_email_queue(
+   subscriber_id uuid NOT NULL REFERENCES subscriptions (id),
+   welcome_email_id uuid NOT NULL
+      REFERENCES welcome_emails (welcome_email_id) ON DELETE CASCADE,
+   send_at timestamptz NOT NULL,
+   PRIMARY KEY(subscriber_id, welcome_email_id)
+);
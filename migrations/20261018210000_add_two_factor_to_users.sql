@@ -0,0 +1,10 @@
+-- Optional TOTP second factor for admins. The last used time step keeps
+-- a code from being used twice.
+ALTER TABLE users ADD COLUMN totp_secret TEXT NULL;
+ALTER TABLE users ADD COLUMN totp_last_used_step BIGINT NULL;
+CREATE TABLE recovery_codes(
+   user_id uuid NOT NULL REFERENCES users (user_id),
+   code_hash TEXT NOT NULL,
+   used_at timestamptz NULL,
+   PRIMARY KEY(user_id, code_hash)
+);
@@ -0,0 +1,10 @@
+-- Where admins receive password reset links.
+ALTER TABLE users ADD COLUMN email TEXT NULL;
+-- Only a hash of each token is stored: the token itself is in the email.
+CREATE TABLE password_reset_tokens(
+   token_hash TEXT NOT NULL,
+   user_id uuid NOT NULL REFERENCES users (user_id),
+   expires_at timestamptz NOT NULL,
+   used_at timestamptz NULL,
+   PRIMARY KEY(token_hash)
+);
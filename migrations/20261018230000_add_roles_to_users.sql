@@ -0,0 +1,14 @@
+-- Existing users keep full access: they become owners.
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'owner'
+    CHECK (role IN ('owner', 'editor', 'viewer'));
+ALTER TABLE users ALTER COLUMN role DROP DEFAULT;
+-- Removing a user removes what belongs to them.
+ALTER TABLE idempotency DROP CONSTRAINT idempotency_user_id_fkey,
+    ADD CONSTRAINT idempotency_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users (user_id) ON DELETE CASCADE;
+ALTER TABLE recovery_codes DROP CONSTRAINT recovery_codes_user_id_fkey,
+    ADD CONSTRAINT recovery_codes_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users (user_id) ON DELETE CASCADE;
+ALTER TABLE password_reset_tokens DROP CONSTRAINT password_reset_tokens_user_id_fkey,
+    ADD CONSTRAINT password_reset_tokens_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users (user_id) ON DELETE CASCADE;
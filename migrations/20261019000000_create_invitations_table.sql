@@ -0,0 +1,11 @@
+-- Only a hash of each token is stored: the token itself is in the email.
+CREATE TABLE invitations(
+   invitation_id uuid NOT NULL,
+   token_hash TEXT NOT NULL UNIQUE,
+   email TEXT NOT NULL,
+   role TEXT NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+   invited_by uuid NULL REFERENCES users (user_id) ON DELETE SET NULL,
+   expires_at timestamptz NOT NULL,
+   accepted_at timestamptz NULL,
+   PRIMARY KEY(invitation_id)
+);
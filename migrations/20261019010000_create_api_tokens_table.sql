@@ -0,0 +1,12 @@
+-- Tokens for headless clients. As for the other tokens, only a hash is
+-- stored: the token itself is shown once, when it is created.
+CREATE TABLE api_tokens(
+   api_token_id uuid NOT NULL,
+   user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+   name TEXT NOT NULL,
+   token_hash TEXT NOT NULL UNIQUE,
+   scopes TEXT[] NOT NULL,
+   created_at timestamptz NOT NULL,
+   last_used_at timestamptz NULL,
+   PRIMARY KEY(api_token_id)
+);
@@ -0,0 +1,3 @@
+-- When an issue was last added to or removed from the web archive.
+-- Feeds use it, along with publication dates, as their Last-Modified date.
+ALTER TABLE newsletter_issues ADD COLUMN visibility_changed_at TIMESTAMPTZ NULL;
@@ -0,0 +1,7 @@
+-- Removing a subscriber also removes the rest of their welcome sequence.
+ALTER TABLE welcome_email_queue
+   DROP CONSTRAINT welcome_email_queue_subscriber_id_fkey,
+   ADD CONSTRAINT welcome_email_queue_subscriber_id_fkey
+      FOREIGN KEY (subscriber_id) REFERENCES subscriptions (id) ON DELETE CASCADE;
+-- Welcome emails that could not be sent are tried again later.
+ALTER TABLE welcome_email_queue ADD COLUMN n_retries INTEGER NOT NULL DEFAULT 0;
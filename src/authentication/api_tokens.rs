@@ -0,0 +1,180 @@
+use anyhow::Context;
+use rand::distributions::{Alphanumeric, DistString};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::roles::Role;
+
+/// What an API token can be used for. The role of its user still applies:
+/// a viewer's token cannot publish, whatever its scopes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiScope {
+    PublishNewsletters,
+    ReadSubscribers,
+    WriteSubscribers,
+    ReadIssues,
+    WriteIssues,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 5] = [
+        ApiScope::PublishNewsletters,
+        ApiScope::ReadSubscribers,
+        ApiScope::WriteSubscribers,
+        ApiScope::ReadIssues,
+        ApiScope::WriteIssues,
+    ];
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| format!("`{}` is not a valid scope.", s))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::PublishNewsletters => "newsletters:publish",
+            ApiScope::ReadSubscribers => "subscribers:read",
+            ApiScope::WriteSubscribers => "subscribers:write",
+            ApiScope::ReadIssues => "issues:read",
+            ApiScope::WriteIssues => "issues:write",
+        }
+    }
+
+    /// The role the user of the token needs for the scope to be honoured,
+    /// the same as in the admin area.
+    pub fn required_role(&self) -> Role {
+        match self {
+            ApiScope::ReadSubscribers | ApiScope::ReadIssues => Role::Viewer,
+            ApiScope::PublishNewsletters | ApiScope::WriteSubscribers | ApiScope::WriteIssues => {
+                Role::Editor
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The API token a request was authenticated with.
+#[derive(Debug, Clone)]
+pub struct ApiToken {
+    pub api_token_id: Uuid,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl ApiToken {
+    pub fn has_scope(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+/// A new API token for the user. Returns the token itself, which is not
+/// stored anywhere.
+#[tracing::instrument(name = "Create an API token", skip(pool))]
+pub async fn create_api_token(
+    user_id: Uuid,
+    name: &str,
+    scopes: &[ApiScope],
+    pool: &PgPool,
+) -> Result<Secret<String>, anyhow::Error> {
+    let token = Secret::new(Alphanumeric.sample_string(&mut rand::thread_rng(), 40));
+    let scopes: Vec<String> = scopes.iter().map(|s| s.as_str().to_owned()).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO api_tokens (api_token_id, user_id, name, token_hash, scopes, created_at)
+        VALUES ($1, $2, $3, $4, $5, now())
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        name,
+        hash_token(&token),
+        &scopes[..],
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the API token.")?;
+    Ok(token)
+}
+
+/// The user a token belongs to, along with the token, or `None` if it is
+/// unknown or has been revoked. Records that the token has been used.
+#[tracing::instrument(name = "Authenticate an API token", skip_all)]
+pub async fn authenticate_api_token(
+    token: &Secret<String>,
+    pool: &PgPool,
+) -> Result<Option<(Uuid, ApiToken)>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE api_tokens
+        SET last_used_at = now()
+        WHERE token_hash = $1
+        RETURNING api_token_id, user_id, scopes
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the API token.")?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let scopes = row
+        .scopes
+        .iter()
+        .map(|s| ApiScope::parse(s))
+        .collect::<Result<_, _>>()
+        .map_err(anyhow::Error::msg)?;
+    Ok(Some((
+        row.user_id,
+        ApiToken {
+            api_token_id: row.api_token_id,
+            scopes,
+        },
+    )))
+}
+
+/// Revoke one of the user's tokens. Returns `false` if they have no such
+/// token.
+#[tracing::instrument(name = "Revoke an API token", skip(pool))]
+pub async fn revoke_api_token(
+    user_id: Uuid,
+    api_token_id: Uuid,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM api_tokens
+        WHERE api_token_id = $1 AND user_id = $2
+        "#,
+        api_token_id,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke the API token.")?;
+    Ok(result.rows_affected() > 0)
+}
+
+fn hash_token(token: &Secret<String>) -> String {
+    hex::encode(Sha256::digest(token.expose_secret().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApiScope;
+
+    #[test]
+    fn scopes_round_trip_through_their_name() {
+        for scope in ApiScope::ALL {
+            assert_eq!(ApiScope::parse(scope.as_str()), Ok(scope));
+        }
+        assert!(ApiScope::parse("newsletters").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use actix_web::web::Data;
+use actix_web::HttpRequest;
+use std::net::IpAddr;
+
+/// The reverse proxies whose `X-Forwarded-For` header can be trusted.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+/// The address the request comes from, as used to throttle logins and to
+/// describe sessions.
+///
+/// Anyone can send an `X-Forwarded-For` header, so it is only read when the
+/// request comes from one of the trusted proxies. Each proxy appends the
+/// address it got the request from, so the client is the last address that
+/// is not one of them.
+pub fn client_ip(request: &HttpRequest) -> String {
+    let Some(peer) = request.peer_addr().map(|addr| addr.ip()) else {
+        return String::new();
+    };
+    let trusted = request
+        .app_data::<Data<TrustedProxies>>()
+        .map(|proxies| proxies.0.as_slice())
+        .unwrap_or_default();
+    if !trusted.contains(&peer) {
+        return peer.to_string();
+    }
+    let forwarded: Vec<&str> = request
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .collect();
+    for addr in forwarded.into_iter().rev() {
+        match addr.trim().parse::<IpAddr>() {
+            Ok(addr) if trusted.contains(&addr) => continue,
+            Ok(addr) => return addr.to_string(),
+            // Nothing before a malformed entry can be relied on.
+            Err(_) => break,
+        }
+    }
+    peer.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{client_ip, TrustedProxies};
+    use actix_web::test::TestRequest;
+    use actix_web::web::Data;
+
+    fn request(peer: &str, forwarded_for: &str) -> TestRequest {
+        TestRequest::default()
+            .peer_addr(format!("{}:443", peer).parse().unwrap())
+            .insert_header(("X-Forwarded-For", forwarded_for))
+    }
+
+    #[test]
+    fn forwarded_addresses_are_ignored_without_trusted_proxies() {
+        let request = request("203.0.113.7", "192.0.2.1").to_http_request();
+        assert_eq!(client_ip(&request), "203.0.113.7");
+    }
+
+    #[test]
+    fn forwarded_addresses_are_ignored_from_other_peers() {
+        let request = request("203.0.113.7", "192.0.2.1")
+            .app_data(Data::new(TrustedProxies(vec!["10.0.0.1".parse().unwrap()])))
+            .to_http_request();
+        assert_eq!(client_ip(&request), "203.0.113.7");
+    }
+
+    #[test]
+    fn the_client_is_the_last_address_before_the_trusted_proxies() {
+        let proxies = TrustedProxies(vec![
+            "10.0.0.1".parse().unwrap(),
+            "10.0.0.2".parse().unwrap(),
+        ]);
+        // The client made up the first address.
+        let request = request("10.0.0.1", "192.0.2.1, 198.51.100.4, 10.0.0.2")
+            .app_data(Data::new(proxies))
+            .to_http_request();
+        assert_eq!(client_ip(&request), "198.51.100.4");
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::Context;
+use rand::distributions::{Alphanumeric, DistString};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::password::compute_password_hash;
+use super::roles::Role;
+use crate::domain::SubscriberEmail;
+use crate::telemetry::spawn_blocking_with_tracing;
+
+/// How long an invitation link stays valid.
+pub const INVITATION_LIFETIME_DAYS: i32 = 7;
+
+#[derive(thiserror::Error, Debug)]
+pub enum InvitationError {
+    #[error("This invitation is invalid or has expired.")]
+    InvalidToken,
+
+    #[error("This username is already taken.")]
+    UsernameTaken,
+
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+/// A new invitation for `email` to join as `role`. Returns the token to
+/// put in the invitation link.
+#[tracing::instrument(name = "Create an invitation", skip(pool))]
+pub async fn create_invitation(
+    email: &SubscriberEmail,
+    role: Role,
+    invited_by: Uuid,
+    pool: &PgPool,
+) -> Result<Secret<String>, anyhow::Error> {
+    let token = Secret::new(Alphanumeric.sample_string(&mut rand::thread_rng(), 32));
+    sqlx::query!(
+        r#"
+        INSERT INTO invitations (
+            invitation_id, token_hash, email, role, invited_by, expires_at
+        )
+        VALUES ($1, $2, $3, $4, $5, now() + make_interval(days => $6))
+        "#,
+        Uuid::new_v4(),
+        hash_token(&token),
+        email.as_ref(),
+        role.as_str(),
+        invited_by,
+        INVITATION_LIFETIME_DAYS,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the invitation.")?;
+    Ok(token)
+}
+
+/// The role an invitation grants, or `None` if it cannot be accepted.
+#[tracing::instrument(name = "Check an invitation", skip_all)]
+pub async fn invitation_role(
+    token: &Secret<String>,
+    pool: &PgPool,
+) -> Result<Option<Role>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT role
+        FROM invitations
+        WHERE token_hash = $1 AND accepted_at IS NULL AND expires_at > now()
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the invitation.")?;
+    row.map(|r| Role::parse(&r.role).map_err(anyhow::Error::msg))
+        .transpose()
+}
+
+/// Create the account of an invited user, with the role and the email
+/// address of their invitation. The invitation is used up.
+#[tracing::instrument(name = "Accept an invitation", skip(token, password, pool))]
+pub async fn accept_invitation(
+    token: &Secret<String>,
+    username: &str,
+    password: Secret<String>,
+    pool: &PgPool,
+) -> Result<Uuid, InvitationError> {
+    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
+        .await
+        .context("Failed to spawn the password hashing.")?
+        .context("Failed to hash password")?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a transaction.")?;
+    let invitation = sqlx::query!(
+        r#"
+        UPDATE invitations
+        SET accepted_at = now()
+        WHERE token_hash = $1 AND accepted_at IS NULL AND expires_at > now()
+        RETURNING email, role
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to use the invitation.")?
+    .ok_or(InvitationError::InvalidToken)?;
+    let user_id = Uuid::new_v4();
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, password_hash, role, email)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (username) DO NOTHING
+        "#,
+        user_id,
+        username,
+        password_hash.expose_secret(),
+        invitation.role,
+        invitation.email
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to store the new user.")?;
+    // Dropping the transaction rolls back the use of the invitation, so
+    // that another username can be picked.
+    if created.rows_affected() == 0 {
+        return Err(InvitationError::UsernameTaken);
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the new user.")?;
+    Ok(user_id)
+}
+
+/// Withdraw an invitation that has not been accepted yet.
+#[tracing::instrument(name = "Revoke an invitation", skip(pool))]
+pub async fn revoke_invitation(invitation_id: Uuid, pool: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM invitations
+        WHERE invitation_id = $1 AND accepted_at IS NULL
+        "#,
+        invitation_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke the invitation.")?;
+    Ok(())
+}
+
+fn hash_token(token: &Secret<String>) -> String {
+    hex::encode(Sha256::digest(token.expose_secret().as_bytes()))
+}
@@ -1,39 +1,217 @@
 use std::ops::Deref;
 
 use actix_web::{
-    body::MessageBody,
+    body::{EitherBody, MessageBody},
     dev::{ServiceRequest, ServiceResponse},
-    error::InternalError,
-    FromRequest, HttpMessage,
+    error::{ErrorForbidden, InternalError},
+    http::header::{AUTHORIZATION, CONTENT_TYPE},
+    web, FromRequest, HttpMessage,
 };
+use actix_web_flash_messages::FlashMessage;
 use actix_web_lab::middleware::Next;
+use secrecy::Secret;
+use sqlx::PgPool;
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 
+use super::api_tokens::authenticate_api_token;
+use super::roles::{get_role, Role};
+use super::sessions::touch_session;
 use crate::{
+    routes::ApiError,
     session_state::TypedSession,
-    utils::{e500, login_page},
+    utils::{dashboard_page, e500, login_page},
 };
 
-pub async fn reject_anonymous_users(
+pub async fn reject_anonymous_users<B: MessageBody + 'static>(
     mut req: ServiceRequest,
-    next: Next<impl MessageBody>,
-) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
     let session = {
         let (http_request, payload) = req.parts_mut();
         TypedSession::from_request(http_request, payload).await
     }?;
 
-    match session.get_user_id().map_err(e500)? {
-        Some(user_id) => {
-            req.extensions_mut().insert(UserId(user_id));
-            next.call(req).await
-        }
+    let user_id = match session.get_user_id().map_err(e500)? {
+        Some(user_id) => user_id,
         None => {
             let resp = login_page();
             let e = anyhow::anyhow!("The user has not logged in");
-            Err(InternalError::from_response(e, resp).into())
+            return Err(InternalError::from_response(e, resp).into());
+        }
+    };
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or_else(|| e500("The connection pool is missing"))?;
+    // Sessions opened before the index of sessions existed cannot be
+    // revoked, so they are treated as if they had been.
+    let active = match session.get_session_id().map_err(e500)? {
+        Some(session_id) => touch_session(user_id, session_id, pool)
+            .await
+            .map_err(e500)?,
+        None => false,
+    };
+    if !active {
+        tracing::info!("Rejected a revoked session");
+        // Answered with a response rather than an error, so that the session
+        // cookie is cleared on the way out.
+        session.logout();
+        FlashMessage::info("Your session has ended. Please log in again.").send();
+        return Ok(req.into_response(login_page()).map_into_right_body());
+    }
+    req.extensions_mut().insert(UserId(user_id));
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+/// Authenticate headless clients with the API token in their
+/// `Authorization: Bearer` header, instead of a session cookie.
+pub async fn reject_invalid_api_tokens(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|t| Secret::new(t.trim().to_owned()));
+    let Some(token) = token else {
+        return Err(unauthorized("The request has no API token."));
+    };
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or_else(|| e500("The connection pool is missing"))?;
+    match authenticate_api_token(&token, pool).await.map_err(e500)? {
+        Some((user_id, api_token)) => {
+            req.extensions_mut().insert(UserId(user_id));
+            req.extensions_mut().insert(api_token);
+            next.call(req).await
+        }
+        None => Err(unauthorized("The API token is invalid or has been revoked.")),
+    }
+}
+
+/// Reject state-changing requests that do not send the session's CSRF token
+/// back, in the `csrf_token` field of the form or in the `X-CSRF-Token`
+/// header. Every request gets the token in its extensions, for the forms of
+/// the page it renders. Must be layered inside `reject_anonymous_users`.
+pub async fn reject_forged_requests(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let session = {
+        let (http_request, payload) = req.parts_mut();
+        TypedSession::from_request(http_request, payload).await
+    }?;
+    let token = match session.get_csrf_token().map_err(e500)? {
+        Some(token) => token,
+        // Sessions opened before the tokens were introduced.
+        None => session.insert_new_csrf_token().map_err(e500)?,
+    };
+    if !req.method().is_safe() {
+        let submitted = match req.headers().get(CsrfToken::HEADER) {
+            Some(header) => header.to_str().ok().map(str::to_owned),
+            None => submitted_csrf_token(&mut req).await?,
+        };
+        // Compared in constant time, so that how long the rejection takes
+        // does not tell how much of a guessed token is right.
+        let genuine = submitted
+            .map(|submitted| bool::from(submitted.as_bytes().ct_eq(token.as_bytes())))
+            .unwrap_or(false);
+        if !genuine {
+            tracing::warn!("Rejected a request without the CSRF token of the session");
+            return Err(ErrorForbidden(
+                "This form has expired. Go back, reload the page and try again.",
+            ));
         }
     }
+    req.extensions_mut().insert(CsrfToken(token));
+    next.call(req).await
+}
+
+/// The `csrf_token` field of a form. The body is put back for the handler.
+async fn submitted_csrf_token(
+    req: &mut ServiceRequest,
+) -> Result<Option<String>, actix_web::Error> {
+    #[derive(serde::Deserialize)]
+    struct CsrfForm {
+        csrf_token: Option<String>,
+    }
+
+    let is_form = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|h| h.starts_with("application/x-www-form-urlencoded"));
+    if !is_form {
+        return Ok(None);
+    }
+    let body = req.extract::<web::Bytes>().await?;
+    let token = serde_urlencoded::from_bytes::<CsrfForm>(&body)
+        .ok()
+        .and_then(|form| form.csrf_token);
+    req.set_payload(body.into());
+    Ok(token)
+}
+
+fn unauthorized(message: &str) -> actix_web::Error {
+    ApiError::Unauthorized(message.into()).into()
+}
+
+/// Only let editors and owners through. Must be layered inside
+/// `reject_anonymous_users`.
+pub async fn require_editor<B: MessageBody + 'static>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    reject_users_below(Role::Editor, req, next).await
+}
+
+/// Only let owners through. Must be layered inside `reject_anonymous_users`.
+pub async fn require_owner<B: MessageBody + 'static>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    reject_users_below(Role::Owner, req, next).await
+}
+
+async fn reject_users_below<B: MessageBody + 'static>(
+    required: Role,
+    mut req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    let user_id = req
+        .extensions()
+        .get::<UserId>()
+        .copied()
+        .ok_or_else(|| e500("The user has not been authenticated"))?;
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or_else(|| e500("The connection pool is missing"))?;
+    // The role is read on every request, so that changing it takes
+    // effect right away.
+    let Some(role) = get_role(*user_id, pool).await.map_err(e500)? else {
+        tracing::info!("Rejected the session of a removed user");
+        let session = {
+            let (http_request, payload) = req.parts_mut();
+            TypedSession::from_request(http_request, payload).await
+        }?;
+        session.logout();
+        FlashMessage::info("Your session has ended. Please log in again.").send();
+        return Ok(req.into_response(login_page()).map_into_right_body());
+    };
+    if role < required {
+        tracing::warn!("The user is a {}, not an {}", role, required);
+        // Answered with a response rather than an error, so that the flash
+        // message makes it into the cookie on the way out.
+        FlashMessage::error("You do not have permission to do that.").send();
+        return Ok(req.into_response(dashboard_page()).map_into_right_body());
+    }
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -52,3 +230,19 @@ impl Deref for UserId {
         &self.0
     }
 }
+
+/// The synchronizer token of the session, set by `reject_forged_requests`.
+#[derive(Debug, Clone)]
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+    pub const HEADER: &'static str = "X-CSRF-Token";
+
+    /// The hidden field every form posting to `/admin` must include.
+    pub fn form_field(&self) -> String {
+        format!(
+            r#"<input type="hidden" name="csrf_token" value="{}">"#,
+            self.0
+        )
+    }
+}
@@ -1,11 +1,45 @@
+mod api_tokens;
+mod client_ip;
+mod invitations;
 mod middleware;
 mod password;
+mod password_reset;
+mod roles;
+mod sessions;
+mod throttle;
+mod two_factor;
 
 pub use password::{
     change_password, 
+    create_user,
     validate_credentials,
+    validate_new_password,
     AuthError, 
     Credentials
 };
 
-pub use middleware::{reject_anonymous_users, UserId};
\ No newline at end of file
+pub use api_tokens::{
+    authenticate_api_token, create_api_token, revoke_api_token, ApiScope, ApiToken,
+};
+pub use client_ip::{client_ip, TrustedProxies};
+pub use invitations::{
+    accept_invitation, create_invitation, invitation_role, revoke_invitation, InvitationError,
+    INVITATION_LIFETIME_DAYS,
+};
+pub use middleware::{
+    reject_anonymous_users, reject_forged_requests, reject_invalid_api_tokens, require_editor,
+    require_owner, CsrfToken, UserId,
+};
+pub use roles::{get_role, Role};
+pub use sessions::{
+    list_user_sessions, revoke_other_sessions, revoke_session, start_session, touch_session,
+    UserSession, SESSION_TTL_DAYS,
+};
+pub use throttle::LoginThrottle;
+pub use password_reset::{
+    create_password_reset_token, password_reset_token_is_valid, use_password_reset_token,
+};
+pub use two_factor::{
+    count_recovery_codes, disable_two_factor, enable_two_factor, generate_totp_secret,
+    has_two_factor, qr_code_svg, totp_code, totp_uri, validate_second_factor,
+};
\ No newline at end of file
@@ -5,7 +5,9 @@ use argon2::{
 };
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
+use uuid::Uuid;
 
+use super::roles::Role;
 use crate::telemetry::spawn_blocking_with_tracing;
 
 #[derive(thiserror::Error, Debug)]
@@ -97,6 +99,27 @@ async fn get_stored_credentials(
     Ok(row)
 }
 
+/// Check that a new password is acceptable and was typed the same way twice.
+pub fn validate_new_password(
+    new_password: &Secret<String>,
+    new_password_check: &Secret<String>,
+) -> Result<(), &'static str> {
+    // passwords should be longer than 12 characters but shorter than 128 characters.
+    let password_len = new_password.expose_secret().len();
+    if !(12..=128).contains(&password_len) {
+        return Err(
+            "new passwords should be longer than 12 characters but shorter than 128 characters.",
+        );
+    }
+
+    // `Secret<String>` does not implement `Eq`,
+    // therefore we need to compare the underlying `String`.
+    if new_password.expose_secret() != new_password_check.expose_secret() {
+        return Err("You entered two different new passwords - the field values must match.");
+    }
+    Ok(())
+}
+
 #[tracing::instrument(name = "Change password", skip(password, pool))]
 pub async fn change_password(
     user_id: uuid::Uuid,
@@ -123,7 +146,38 @@ pub async fn change_password(
     Ok(())
 }
 
-fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>, anyhow::Error> {
+/// Add an admin user. Returns `None` if the username is already taken.
+#[tracing::instrument(name = "Create user", skip(password, pool))]
+pub async fn create_user(
+    username: &str,
+    password: Secret<String>,
+    role: Role,
+    pool: &PgPool,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
+        .await?
+        .context("Failed to hash password")?;
+    let user_id = Uuid::new_v4();
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, password_hash, role)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (username) DO NOTHING
+        "#,
+        user_id,
+        username,
+        password_hash.expose_secret(),
+        role.as_str()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the new user.")?;
+    Ok((created.rows_affected() > 0).then_some(user_id))
+}
+
+pub(super) fn compute_password_hash(
+    password: Secret<String>,
+) -> Result<Secret<String>, anyhow::Error> {
     let salt = SaltString::generate(&mut rand::thread_rng());
     let password_hash = Argon2::new(
         Algorithm::Argon2id,
@@ -0,0 +1,116 @@
+use anyhow::Context;
+use rand::distributions::{Alphanumeric, DistString};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::SubscriberEmail;
+
+/// How long a reset link stays valid.
+const TOKEN_LIFETIME_MINUTES: i32 = 60;
+
+/// A new reset token for the user with this username, along with the
+/// address to email it to. `None` if there is no such user or if they
+/// have not given us an email address.
+#[tracing::instrument(name = "Create a password reset token", skip(pool))]
+pub async fn create_password_reset_token(
+    username: &str,
+    pool: &PgPool,
+) -> Result<Option<(Secret<String>, SubscriberEmail)>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT user_id, email AS "email!"
+        FROM users
+        WHERE username = $1 AND email IS NOT NULL
+        "#,
+        username
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the user.")?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let email = SubscriberEmail::parse(row.email).map_err(anyhow::Error::msg)?;
+    let token = Secret::new(Alphanumeric.sample_string(&mut rand::thread_rng(), 32));
+    sqlx::query!(
+        r#"
+        INSERT INTO password_reset_tokens (token_hash, user_id, expires_at)
+        VALUES ($1, $2, now() + make_interval(mins => $3))
+        "#,
+        hash_token(&token),
+        row.user_id,
+        TOKEN_LIFETIME_MINUTES,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the password reset token.")?;
+    Ok(Some((token, email)))
+}
+
+/// Whether the token can still be used to reset a password.
+#[tracing::instrument(name = "Check a password reset token", skip_all)]
+pub async fn password_reset_token_is_valid(
+    token: &Secret<String>,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT 1 AS "valid!"
+        FROM password_reset_tokens
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the password reset token.")?;
+    Ok(row.is_some())
+}
+
+/// Use up a reset token, returning the user it was issued to, or `None`
+/// if it is unknown, expired or already used. Any other token of theirs
+/// stops working too.
+#[tracing::instrument(name = "Use a password reset token", skip_all)]
+pub async fn use_password_reset_token(
+    token: &Secret<String>,
+    pool: &PgPool,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let row = sqlx::query!(
+        r#"
+        UPDATE password_reset_tokens
+        SET used_at = now()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+        RETURNING user_id
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to use the password reset token.")?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    sqlx::query!(
+        r#"
+        UPDATE password_reset_tokens
+        SET used_at = now()
+        WHERE user_id = $1 AND used_at IS NULL
+        "#,
+        row.user_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to invalidate the other password reset tokens.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the use of the password reset token.")?;
+    Ok(Some(row.user_id))
+}
+
+fn hash_token(token: &Secret<String>) -> String {
+    hex::encode(Sha256::digest(token.expose_secret().as_bytes()))
+}
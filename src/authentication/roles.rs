@@ -0,0 +1,78 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// What an admin user is allowed to do. Each role can do everything the
+/// roles below it can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Can look at everything, but only change their own account.
+    Viewer,
+    /// Can also write, schedule and send issues, and manage subscribers
+    /// and emails.
+    Editor,
+    /// Can also manage the other admin users.
+    Owner,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Owner, Role::Editor, Role::Viewer];
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|role| role.as_str() == s)
+            .ok_or_else(|| format!("`{}` is not a valid role.", s))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Owner => "owner",
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The role of the user, or `None` if they have been removed.
+#[tracing::instrument(name = "Get the role of a user", skip(pool))]
+pub async fn get_role(user_id: Uuid, pool: &PgPool) -> Result<Option<Role>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT role
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the role of the user.")?;
+    row.map(|row| Role::parse(&row.role).map_err(anyhow::Error::msg))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Role;
+
+    #[test]
+    fn roles_round_trip_through_their_name() {
+        for role in Role::ALL {
+            assert_eq!(Role::parse(role.as_str()), Ok(role));
+        }
+        assert!(Role::parse("admin").is_err());
+    }
+
+    #[test]
+    fn owners_can_do_what_editors_can() {
+        assert!(Role::Owner > Role::Editor);
+        assert!(Role::Editor > Role::Viewer);
+    }
+}
@@ -0,0 +1,244 @@
+use actix_web::http::header::USER_AGENT;
+use actix_web::HttpRequest;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::client_ip::client_ip;
+use crate::session_state::TypedSession;
+
+/// How long a session lasts once it is no longer used. The session state
+/// expires from Redis after this long, so older entries of the index are
+/// pruned.
+pub const SESSION_TTL_DAYS: i32 = 1;
+
+/// A browser the user is logged in with.
+pub struct UserSession {
+    pub session_id: Uuid,
+    pub device: String,
+    pub ip_address: String,
+    pub user_agent: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+/// Log the user in: the session gets their id, a new CSRF token and an
+/// entry in the index of their sessions. The session should have been
+/// renewed beforehand.
+#[tracing::instrument(name = "Start a session", skip(session, request, pool))]
+pub async fn start_session(
+    session: &TypedSession,
+    user_id: Uuid,
+    request: &HttpRequest,
+    pool: &PgPool,
+) -> Result<(), anyhow::Error> {
+    let user_agent = request
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default();
+    let ip_address = client_ip(request);
+    let session_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO user_sessions (
+            session_id, user_id, device, ip_address, user_agent, created_at, last_seen_at
+        )
+        VALUES ($1, $2, $3, $4, $5, now(), now())
+        "#,
+        session_id,
+        user_id,
+        describe_device(user_agent),
+        ip_address,
+        user_agent,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record the session.")?;
+    prune_expired_sessions(user_id, pool).await?;
+    session.insert_user_id(user_id)?;
+    session.insert_session_id(session_id)?;
+    session.insert_new_csrf_token()?;
+    Ok(())
+}
+
+/// Record that the session has been used. Returns `false` if it has been
+/// revoked.
+#[tracing::instrument(name = "Touch a session", skip(pool))]
+pub async fn touch_session(
+    user_id: Uuid,
+    session_id: Uuid,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let updated = sqlx::query!(
+        r#"
+        UPDATE user_sessions
+        SET last_seen_at = now()
+        WHERE session_id = $1 AND user_id = $2
+        "#,
+        session_id,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to update the session.")?;
+    Ok(updated.rows_affected() == 1)
+}
+
+/// The sessions of the user, the most recently used first.
+#[tracing::instrument(name = "List sessions", skip(pool))]
+pub async fn list_user_sessions(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Vec<UserSession>, anyhow::Error> {
+    let sessions = sqlx::query_as!(
+        UserSession,
+        r#"
+        SELECT session_id, device, ip_address, user_agent, created_at, last_seen_at
+        FROM user_sessions
+        WHERE user_id = $1 AND last_seen_at > now() - make_interval(days => $2)
+        ORDER BY last_seen_at DESC
+        "#,
+        user_id,
+        SESSION_TTL_DAYS
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the sessions.")?;
+    Ok(sessions)
+}
+
+/// Revoke one of the user's sessions. Returns `false` if they have no such
+/// session.
+#[tracing::instrument(name = "Revoke a session", skip(pool))]
+pub async fn revoke_session(
+    user_id: Uuid,
+    session_id: Uuid,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let deleted = sqlx::query!(
+        "DELETE FROM user_sessions WHERE session_id = $1 AND user_id = $2",
+        session_id,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke the session.")?;
+    Ok(deleted.rows_affected() == 1)
+}
+
+/// Revoke every session of the user but `keep`, if any. Returns how many
+/// were revoked.
+#[tracing::instrument(name = "Revoke other sessions", skip(pool))]
+pub async fn revoke_other_sessions(
+    user_id: Uuid,
+    keep: Option<Uuid>,
+    pool: &PgPool,
+) -> Result<u64, anyhow::Error> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM user_sessions
+        WHERE user_id = $1 AND ($2::uuid IS NULL OR session_id <> $2)
+        "#,
+        user_id,
+        keep
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke the sessions.")?;
+    Ok(deleted.rows_affected())
+}
+
+/// Forget the sessions of the user that have expired without being logged
+/// out, so that the index does not grow with each login.
+#[tracing::instrument(name = "Prune expired sessions", skip(pool))]
+async fn prune_expired_sessions(user_id: Uuid, pool: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM user_sessions
+        WHERE user_id = $1 AND last_seen_at < now() - make_interval(days => $2)
+        "#,
+        user_id,
+        SESSION_TTL_DAYS
+    )
+    .execute(pool)
+    .await
+    .context("Failed to prune the expired sessions.")?;
+    Ok(())
+}
+
+/// A short description of the browser, such as "Firefox on Linux".
+fn describe_device(user_agent: &str) -> String {
+    // The order matters: most browsers also claim to be the ones they
+    // were derived from.
+    const BROWSERS: [(&str, &str); 6] = [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("Firefox/", "Firefox"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+        ("curl/", "curl"),
+    ];
+    const SYSTEMS: [(&str, &str); 7] = [
+        ("Android", "Android"),
+        ("iPhone", "iOS"),
+        ("iPad", "iPadOS"),
+        ("CrOS", "ChromeOS"),
+        ("Windows", "Windows"),
+        ("Mac OS X", "macOS"),
+        ("Linux", "Linux"),
+    ];
+    let browser = BROWSERS
+        .iter()
+        .find(|(marker, _)| user_agent.contains(marker))
+        .map(|(_, name)| *name);
+    let system = SYSTEMS
+        .iter()
+        .find(|(marker, _)| user_agent.contains(marker))
+        .map(|(_, name)| *name);
+    match (browser, system) {
+        (Some(browser), Some(system)) => format!("{} on {}", browser, system),
+        (Some(name), None) | (None, Some(name)) => name.to_string(),
+        (None, None) => "Unknown device".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::describe_device;
+
+    #[test]
+    fn browsers_are_told_apart_from_the_ones_they_derive_from() {
+        for (user_agent, device) in [
+            (
+                "Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0",
+                "Firefox on Linux",
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+                (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36 Edg/129.0.0.0",
+                "Edge on Windows",
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 14) AppleWebKit/537.36 \
+                (KHTML, like Gecko) Chrome/129.0.6668.81 Mobile Safari/537.36",
+                "Chrome on Android",
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_6 like Mac OS X) AppleWebKit/605.1.15 \
+                (KHTML, like Gecko) Version/17.6 Mobile/15E148 Safari/604.1",
+                "Safari on iOS",
+            ),
+            ("curl/8.5.0", "curl"),
+        ] {
+            assert_eq!(describe_device(user_agent), device);
+        }
+    }
+
+    #[test]
+    fn unknown_user_agents_are_described_as_such() {
+        assert_eq!(describe_device(""), "Unknown device");
+        assert_eq!(describe_device("reqwest"), "Unknown device");
+    }
+}
@@ -0,0 +1,219 @@
+use anyhow::Context;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use std::time::Duration;
+
+/// Failed attempts for one username before it gets locked.
+const MAX_FAILURES_PER_USERNAME: u64 = 5;
+/// Failed attempts from one IP address before it gets locked. Higher than
+/// the per-username limit, as several admins may share an address.
+const MAX_FAILURES_PER_IP: u64 = 20;
+/// Failures are forgotten after an hour without any new one.
+const FAILURE_WINDOW_SECONDS: usize = 60 * 60;
+/// The first lockout lasts a minute, then each failure after it doubles
+/// the next one, up to an hour.
+const BASE_LOCKOUT_SECONDS: u64 = 60;
+const MAX_LOCKOUT_SECONDS: u64 = 60 * 60;
+/// Password reset links that can be asked for one username, and from one
+/// IP address, per hour. Each of them sends an email.
+const MAX_RESET_REQUESTS_PER_USERNAME: u64 = 3;
+const MAX_RESET_REQUESTS_PER_IP: u64 = 10;
+const RESET_REQUEST_WINDOW_SECONDS: usize = 60 * 60;
+
+/// Counts failed logins per username and per IP address in Redis, and
+/// locks them out for a while once they fail too often.
+#[derive(Clone)]
+pub struct LoginThrottle {
+    redis: ConnectionManager,
+}
+
+impl LoginThrottle {
+    pub async fn new(redis_uri: &str) -> Result<Self, anyhow::Error> {
+        let client = redis::Client::open(redis_uri).context("Invalid Redis URI.")?;
+        let redis = client
+            .get_tokio_connection_manager()
+            .await
+            .context("Failed to connect to Redis.")?;
+        Ok(Self { redis })
+    }
+
+    /// How long the username or the IP address is still locked out for,
+    /// if it is.
+    #[tracing::instrument(name = "Check login lockout", skip(self))]
+    pub async fn lockout(
+        &self,
+        username: &str,
+        ip: &str,
+    ) -> Result<Option<Duration>, anyhow::Error> {
+        let mut remaining = None;
+        for (kind, value) in [("user", username), ("ip", ip)] {
+            remaining = remaining.max(self.lockout_of(kind, value).await?);
+        }
+        Ok(remaining)
+    }
+
+    /// Count an attempt before its password is checked, so that attempts
+    /// sent in parallel cannot all get past the limit. Returns how long the
+    /// username or the IP address is locked out for if the attempt must be
+    /// turned away.
+    ///
+    /// Reaching the limit locks them out right away. The attempt that did it
+    /// is the only one let through, and the lockout stands if it fails.
+    #[tracing::instrument(name = "Record login attempt", skip(self))]
+    pub async fn record_attempt(
+        &self,
+        username: &str,
+        ip: &str,
+    ) -> Result<Option<Duration>, anyhow::Error> {
+        let mut lockout = None;
+        for (kind, value, max_failures) in [
+            ("user", username, MAX_FAILURES_PER_USERNAME),
+            ("ip", ip, MAX_FAILURES_PER_IP),
+        ] {
+            let failures = self.increment_failures(kind, value).await?;
+            if failures >= max_failures {
+                let duration = lockout_duration(failures - max_failures);
+                if !self.try_lock(kind, value, duration).await? {
+                    // Another attempt got there first.
+                    lockout = lockout.max(self.lockout_of(kind, value).await?);
+                }
+            }
+        }
+        Ok(lockout)
+    }
+
+    /// Forget the failures of a username once it logs in successfully, and
+    /// take the attempt back from those of the IP address. The other
+    /// failures of the address are kept, as it may be trying many usernames.
+    #[tracing::instrument(name = "Reset login failures", skip(self))]
+    pub async fn record_success(&self, username: &str, ip: &str) -> Result<(), anyhow::Error> {
+        let mut redis = self.redis.clone();
+        let key = failures_key("ip", ip);
+        redis::pipe()
+            .atomic()
+            .del(failures_key("user", username))
+            .del(lock_key("user", username))
+            .decr(&key, 1)
+            .expire(&key, FAILURE_WINDOW_SECONDS)
+            .query_async::<_, ()>(&mut redis)
+            .await
+            .context("Failed to reset the login failures.")?;
+        Ok(())
+    }
+
+    /// Count a request for a password reset link. Returns `false` once the
+    /// username or the IP address has asked for too many of them this hour,
+    /// whether the user exists or not.
+    #[tracing::instrument(name = "Record password reset request", skip(self))]
+    pub async fn allow_reset_request(
+        &self,
+        username: &str,
+        ip: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let mut allowed = true;
+        for (kind, value, max_requests) in [
+            ("user", username, MAX_RESET_REQUESTS_PER_USERNAME),
+            ("ip", ip, MAX_RESET_REQUESTS_PER_IP),
+        ] {
+            let mut redis = self.redis.clone();
+            let key = format!("reset_requests:{}:{}", kind, value);
+            // The window starts with the first request: creating the
+            // counter and counting the request happen in one transaction.
+            let (requests,): (u64,) = redis::pipe()
+                .atomic()
+                .cmd("SET")
+                .arg(&key)
+                .arg(0)
+                .arg("NX")
+                .arg("EX")
+                .arg(RESET_REQUEST_WINDOW_SECONDS)
+                .ignore()
+                .incr(&key, 1)
+                .query_async(&mut redis)
+                .await
+                .context("Failed to count a password reset request.")?;
+            allowed &= requests <= max_requests;
+        }
+        Ok(allowed)
+    }
+
+    async fn increment_failures(&self, kind: &str, value: &str) -> Result<u64, anyhow::Error> {
+        let mut redis = self.redis.clone();
+        let key = failures_key(kind, value);
+        let (failures,): (u64,) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .expire(&key, FAILURE_WINDOW_SECONDS)
+            .ignore()
+            .query_async(&mut redis)
+            .await
+            .context("Failed to count a login attempt.")?;
+        Ok(failures)
+    }
+
+    async fn lockout_of(&self, kind: &str, value: &str) -> Result<Option<Duration>, anyhow::Error> {
+        let mut redis = self.redis.clone();
+        let locked_until: Option<i64> = redis
+            .get(lock_key(kind, value))
+            .await
+            .context("Failed to read a login lockout.")?;
+        let now = chrono::Utc::now().timestamp();
+        Ok(locked_until
+            .map(|until| until - now)
+            .filter(|seconds| *seconds > 0)
+            .map(|seconds| Duration::from_secs(seconds as u64)))
+    }
+
+    /// Lock the username or the IP address out, unless it already is.
+    /// Returns whether the lockout was set.
+    async fn try_lock(
+        &self,
+        kind: &str,
+        value: &str,
+        duration: Duration,
+    ) -> Result<bool, anyhow::Error> {
+        let mut redis = self.redis.clone();
+        let locked_until = chrono::Utc::now().timestamp() + duration.as_secs() as i64;
+        let locked: Option<String> = redis::cmd("SET")
+            .arg(lock_key(kind, value))
+            .arg(locked_until)
+            .arg("NX")
+            .arg("EX")
+            .arg(duration.as_secs())
+            .query_async(&mut redis)
+            .await
+            .context("Failed to store a login lockout.")?;
+        Ok(locked.is_some())
+    }
+}
+
+/// The lockout after `extra_failures` failures beyond the limit.
+fn lockout_duration(extra_failures: u64) -> Duration {
+    let seconds = BASE_LOCKOUT_SECONDS
+        .saturating_mul(1 << extra_failures.min(16))
+        .min(MAX_LOCKOUT_SECONDS);
+    Duration::from_secs(seconds)
+}
+
+fn failures_key(kind: &str, value: &str) -> String {
+    format!("login_failures:{}:{}", kind, value)
+}
+
+fn lock_key(kind: &str, value: &str) -> String {
+    format!("login_lockout:{}:{}", kind, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lockout_duration;
+    use std::time::Duration;
+
+    #[test]
+    fn lockouts_double_up_to_an_hour() {
+        assert_eq!(lockout_duration(0), Duration::from_secs(60));
+        assert_eq!(lockout_duration(1), Duration::from_secs(120));
+        assert_eq!(lockout_duration(5), Duration::from_secs(1920));
+        assert_eq!(lockout_duration(6), Duration::from_secs(3600));
+        assert_eq!(lockout_duration(1000), Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,447 @@
+use crate::startup::HmacSecret;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use rand::distributions::{Alphanumeric, DistString};
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Codes change every 30 seconds (RFC 6238).
+const TIME_STEP_SECONDS: u64 = 30;
+const CODE_DIGITS: u32 = 6;
+const RECOVERY_CODES: usize = 10;
+/// Marks the TOTP secrets stored encrypted. Those stored before they were
+/// encrypted have no prefix, and are encrypted the next time they are used.
+const ENCRYPTED_SECRET_PREFIX: &str = "v1:";
+const NONCE_BYTES: usize = 12;
+
+/// A new random TOTP secret, base32-encoded as authenticator apps expect it.
+pub fn generate_totp_secret() -> Secret<String> {
+    let mut key = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut key);
+    Secret::new(base32::encode(
+        base32::Alphabet::RFC4648 { padding: false },
+        &key,
+    ))
+}
+
+/// The code an authenticator app shows at `unix_time` for this secret.
+pub fn totp_code(secret: &Secret<String>, unix_time: u64) -> Result<String, anyhow::Error> {
+    let key = decode_secret(secret)?;
+    Ok(format_code(hotp(&key, unix_time / TIME_STEP_SECONDS)))
+}
+
+/// The `otpauth://` URI to enrol the secret in an authenticator app.
+pub fn totp_uri(secret: &Secret<String>, issuer: &str, account: &str) -> String {
+    let issuer = urlencoding::encode(issuer);
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&digits={}&period={}",
+        issuer,
+        urlencoding::encode(account),
+        secret.expose_secret(),
+        issuer,
+        CODE_DIGITS,
+        TIME_STEP_SECONDS,
+    )
+}
+
+/// A QR code of `data`, as an inline SVG image.
+pub fn qr_code_svg(data: &str) -> Result<String, anyhow::Error> {
+    let code = qrcode::QrCode::new(data.as_bytes()).context("Failed to encode the QR code.")?;
+    Ok(code
+        .render::<qrcode::render::svg::Color>()
+        .min_dimensions(200, 200)
+        .build())
+}
+
+/// The time step matching `code`, if it is valid at `unix_time`.
+/// The codes of the previous and next steps are accepted as well, to make
+/// up for clock drift and for the time it takes to type them.
+fn verify_totp_code(key: &[u8], code: &str, unix_time: u64) -> Option<u64> {
+    let current_step = unix_time / TIME_STEP_SECONDS;
+    (current_step.saturating_sub(1)..=current_step + 1)
+        .find(|step| format_code(hotp(key, *step)) == code)
+}
+
+/// HMAC-based one-time password (RFC 4226).
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC can take a key of any size");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let truncated = u32::from_be_bytes([
+        hash[offset],
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]) & 0x7fff_ffff;
+    truncated % 10u32.pow(CODE_DIGITS)
+}
+
+fn format_code(code: u32) -> String {
+    format!("{:0width$}", code, width = CODE_DIGITS as usize)
+}
+
+fn decode_secret(secret: &Secret<String>) -> Result<Vec<u8>, anyhow::Error> {
+    base32::decode(
+        base32::Alphabet::RFC4648 { padding: false },
+        secret.expose_secret(),
+    )
+    .context("The TOTP secret is not valid base32.")
+}
+
+/// The cipher TOTP secrets are stored with, so that reading the database is
+/// not enough to generate second factors. Its key is derived from the HMAC
+/// secret of the configuration rather than being the same key.
+fn secret_cipher(hmac_secret: &HmacSecret) -> Aes256Gcm {
+    let mut mac = Hmac::<Sha256>::new_from_slice(hmac_secret.0.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(b"totp-secret-encryption");
+    // `KeyInit` is not imported: it would clash with `Mac::new_from_slice`.
+    <Aes256Gcm as aes_gcm::KeyInit>::new(&mac.finalize().into_bytes())
+}
+
+/// Encrypt a TOTP secret to store it. The user id is authenticated along
+/// with it, so that it cannot be copied over to another user.
+fn encrypt_secret(
+    secret: &Secret<String>,
+    user_id: Uuid,
+    hmac_secret: &HmacSecret,
+) -> Result<String, anyhow::Error> {
+    let mut nonce = [0u8; NONCE_BYTES];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let payload = Payload {
+        msg: secret.expose_secret().as_bytes(),
+        aad: user_id.as_bytes(),
+    };
+    let ciphertext = secret_cipher(hmac_secret)
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt the TOTP secret."))?;
+    Ok(format!(
+        "{}{}{}",
+        ENCRYPTED_SECRET_PREFIX,
+        hex::encode(nonce),
+        hex::encode(ciphertext)
+    ))
+}
+
+fn decrypt_secret(
+    stored: &str,
+    user_id: Uuid,
+    hmac_secret: &HmacSecret,
+) -> Result<Secret<String>, anyhow::Error> {
+    let Some(encrypted) = stored.strip_prefix(ENCRYPTED_SECRET_PREFIX) else {
+        return Ok(Secret::new(stored.to_string()));
+    };
+    let encrypted = hex::decode(encrypted).context("The TOTP secret is not valid hex.")?;
+    if encrypted.len() < NONCE_BYTES {
+        anyhow::bail!("The TOTP secret is too short.");
+    }
+    let (nonce, ciphertext) = encrypted.split_at(NONCE_BYTES);
+    let payload = Payload {
+        msg: ciphertext,
+        aad: user_id.as_bytes(),
+    };
+    let secret = secret_cipher(hmac_secret)
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt the TOTP secret."))?;
+    Ok(Secret::new(
+        String::from_utf8(secret).context("The TOTP secret is not valid UTF-8.")?,
+    ))
+}
+
+fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            let code = Alphanumeric.sample_string(&mut rng, 10).to_lowercase();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+/// Recovery codes are random enough that a fast hash is all they need.
+fn hash_recovery_code(code: &str) -> String {
+    let code: String = code
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+    hex::encode(Sha256::digest(code.as_bytes()))
+}
+
+fn unix_time_now() -> u64 {
+    chrono::Utc::now()
+        .timestamp()
+        .try_into()
+        .unwrap_or_default()
+}
+
+#[tracing::instrument(name = "Check if two-factor authentication is enabled", skip(pool))]
+pub async fn has_two_factor(user_id: Uuid, pool: &PgPool) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT totp_secret
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to retrieve the two-factor settings of the user.")?;
+    Ok(row.totp_secret.is_some())
+}
+
+/// The number of recovery codes the user has not used yet.
+#[tracing::instrument(name = "Count unused recovery codes", skip(pool))]
+pub async fn count_recovery_codes(user_id: Uuid, pool: &PgPool) -> Result<i64, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT count(*) AS "n!"
+        FROM recovery_codes
+        WHERE user_id = $1 AND used_at IS NULL
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count the recovery codes of the user.")?;
+    Ok(row.n)
+}
+
+/// Turn on two-factor authentication once the user has proven that their
+/// authenticator app knows `secret`. Returns their new recovery codes, or
+/// `None` if `code` is not valid.
+#[tracing::instrument(
+    name = "Enable two-factor authentication",
+    skip(secret, code, hmac_secret, pool)
+)]
+pub async fn enable_two_factor(
+    user_id: Uuid,
+    secret: &Secret<String>,
+    code: &str,
+    hmac_secret: &HmacSecret,
+    pool: &PgPool,
+) -> Result<Option<Vec<String>>, anyhow::Error> {
+    let key = decode_secret(secret)?;
+    let step = match verify_totp_code(&key, code.trim(), unix_time_now()) {
+        Some(step) => step,
+        None => return Ok(None),
+    };
+    let recovery_codes = generate_recovery_codes();
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_secret = $1, totp_last_used_step = $2
+        WHERE user_id = $3
+        "#,
+        encrypt_secret(secret, user_id, hmac_secret)?,
+        step as i64,
+        user_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to store the TOTP secret.")?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut transaction)
+        .await
+        .context("Failed to delete the old recovery codes.")?;
+    for code in &recovery_codes {
+        sqlx::query!(
+            r#"
+            INSERT INTO recovery_codes (user_id, code_hash)
+            VALUES ($1, $2)
+            "#,
+            user_id,
+            hash_recovery_code(code)
+        )
+        .execute(&mut transaction)
+        .await
+        .context("Failed to store a recovery code.")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the two-factor settings.")?;
+    Ok(Some(recovery_codes))
+}
+
+#[tracing::instrument(name = "Disable two-factor authentication", skip(pool))]
+pub async fn disable_two_factor(user_id: Uuid, pool: &PgPool) -> Result<(), anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_secret = NULL, totp_last_used_step = NULL
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to remove the TOTP secret.")?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut transaction)
+        .await
+        .context("Failed to delete the recovery codes.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the two-factor settings.")?;
+    Ok(())
+}
+
+/// Check the second factor of a user: a code from their authenticator app
+/// or one of their recovery codes. Both can only be used once.
+#[tracing::instrument(name = "Validate second factor", skip(code, hmac_secret, pool))]
+pub async fn validate_second_factor(
+    user_id: Uuid,
+    code: &Secret<String>,
+    hmac_secret: &HmacSecret,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let code = code.expose_secret().trim();
+    let mut transaction = pool.begin().await?;
+    let row = sqlx::query!(
+        r#"
+        SELECT totp_secret, totp_last_used_step
+        FROM users
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_one(&mut transaction)
+    .await
+    .context("Failed to retrieve the two-factor settings of the user.")?;
+    let Some(stored_secret) = row.totp_secret else {
+        return Ok(false);
+    };
+    let secret = decrypt_secret(&stored_secret, user_id, hmac_secret)?;
+    if !stored_secret.starts_with(ENCRYPTED_SECRET_PREFIX) {
+        sqlx::query!(
+            "UPDATE users SET totp_secret = $1 WHERE user_id = $2",
+            encrypt_secret(&secret, user_id, hmac_secret)?,
+            user_id
+        )
+        .execute(&mut transaction)
+        .await
+        .context("Failed to encrypt the TOTP secret.")?;
+    }
+    let key = decode_secret(&secret)?;
+    if let Some(step) = verify_totp_code(&key, code, unix_time_now()) {
+        // A code that has already been used could have been seen over the
+        // user's shoulder: reject it, and any older one.
+        if row
+            .totp_last_used_step
+            .is_some_and(|last| step as i64 <= last)
+        {
+            return Ok(false);
+        }
+        sqlx::query!(
+            "UPDATE users SET totp_last_used_step = $1 WHERE user_id = $2",
+            step as i64,
+            user_id
+        )
+        .execute(&mut transaction)
+        .await
+        .context("Failed to record the use of a TOTP code.")?;
+    } else {
+        let used = sqlx::query!(
+            r#"
+            UPDATE recovery_codes
+            SET used_at = now()
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#,
+            user_id,
+            hash_recovery_code(code)
+        )
+        .execute(&mut transaction)
+        .await
+        .context("Failed to use a recovery code.")?;
+        if used.rows_affected() == 0 {
+            return Ok(false);
+        }
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the use of the second factor.")?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt_secret, encrypt_secret, hash_recovery_code, hotp, verify_totp_code};
+    use crate::startup::HmacSecret;
+    use secrecy::{ExposeSecret, Secret};
+    use uuid::Uuid;
+
+    // The SHA-1 test vectors of RFC 6238, truncated to 6 digits.
+    const KEY: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn codes_match_the_rfc_test_vectors() {
+        for (time, code) in [
+            (59, 287082),
+            (1111111109, 81804),
+            (1111111111, 50471),
+            (1234567890, 5924),
+            (2000000000, 279037),
+        ] {
+            assert_eq!(hotp(KEY, time / 30), code);
+        }
+    }
+
+    #[test]
+    fn codes_of_the_neighbouring_steps_are_accepted() {
+        assert_eq!(
+            verify_totp_code(KEY, "081804", 1111111109 + 30),
+            Some(37037036)
+        );
+        assert_eq!(
+            verify_totp_code(KEY, "081804", 1111111109 - 30),
+            Some(37037036)
+        );
+        assert_eq!(verify_totp_code(KEY, "081804", 1111111109 + 60), None);
+        assert_eq!(verify_totp_code(KEY, "81804", 1111111109), None);
+    }
+
+    #[test]
+    fn recovery_codes_ignore_case_and_spaces() {
+        assert_eq!(
+            hash_recovery_code("abcde-12345"),
+            hash_recovery_code(" ABCDE-12345 ")
+        );
+    }
+
+    #[test]
+    fn secrets_are_only_decrypted_for_their_user() {
+        let hmac_secret = HmacSecret(Secret::new("a-long-and-random-key".into()));
+        let secret = Secret::new("JBSWY3DPEHPK3PXP".to_string());
+        let user_id = Uuid::new_v4();
+
+        let stored = encrypt_secret(&secret, user_id, &hmac_secret).unwrap();
+
+        assert!(!stored.contains(secret.expose_secret()));
+        let decrypted = decrypt_secret(&stored, user_id, &hmac_secret).unwrap();
+        assert_eq!(decrypted.expose_secret(), secret.expose_secret());
+        assert!(decrypt_secret(&stored, Uuid::new_v4(), &hmac_secret).is_err());
+        let other_key = HmacSecret(Secret::new("another-key".into()));
+        assert!(decrypt_secret(&stored, user_id, &other_key).is_err());
+    }
+
+    #[test]
+    fn secrets_stored_before_encryption_are_still_read() {
+        let hmac_secret = HmacSecret(Secret::new("a-long-and-random-key".into()));
+        let secret = decrypt_secret("JBSWY3DPEHPK3PXP", Uuid::new_v4(), &hmac_secret).unwrap();
+        assert_eq!(secret.expose_secret(), "JBSWY3DPEHPK3PXP");
+    }
+}
@@ -1,4 +1,5 @@
 use std::convert::TryFrom;
+use std::net::IpAddr;
 
 use secrecy::{ExposeSecret, Secret};
 use serde_aux::field_attributes::deserialize_number_from_string;
@@ -7,7 +8,10 @@ use sqlx::{
     ConnectOptions,
 };
 
-use crate::{domain::SubscriberEmail, email_client::EmailClient};
+use crate::{
+    domain::{SubscriberEmail, SubscriberTag},
+    email_client::EmailClient,
+};
 
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct Settings {
@@ -15,6 +19,31 @@ pub struct Settings {
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
     pub redis_uri: Secret<String>,
+    pub newsletter: NewsletterSettings,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct NewsletterSettings {
+    // The tags subscribers can opt in or out of from their preference center.
+    pub topics: Vec<String>,
+    // Who receives test copies of an issue before it goes out.
+    pub reviewer_emails: Vec<String>,
+}
+
+impl NewsletterSettings {
+    pub fn topics(&self) -> Result<Vec<SubscriberTag>, String> {
+        self.topics
+            .iter()
+            .map(|t| SubscriberTag::parse(t.clone()))
+            .collect()
+    }
+
+    pub fn reviewer_emails(&self) -> Result<Vec<SubscriberEmail>, String> {
+        self.reviewer_emails
+            .iter()
+            .map(|e| SubscriberEmail::parse(e.clone()))
+            .collect()
+    }
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -55,6 +84,9 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    // The reverse proxies allowed to tell the address of the client.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -0,0 +1,167 @@
+use htmlescape::encode_minimal;
+
+use super::email_layout::{placeholders, render};
+
+/// The merge fields available in the confirmation email.
+pub const CONFIRMATION_FIELDS: [&str; 2] = ["name", "confirmation_link"];
+
+/// The email sent to new subscribers to confirm their subscription.
+/// Both bodies must contain the `{{ confirmation_link }}` placeholder.
+#[derive(Debug, Clone)]
+pub struct ConfirmationEmail {
+    subject: String,
+    html: String,
+    text: String,
+}
+
+impl ConfirmationEmail {
+    /// Returns an instance of `ConfirmationEmail` if the subject is not blank,
+    /// only known merge fields are used and both bodies contain the
+    /// confirmation link.
+    pub fn parse(subject: String, html: String, text: String) -> Result<Self, String> {
+        if subject.trim().is_empty() {
+            return Err("The subject of the confirmation email cannot be empty.".into());
+        }
+        for (template, kind) in [
+            (&subject, "subject"),
+            (&html, "HTML body"),
+            (&text, "plain text body"),
+        ] {
+            if let Some(unknown) = placeholders(template)
+                .into_iter()
+                .find(|f| !CONFIRMATION_FIELDS.contains(f))
+            {
+                return Err(format!(
+                    "The {} uses an unknown merge field: `{{{{ {} }}}}`.",
+                    kind, unknown
+                ));
+            }
+        }
+        for (template, kind) in [(&html, "HTML body"), (&text, "plain text body")] {
+            if !placeholders(template).contains(&"confirmation_link") {
+                return Err(format!(
+                    "The {} must contain `{{{{ confirmation_link }}}}`.",
+                    kind
+                ));
+            }
+        }
+        Ok(Self {
+            subject: subject.trim().into(),
+            html,
+            text,
+        })
+    }
+
+    /// The confirmation email sent until an admin customises it.
+    pub fn default_email() -> Self {
+        Self {
+            subject: "Welcome!".into(),
+            html: r#"Welcome to our newsletter!<br />
+Click <a href="{{ confirmation_link }}">here</a> to confirm your subscription."#
+                .into(),
+            text: "Welcome to our newsletter!\nVisit {{ confirmation_link }} to confirm your subscription."
+                .into(),
+        }
+    }
+
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn render_subject(&self, name: &str, confirmation_link: &str) -> String {
+        render(&self.subject, |f| field(f, name, confirmation_link))
+    }
+
+    /// Fill in the merge fields of the HTML body, escaping their values.
+    pub fn render_html(&self, name: &str, confirmation_link: &str) -> String {
+        render(&self.html, |f| {
+            field(f, name, confirmation_link).map(|v| encode_minimal(&v))
+        })
+    }
+
+    pub fn render_text(&self, name: &str, confirmation_link: &str) -> String {
+        render(&self.text, |f| field(f, name, confirmation_link))
+    }
+}
+
+fn field(field: &str, name: &str, confirmation_link: &str) -> Option<String> {
+    match field {
+        "name" => Some(name.into()),
+        "confirmation_link" => Some(confirmation_link.into()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConfirmationEmail;
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn both_bodies_need_the_confirmation_link() {
+        assert_err!(ConfirmationEmail::parse(
+            "Welcome!".into(),
+            "<p>Hi {{ name }}</p>".into(),
+            "Confirm: {{ confirmation_link }}".into()
+        ));
+        assert_err!(ConfirmationEmail::parse(
+            "Welcome!".into(),
+            r#"<a href="{{confirmation_link}}">Confirm</a>"#.into(),
+            "Hi {{ name }}".into()
+        ));
+        assert_ok!(ConfirmationEmail::parse(
+            "Welcome!".into(),
+            r#"<a href="{{confirmation_link}}">Confirm</a>"#.into(),
+            "Confirm: {{ confirmation_link }}".into()
+        ));
+    }
+
+    #[test]
+    fn unknown_merge_fields_are_rejected() {
+        assert_err!(ConfirmationEmail::parse(
+            "Welcome {{ unsubscribe_url }}".into(),
+            "{{ confirmation_link }}".into(),
+            "{{ confirmation_link }}".into()
+        ));
+    }
+
+    #[test]
+    fn a_blank_subject_is_rejected() {
+        assert_err!(ConfirmationEmail::parse(
+            " ".into(),
+            "{{ confirmation_link }}".into(),
+            "{{ confirmation_link }}".into()
+        ));
+    }
+
+    #[test]
+    fn merge_fields_are_filled_in() {
+        let email = ConfirmationEmail::parse(
+            "Welcome, {{ name }}!".into(),
+            r#"<p>Hi {{ name }}, <a href="{{ confirmation_link }}">confirm</a></p>"#.into(),
+            "Hi {{ name }}, visit {{ confirmation_link }}".into(),
+        )
+        .unwrap();
+        let link = "https://example.com/confirm?token=a&b";
+        assert_eq!(
+            email.render_subject("Tom & Jerry", link),
+            "Welcome, Tom & Jerry!"
+        );
+        assert_eq!(
+            email.render_html("Tom & Jerry", link),
+            r#"<p>Hi Tom &amp; Jerry, <a href="https://example.com/confirm?token=a&amp;b">confirm</a></p>"#
+        );
+        assert_eq!(
+            email.render_text("Tom & Jerry", link),
+            "Hi Tom & Jerry, visit https://example.com/confirm?token=a&b"
+        );
+    }
+}
@@ -0,0 +1,53 @@
+/// How a subscriber wants to receive newsletter issues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, utoipa::ToSchema)]
+#[schema(rename_all = "snake_case")]
+pub enum EmailFormat {
+    /// HTML, with the plain text version as a fallback.
+    #[default]
+    Html,
+    /// Plain text only.
+    PlainText,
+}
+
+impl EmailFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailFormat::Html => "html",
+            EmailFormat::PlainText => "plain_text",
+        }
+    }
+}
+
+impl TryFrom<String> for EmailFormat {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "html" => Ok(Self::Html),
+            "plain_text" => Ok(Self::PlainText),
+            other => Err(format!(
+                "{} is not a supported email format. Use either `html` or `plain_text`.",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EmailFormat;
+    use claim::assert_err;
+
+    #[test]
+    fn formats_round_trip_through_their_string_representation() {
+        for format in [EmailFormat::Html, EmailFormat::PlainText] {
+            let parsed: EmailFormat = format.as_str().to_string().try_into().unwrap();
+            assert_eq!(parsed, format);
+        }
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected() {
+        assert_err!(EmailFormat::try_from("markdown".to_string()));
+    }
+}
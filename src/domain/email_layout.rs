@@ -0,0 +1,250 @@
+use htmlescape::encode_minimal;
+
+/// The merge fields available in layouts and issue content,
+/// e.g. `Hello {{ name }}!`.
+pub const MERGE_FIELDS: [&str; 4] = ["name", "unsubscribe_url", "preferences_url", "archive_url"];
+
+/// The placeholder replaced by the content of the issue in a layout.
+const CONTENT_SLOT: &str = "content";
+
+/// The values of the merge fields for a recipient.
+#[derive(Debug, Clone)]
+pub struct MergeFields {
+    pub name: String,
+    pub unsubscribe_url: String,
+    pub preferences_url: String,
+    pub archive_url: String,
+}
+
+impl MergeFields {
+    /// Merge fields that render as their own placeholder, to preview a
+    /// layout without picking a recipient.
+    pub fn placeholders() -> Self {
+        Self {
+            name: "{{ name }}".into(),
+            unsubscribe_url: "{{ unsubscribe_url }}".into(),
+            preferences_url: "{{ preferences_url }}".into(),
+            archive_url: "{{ archive_url }}".into(),
+        }
+    }
+
+    fn get(&self, field: &str) -> Option<&str> {
+        match field {
+            "name" => Some(&self.name),
+            "unsubscribe_url" => Some(&self.unsubscribe_url),
+            "preferences_url" => Some(&self.preferences_url),
+            "archive_url" => Some(&self.archive_url),
+            _ => None,
+        }
+    }
+}
+
+/// The shared header and footer of issues, wrapped around their content.
+/// Both the HTML and the plain text layouts have a `{{ content }}` slot.
+#[derive(Debug, Clone)]
+pub struct EmailLayout {
+    html: String,
+    text: String,
+}
+
+impl EmailLayout {
+    /// Returns an instance of `EmailLayout` if both layouts have exactly
+    /// one content slot, only use known merge fields and give recipients
+    /// a way to unsubscribe.
+    pub fn parse(html: String, text: String) -> Result<EmailLayout, String> {
+        for (layout, kind) in [(&html, "HTML"), (&text, "plain text")] {
+            let fields = placeholders(layout);
+            if fields.iter().filter(|f| **f == CONTENT_SLOT).count() != 1 {
+                return Err(format!(
+                    "The {} layout must contain `{{{{ {} }}}}` exactly once.",
+                    kind, CONTENT_SLOT
+                ));
+            }
+            if let Some(unknown) = fields
+                .iter()
+                .find(|f| **f != CONTENT_SLOT && !MERGE_FIELDS.contains(f))
+            {
+                return Err(format!(
+                    "The {} layout uses an unknown merge field: `{{{{ {} }}}}`.",
+                    kind, unknown
+                ));
+            }
+            if !fields
+                .iter()
+                .any(|f| *f == "unsubscribe_url" || *f == "preferences_url")
+            {
+                return Err(format!(
+                    "The {} layout must link to `{{{{ unsubscribe_url }}}}` or `{{{{ preferences_url }}}}`.",
+                    kind
+                ));
+            }
+        }
+        Ok(Self { html, text })
+    }
+
+    /// The layout of issues without a template: their content between a
+    /// greeting and a link to the preference center.
+    pub fn default_layout() -> Self {
+        Self {
+            html: r#"<p>Hi {{ name }},</p>{{ content }}<p><a href="{{ preferences_url }}">Manage your subscription</a></p>"#
+                .into(),
+            text: "Hi {{ name }},\n\n{{ content }}\n\nManage your subscription: {{ preferences_url }}"
+                .into(),
+        }
+    }
+
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Fill in the merge fields of an HTML issue and wrap it in the layout.
+    /// Merge field values are escaped, the content is not.
+    pub fn render_html(&self, content: &str, fields: &MergeFields) -> String {
+        let field = |f: &str| fields.get(f).map(encode_minimal);
+        let content = render(content, field);
+        render(&self.html, |f| {
+            if f == CONTENT_SLOT {
+                Some(content.clone())
+            } else {
+                field(f)
+            }
+        })
+    }
+
+    /// Fill in the merge fields of a plain text issue and wrap it in the layout.
+    pub fn render_text(&self, content: &str, fields: &MergeFields) -> String {
+        let field = |f: &str| fields.get(f).map(|v| v.to_string());
+        let content = render(content, field);
+        render(&self.text, |f| {
+            if f == CONTENT_SLOT {
+                Some(content.clone())
+            } else {
+                field(f)
+            }
+        })
+    }
+}
+
+/// Replace every `{{ field }}` of the template with its value.
+/// Placeholders without a value are left untouched.
+pub(super) fn render(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some((before, field, after)) = next_placeholder(rest) {
+        rendered.push_str(before);
+        match value(field) {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(&rest[before.len()..rest.len() - after.len()]),
+        }
+        rest = after;
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// The names of the placeholders used in a template.
+pub(super) fn placeholders(template: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut rest = template;
+    while let Some((_, field, after)) = next_placeholder(rest) {
+        fields.push(field);
+        rest = after;
+    }
+    fields
+}
+
+/// Split `s` around its first `{{ field }}` placeholder.
+fn next_placeholder(s: &str) -> Option<(&str, &str, &str)> {
+    let start = s.find("{{")?;
+    let end = start + s[start..].find("}}")?;
+    Some((&s[..start], s[start + 2..end].trim(), &s[end + 2..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmailLayout, MergeFields};
+    use claim::{assert_err, assert_ok};
+
+    fn fields() -> MergeFields {
+        MergeFields {
+            name: "Ursula <Le Guin>".into(),
+            unsubscribe_url: "https://example.com/unsubscribe?id=1&tag=a".into(),
+            preferences_url: "https://example.com/preferences".into(),
+            archive_url: "https://example.com/issues".into(),
+        }
+    }
+
+    #[test]
+    fn layouts_need_a_content_slot() {
+        assert_err!(EmailLayout::parse(
+            "<p>{{ unsubscribe_url }}</p>".into(),
+            "{{ content }} {{ unsubscribe_url }}".into()
+        ));
+        assert_err!(EmailLayout::parse(
+            "{{ content }}{{ content }}{{ unsubscribe_url }}".into(),
+            "{{ content }} {{ unsubscribe_url }}".into()
+        ));
+    }
+
+    #[test]
+    fn layouts_need_an_unsubscribe_link() {
+        assert_err!(EmailLayout::parse(
+            "{{ content }}".into(),
+            "{{ content }} {{ unsubscribe_url }}".into()
+        ));
+        assert_ok!(EmailLayout::parse(
+            "{{content}} {{ preferences_url }}".into(),
+            "{{ content }} {{ unsubscribe_url }}".into()
+        ));
+    }
+
+    #[test]
+    fn layouts_cannot_use_unknown_merge_fields() {
+        assert_err!(EmailLayout::parse(
+            "{{ content }} {{ unsubscribe_url }} {{ nmae }}".into(),
+            "{{ content }} {{ unsubscribe_url }}".into()
+        ));
+    }
+
+    #[test]
+    fn html_merge_fields_are_escaped() {
+        let layout = EmailLayout::parse(
+            r#"<h1>Hi {{ name }}</h1>{{ content }}<a href="{{ unsubscribe_url }}">Unsubscribe</a>"#
+                .into(),
+            "{{ content }} {{ unsubscribe_url }}".into(),
+        )
+        .unwrap();
+        let html = layout.render_html(
+            "<p>Read the {{ archive_url }}, {{ unknown }}</p>",
+            &fields(),
+        );
+        assert_eq!(
+            html,
+            "<h1>Hi Ursula &lt;Le Guin&gt;</h1>\
+            <p>Read the https://example.com/issues, {{ unknown }}</p>\
+            <a href=\"https://example.com/unsubscribe?id=1&amp;tag=a\">Unsubscribe</a>"
+        );
+    }
+
+    #[test]
+    fn text_merge_fields_are_not_escaped() {
+        let layout = EmailLayout::default_layout();
+        let text = layout.render_text("See {{ archive_url }}", &fields());
+        assert_eq!(
+            text,
+            "Hi Ursula <Le Guin>,\n\nSee https://example.com/issues\n\n\
+            Manage your subscription: https://example.com/preferences"
+        );
+    }
+
+    #[test]
+    fn content_is_not_scanned_for_the_content_slot() {
+        let layout = EmailLayout::default_layout();
+        let text = layout.render_text("Write {{ content }} in layouts", &fields());
+        assert!(text.contains("Write {{ content }} in layouts"));
+    }
+}
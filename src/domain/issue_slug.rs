@@ -0,0 +1,80 @@
+/// The URL-friendly identifier of an issue in the web archive,
+/// e.g. `/issues/rust-2022-a-retrospective`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueSlug(String);
+
+const MAX_LENGTH: usize = 80;
+
+impl IssueSlug {
+    /// Derive a slug from an issue title: lowercase ASCII letters and
+    /// digits, with every other run of characters collapsed into a `-`.
+    pub fn from_title(title: &str) -> IssueSlug {
+        let mut slug = String::new();
+        for c in title.chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c.to_ascii_lowercase());
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        slug.truncate(MAX_LENGTH);
+        let slug = slug.trim_end_matches('-');
+        if slug.is_empty() {
+            Self("issue".into())
+        } else {
+            Self(slug.into())
+        }
+    }
+
+    /// A variant of the slug, used when the slug is already taken.
+    pub fn with_suffix(&self, n: u32) -> IssueSlug {
+        Self(format!("{}-{}", self.0, n))
+    }
+}
+
+impl AsRef<str> for IssueSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for IssueSlug {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IssueSlug;
+
+    #[test]
+    fn titles_are_lowercased_and_dashed() {
+        let slug = IssueSlug::from_title("Rust 2022: a retrospective!");
+        assert_eq!(slug.as_ref(), "rust-2022-a-retrospective");
+    }
+
+    #[test]
+    fn non_ascii_characters_are_dropped() {
+        let slug = IssueSlug::from_title("  Ça va? Très bien  ");
+        assert_eq!(slug.as_ref(), "a-va-tr-s-bien");
+    }
+
+    #[test]
+    fn titles_without_letters_or_digits_get_a_default_slug() {
+        assert_eq!(IssueSlug::from_title("🦀🦀").as_ref(), "issue");
+    }
+
+    #[test]
+    fn long_titles_are_truncated() {
+        let slug = IssueSlug::from_title(&"a ".repeat(100));
+        assert!(slug.as_ref().len() <= 80);
+        assert!(!slug.as_ref().ends_with('-'));
+    }
+
+    #[test]
+    fn suffixes_are_appended_with_a_dash() {
+        let slug = IssueSlug::from_title("Hello world");
+        assert_eq!(slug.with_suffix(2).as_ref(), "hello-world-2");
+    }
+}
@@ -0,0 +1,80 @@
+/// Where a newsletter issue is in its lifecycle.
+///
+/// Issues start as drafts, which can be edited and previewed at will.
+/// Once sent they are `Sending` until every queued delivery has been
+/// attempted, and `Sent` afterwards. Delivery can be paused, and cancelled
+/// altogether, while it is in progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, utoipa::ToSchema)]
+#[schema(rename_all = "snake_case")]
+pub enum IssueStatus {
+    Draft,
+    /// Waiting for its publication time to come.
+    Scheduled,
+    Sending,
+    /// Deliveries are on hold until the issue is resumed.
+    Paused,
+    Sent,
+    /// Deliveries that were still queued have been dropped.
+    Cancelled,
+}
+
+impl IssueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueStatus::Draft => "draft",
+            IssueStatus::Scheduled => "scheduled",
+            IssueStatus::Sending => "sending",
+            IssueStatus::Paused => "paused",
+            IssueStatus::Sent => "sent",
+            IssueStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl TryFrom<String> for IssueStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "draft" => Ok(Self::Draft),
+            "scheduled" => Ok(Self::Scheduled),
+            "sending" => Ok(Self::Sending),
+            "paused" => Ok(Self::Paused),
+            "sent" => Ok(Self::Sent),
+            "cancelled" => Ok(Self::Cancelled),
+            other => Err(format!("{} is not a valid issue status.", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for IssueStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IssueStatus;
+    use claim::assert_err;
+
+    #[test]
+    fn statuses_round_trip_through_their_string_representation() {
+        for status in [
+            IssueStatus::Draft,
+            IssueStatus::Scheduled,
+            IssueStatus::Sending,
+            IssueStatus::Paused,
+            IssueStatus::Sent,
+            IssueStatus::Cancelled,
+        ] {
+            let parsed: IssueStatus = status.as_str().to_string().try_into().unwrap();
+            assert_eq!(parsed, status);
+        }
+    }
+
+    #[test]
+    fn unknown_statuses_are_rejected() {
+        assert_err!(IssueStatus::try_from("archived".to_string()));
+    }
+}
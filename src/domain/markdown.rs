@@ -0,0 +1,375 @@
+use ammonia::UrlRelative;
+use pulldown_cmark::{html, Event, Parser, Tag, TagEnd};
+use std::collections::{HashMap, HashSet};
+
+/// The body of a newsletter issue, written in Markdown.
+///
+/// The HTML and plain text versions sent to subscribers are both derived
+/// from it. It is rendered as CommonMark, without extensions.
+///
+/// The generated HTML is safe to embed in emails and web pages: raw HTML in
+/// the source is escaped rather than passed through, links may only point to
+/// `http(s)` or `mailto` URLs and the result goes through a sanitiser that
+/// only keeps the tags and attributes Markdown produces.
+#[derive(Debug, Clone)]
+pub struct MarkdownContent(String);
+
+impl MarkdownContent {
+    pub fn parse(s: String) -> Result<MarkdownContent, String> {
+        if s.trim().is_empty() {
+            Err("The Markdown content is empty.".into())
+        } else {
+            Ok(Self(s))
+        }
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html::push_html(&mut html, self.events());
+        sanitizer().clean(&html).to_string().trim_end().into()
+    }
+
+    pub fn to_plain_text(&self) -> String {
+        render_text(self.events())
+    }
+
+    /// The parsed Markdown, with raw HTML turned into text, unsafe links and
+    /// images replaced by their text and quotes nested at most
+    /// `MAX_QUOTE_DEPTH` deep.
+    fn events(&self) -> impl Iterator<Item = Event<'_>> {
+        // Whether each open link or image is kept.
+        let mut kept = Vec::new();
+        let mut quote_depth = 0;
+        Parser::new(&self.0).filter_map(move |event| match event {
+            Event::Start(Tag::BlockQuote(_)) => {
+                quote_depth += 1;
+                (quote_depth <= MAX_QUOTE_DEPTH).then_some(event)
+            }
+            Event::End(TagEnd::BlockQuote(_)) => {
+                quote_depth -= 1;
+                (quote_depth < MAX_QUOTE_DEPTH).then_some(event)
+            }
+            Event::Start(Tag::HtmlBlock) => Some(Event::Start(Tag::Paragraph)),
+            Event::End(TagEnd::HtmlBlock) => Some(Event::End(TagEnd::Paragraph)),
+            Event::Html(html) | Event::InlineHtml(html) => Some(Event::Text(html)),
+            Event::Start(Tag::Link { ref dest_url, .. }) => {
+                kept.push(is_safe_link(dest_url));
+                kept.last().unwrap().then_some(event)
+            }
+            Event::Start(Tag::Image { ref dest_url, .. }) => {
+                kept.push(is_safe_image(dest_url));
+                kept.last().unwrap().then_some(event)
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => kept.pop().unwrap().then_some(event),
+            event => Some(event),
+        })
+    }
+}
+
+impl AsRef<str> for MarkdownContent {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// How deeply block quotes can be nested. Deeper quotes are merged into
+/// the innermost one kept, so that the output stays reasonably sized.
+const MAX_QUOTE_DEPTH: usize = 16;
+
+fn is_safe_link(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+}
+
+fn is_safe_image(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// Only keeps what Markdown renders to, should anything else get through.
+fn sanitizer() -> ammonia::Builder<'static> {
+    let tags = [
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "p",
+        "br",
+        "hr",
+        "strong",
+        "em",
+        "code",
+        "pre",
+        "blockquote",
+        "ul",
+        "ol",
+        "li",
+        "a",
+        "img",
+    ];
+    let attributes = HashMap::from([
+        ("a", HashSet::from(["href", "title"])),
+        ("img", HashSet::from(["src", "alt", "title"])),
+        ("ol", HashSet::from(["start"])),
+    ]);
+    let mut builder = ammonia::Builder::empty();
+    builder
+        .tags(HashSet::from(tags))
+        .tag_attributes(attributes)
+        .url_schemes(HashSet::from(["http", "https", "mailto"]))
+        .url_relative(UrlRelative::Deny)
+        .link_rel(None);
+    builder
+}
+
+/// Render the events as plain text: headings are underlined, quotes and
+/// code blocks indented, and links followed by their address.
+fn render_text<'a>(events: impl Iterator<Item = Event<'a>>) -> String {
+    // The blocks written so far in each open quote, list or list item,
+    // innermost last. Quotes are not rendered recursively, however deeply
+    // they are nested.
+    let mut containers: Vec<Vec<String>> = vec![Vec::new()];
+    let mut text = String::new();
+    // The number of the next item of each open list, `None` for bullet lists.
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    // The address of each open link, along with where its label starts.
+    let mut links: Vec<(String, usize)> = Vec::new();
+    for event in events {
+        match event {
+            Event::Text(t) | Event::Code(t) | Event::Html(t) | Event::InlineHtml(t) => {
+                text.push_str(&t)
+            }
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => push_block(&mut containers, "----------".into()),
+            Event::Start(Tag::BlockQuote(_) | Tag::Item) => {
+                end_block(&mut containers, &mut text);
+                containers.push(Vec::new());
+            }
+            Event::Start(Tag::List(start)) => {
+                end_block(&mut containers, &mut text);
+                lists.push(start);
+                containers.push(Vec::new());
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => links.push((dest_url.into(), text.len())),
+            Event::End(TagEnd::Link) => {
+                let (url, start) = links.pop().expect("Links are closed once.");
+                let address = url.strip_prefix("mailto:").unwrap_or(&url);
+                let label = &text[start..];
+                if label == url || label == address {
+                    text.replace_range(start.., address);
+                } else {
+                    text.push_str(&format!(" ({address})"));
+                }
+            }
+            Event::End(TagEnd::Heading(level)) => {
+                let heading = std::mem::take(&mut text);
+                let width = heading
+                    .lines()
+                    .map(|l| l.chars().count())
+                    .max()
+                    .unwrap_or(0);
+                let block = match level as u8 {
+                    1 => format!("{heading}\n{}", "=".repeat(width)),
+                    2 => format!("{heading}\n{}", "-".repeat(width)),
+                    _ => heading,
+                };
+                push_block(&mut containers, block);
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                let code = std::mem::take(&mut text);
+                push_block(&mut containers, indent(code.trim_end_matches('\n'), "    "));
+            }
+            Event::End(TagEnd::Paragraph) => end_block(&mut containers, &mut text),
+            Event::End(TagEnd::BlockQuote(_)) => {
+                end_block(&mut containers, &mut text);
+                let quote = containers.pop().expect("Quotes are closed once.");
+                push_block(&mut containers, indent(&quote.join("\n\n"), "> "));
+            }
+            Event::End(TagEnd::Item) => {
+                end_block(&mut containers, &mut text);
+                let item = containers.pop().expect("Items are closed once.");
+                let marker = match lists.last_mut().expect("Items are in a list.") {
+                    None => "-".to_string(),
+                    Some(n) => {
+                        *n += 1;
+                        format!("{}.", *n - 1)
+                    }
+                };
+                let item = indent(&item.join("\n\n"), &" ".repeat(marker.len() + 1));
+                push_block(&mut containers, format!("{marker} {}", item.trim_start()));
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                let items = containers.pop().expect("Lists are closed once.");
+                push_block(&mut containers, items.join("\n"));
+            }
+            _ => {}
+        }
+    }
+    end_block(&mut containers, &mut text);
+    containers.concat().join("\n\n")
+}
+
+fn push_block(containers: &mut [Vec<String>], block: String) {
+    containers
+        .last_mut()
+        .expect("The document is never closed.")
+        .push(block);
+}
+
+/// Turn the text written so far into a block of the innermost container.
+fn end_block(containers: &mut [Vec<String>], text: &mut String) {
+    let block = std::mem::take(text);
+    let block = block.trim_end_matches('\n');
+    if !block.is_empty() {
+        push_block(containers, block.into());
+    }
+}
+
+fn indent(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| format!("{prefix}{line}").trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MarkdownContent;
+    use claim::assert_err;
+
+    fn markdown(s: &str) -> MarkdownContent {
+        MarkdownContent::parse(s.to_string()).unwrap()
+    }
+
+    #[test]
+    fn blank_content_is_rejected() {
+        assert_err!(MarkdownContent::parse(" \n\n ".to_string()));
+    }
+
+    #[test]
+    fn headings_and_paragraphs_are_rendered() {
+        let content = markdown("# Hello\n\nFirst line\nsecond line\n\n## Details");
+        assert_eq!(
+            content.to_html(),
+            "<h1>Hello</h1>\n<p>First line\nsecond line</p>\n<h2>Details</h2>"
+        );
+        assert_eq!(
+            content.to_plain_text(),
+            "Hello\n=====\n\nFirst line\nsecond line\n\nDetails\n-------"
+        );
+    }
+
+    #[test]
+    fn inline_markup_is_rendered() {
+        let content = markdown("Some **bold**, *italic* and `code` in snake_case_words.");
+        assert_eq!(
+            content.to_html(),
+            "<p>Some <strong>bold</strong>, <em>italic</em> and <code>code</code> in snake_case_words.</p>"
+        );
+        assert_eq!(
+            content.to_plain_text(),
+            "Some bold, italic and code in snake_case_words."
+        );
+    }
+
+    #[test]
+    fn links_keep_their_address_in_plain_text() {
+        let content =
+            markdown("Read [the book](https://zero2prod.com) or <mailto:me@example.com>.");
+        assert_eq!(
+            content.to_html(),
+            "<p>Read <a href=\"https://zero2prod.com\">the book</a> or \
+            <a href=\"mailto:me@example.com\">mailto:me@example.com</a>.</p>"
+        );
+        assert_eq!(
+            content.to_plain_text(),
+            "Read the book (https://zero2prod.com) or me@example.com."
+        );
+    }
+
+    #[test]
+    fn raw_html_is_escaped() {
+        let content = markdown("<script>alert('pwned')</script>\n\n<b>bold</b> & co");
+        assert_eq!(
+            content.to_html(),
+            "<p>&lt;script&gt;alert('pwned')&lt;/script&gt;\n</p>\n\
+            <p>&lt;b&gt;bold&lt;/b&gt; &amp; co</p>"
+        );
+    }
+
+    #[test]
+    fn quotes_cannot_break_out_of_attributes() {
+        let content = markdown("[x](https://example.com/\"onmouseover=\"alert(1))");
+        assert_eq!(
+            content.to_html(),
+            "<p><a href=\"https://example.com/%22onmouseover=%22alert(1)\">x</a></p>"
+        );
+    }
+
+    #[test]
+    fn unsafe_links_and_images_are_dropped() {
+        let content = markdown("[click me](javascript:void) ![pixel](data:image/png;base64,AAAA)");
+        assert_eq!(content.to_html(), "<p>click me pixel</p>");
+    }
+
+    #[test]
+    fn lists_are_rendered() {
+        let content = markdown("- one\n- two\n  continued\n\n3. three\n4. four");
+        assert_eq!(
+            content.to_html(),
+            "<ul>\n<li>one</li>\n<li>two\ncontinued</li>\n</ul>\n\
+            <ol start=\"3\">\n<li>three</li>\n<li>four</li>\n</ol>"
+        );
+        assert_eq!(
+            content.to_plain_text(),
+            "- one\n- two\n  continued\n\n3. three\n4. four"
+        );
+    }
+
+    #[test]
+    fn code_blocks_are_not_interpreted() {
+        let content = markdown("```rust\nlet x = *y;\n# not a heading\n```");
+        assert_eq!(
+            content.to_html(),
+            "<pre><code>let x = *y;\n# not a heading\n</code></pre>"
+        );
+        assert_eq!(
+            content.to_plain_text(),
+            "    let x = *y;\n    # not a heading"
+        );
+    }
+
+    #[test]
+    fn quotes_and_rules_are_rendered() {
+        let content = markdown("> Quoted *text*\n> on two lines\n\n---");
+        assert_eq!(
+            content.to_html(),
+            "<blockquote>\n<p>Quoted <em>text</em>\non two lines</p>\n</blockquote>\n<hr>"
+        );
+        assert_eq!(
+            content.to_plain_text(),
+            "> Quoted text\n> on two lines\n\n----------"
+        );
+    }
+
+    #[test]
+    fn deeply_nested_quotes_do_not_overflow_the_stack() {
+        let content = markdown(&format!("{} hi", ">".repeat(16_000)));
+        let html = content.to_html();
+        assert_eq!(html.matches("<blockquote>").count(), 16);
+        assert!(html.contains("<p>hi</p>"));
+        let text = content.to_plain_text();
+        assert_eq!(text, format!("{}hi", "> ".repeat(16)));
+    }
+
+    #[test]
+    fn unclosed_markup_is_rendered_in_linear_time() {
+        let content = markdown(&"<".repeat(100_000));
+        assert!(content.to_html().starts_with("<p>&lt;&lt;&lt;"));
+    }
+}
@@ -1,8 +1,23 @@
 mod subscriber_name;
 mod subscriber_email;
+mod confirmation_email;
+mod email_format;
+mod email_layout;
+mod issue_slug;
+mod issue_status;
+mod markdown;
+mod subscriber_tag;
+mod segment;
 mod new_subscriber;
 
 pub use subscriber_name::SubscriberName;
 pub use new_subscriber::NewSubscriber;
 pub use subscriber_email::SubscriberEmail;
-
+pub use confirmation_email::{ConfirmationEmail, CONFIRMATION_FIELDS};
+pub use email_format::EmailFormat;
+pub use email_layout::{EmailLayout, MergeFields, MERGE_FIELDS};
+pub use issue_slug::IssueSlug;
+pub use issue_status::IssueStatus;
+pub use markdown::MarkdownContent;
+pub use subscriber_tag::SubscriberTag;
+pub use segment::Segment;
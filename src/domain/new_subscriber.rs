@@ -1,8 +1,10 @@
 use crate::domain::subscriber_name::SubscriberName;
 use crate::domain::subscriber_email::SubscriberEmail;
+use crate::domain::subscriber_tag::SubscriberTag;
 
 
 pub struct NewSubscriber {
 	pub email: SubscriberEmail,
-	pub name: SubscriberName
-}
\ No newline at end of file
+	pub name: SubscriberName,
+	pub tags: Vec<SubscriberTag>,
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+use crate::domain::SubscriberTag;
+
+/// The subset of confirmed subscribers a newsletter issue is delivered to.
+///
+/// A subscriber belongs to the segment if they carry at least one of the
+/// `include_tags` (or `include_tags` is empty), none of the `exclude_tags`
+/// and signed up within `[subscribed_after, subscribed_before)`.
+#[derive(Debug, Default)]
+pub struct Segment {
+    pub include_tags: Vec<SubscriberTag>,
+    pub exclude_tags: Vec<SubscriberTag>,
+    pub subscribed_after: Option<DateTime<Utc>>,
+    pub subscribed_before: Option<DateTime<Utc>>,
+}
+
+impl Segment {
+    /// Build a segment out of raw form fields.
+    /// Tags are comma-separated lists, dates use the `YYYY-MM-DD` format
+    /// and blank fields mean "no filter".
+    pub fn parse(
+        include_tags: &str,
+        exclude_tags: &str,
+        subscribed_after: &str,
+        subscribed_before: &str,
+    ) -> Result<Segment, String> {
+        let segment = Self {
+            include_tags: SubscriberTag::parse_list(include_tags)?,
+            exclude_tags: SubscriberTag::parse_list(exclude_tags)?,
+            subscribed_after: parse_date(subscribed_after)?,
+            subscribed_before: parse_date(subscribed_before)?,
+        };
+        if segment
+            .include_tags
+            .iter()
+            .any(|t| segment.exclude_tags.contains(t))
+        {
+            return Err("A tag cannot be both included and excluded.".into());
+        }
+        if let (Some(after), Some(before)) = (segment.subscribed_after, segment.subscribed_before) {
+            if after >= before {
+                return Err("The signup date range is empty.".into());
+            }
+        }
+        Ok(segment)
+    }
+
+    pub fn include_tags(&self) -> Vec<String> {
+        self.include_tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    pub fn exclude_tags(&self) -> Vec<String> {
+        self.exclude_tags.iter().map(|t| t.to_string()).collect()
+    }
+}
+
+fn parse_date(s: &str) -> Result<Option<DateTime<Utc>>, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(None);
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| format!("{} is not a valid date. Use the YYYY-MM-DD format.", s))?;
+    let midnight = date
+        .and_hms_opt(0, 0, 0)
+        .expect("Midnight is a valid time.");
+    Ok(Some(Utc.from_utc_datetime(&midnight)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Segment;
+    use claim::{assert_err, assert_none, assert_ok};
+
+    #[test]
+    fn blank_fields_match_every_subscriber() {
+        let segment = Segment::parse("", "", "", "").unwrap();
+        assert!(segment.include_tags.is_empty());
+        assert!(segment.exclude_tags.is_empty());
+        assert_none!(segment.subscribed_after);
+        assert_none!(segment.subscribed_before);
+    }
+
+    #[test]
+    fn a_tag_cannot_be_both_included_and_excluded() {
+        assert_err!(Segment::parse("rust, go", "Go", "", ""));
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        assert_err!(Segment::parse("", "", "18/10/2022", ""));
+    }
+
+    #[test]
+    fn an_empty_date_range_is_rejected() {
+        assert_err!(Segment::parse("", "", "2022-10-18", "2022-10-18"));
+        assert_ok!(Segment::parse("", "", "2022-10-18", "2022-10-19"));
+    }
+}
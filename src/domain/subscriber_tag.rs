@@ -0,0 +1,87 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriberTag(String);
+
+impl SubscriberTag {
+    /// Returns an instance of `SubscriberTag` if the input is a valid tag.
+    /// Tags are case-insensitive, so they are stored lowercased.
+    pub fn parse(s: String) -> Result<SubscriberTag, String> {
+        let tag = s.trim().to_lowercase();
+        let is_too_long = tag.chars().count() > 64;
+        let has_invalid_characters = tag
+            .chars()
+            .any(|c| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        if tag.is_empty() || is_too_long || has_invalid_characters {
+            Err(format!(
+                "{} is not a valid tag. Use up to 64 letters, digits, `-` or `_`.",
+                s
+            ))
+        } else {
+            Ok(Self(tag))
+        }
+    }
+
+    /// Parse a comma-separated list of tags, e.g. `"rust, beta-readers"`.
+    /// Blank entries are ignored and duplicates are removed.
+    pub fn parse_list(s: &str) -> Result<Vec<SubscriberTag>, String> {
+        let mut tags: Vec<SubscriberTag> = Vec::new();
+        for candidate in s.split(',').filter(|c| !c.trim().is_empty()) {
+            let tag = SubscriberTag::parse(candidate.to_string())?;
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        Ok(tags)
+    }
+}
+
+impl AsRef<str> for SubscriberTag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for SubscriberTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubscriberTag;
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn empty_string_is_rejected() {
+        assert_err!(SubscriberTag::parse("".to_string()));
+    }
+
+    #[test]
+    fn tags_with_spaces_inside_are_rejected() {
+        assert_err!(SubscriberTag::parse("beta readers".to_string()));
+    }
+
+    #[test]
+    fn a_65_characters_long_tag_is_rejected() {
+        assert_err!(SubscriberTag::parse("a".repeat(65)));
+    }
+
+    #[test]
+    fn tags_are_trimmed_and_lowercased() {
+        let tag = SubscriberTag::parse("  Beta-Readers_2 ".to_string()).unwrap();
+        assert_eq!(tag.as_ref(), "beta-readers_2");
+    }
+
+    #[test]
+    fn a_list_of_tags_is_parsed_and_deduplicated() {
+        let tags = SubscriberTag::parse_list("rust, Rust,, beta ,").unwrap();
+        let tags: Vec<&str> = tags.iter().map(|t| t.as_ref()).collect();
+        assert_eq!(tags, vec!["rust", "beta"]);
+    }
+
+    #[test]
+    fn a_list_with_an_invalid_tag_is_rejected() {
+        assert_err!(SubscriberTag::parse_list("rust, not valid"));
+        assert_ok!(SubscriberTag::parse_list(""));
+    }
+}
@@ -35,6 +35,28 @@ impl EmailClient {
         subject: &str,
         html_content: &str,
         text_content: &str,
+    ) -> Result<(), reqwest::Error> {
+        self.send(recipient, subject, Some(html_content), text_content)
+            .await
+    }
+
+    /// Send an email without an HTML part, for subscribers who
+    /// asked for plain text only.
+    pub async fn send_plain_text_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        text_content: &str,
+    ) -> Result<(), reqwest::Error> {
+        self.send(recipient, subject, None, text_content).await
+    }
+
+    async fn send(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: Option<&str>,
+        text_content: &str,
     ) -> Result<(), reqwest::Error> {
         let url = format!("{}/email", self.base_url);
         let request_body = SendEmailRequest {
@@ -64,7 +86,8 @@ struct SendEmailRequest<'a> {
     from: &'a str,
     to: &'a str,
     subject: &'a str,
-    html_body: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html_body: Option<&'a str>,
     text_body: &'a str,
 }
 
@@ -155,6 +178,32 @@ mod tests {
         // Assert
     }
 
+    #[tokio::test]
+    async fn send_plain_text_email_does_not_send_an_html_body() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_plain_text_email(&email(), &subject(), &content())
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert!(body.get("HtmlBody").is_none());
+        assert!(body.get("TextBody").is_some());
+    }
+
     #[tokio::test]
     async fn send_email_successds_if_the_server_returns_200() {
         // Arrange
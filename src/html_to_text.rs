@@ -0,0 +1,97 @@
+//! Plain text versions of HTML emails, for issues whose author only wrote
+//! the HTML content.
+//!
+//! The conversion is left to `html2text`: paragraphs, headings, lists, quotes
+//! and preformatted blocks keep their shape, links are turned into numbered
+//! footnotes and everything that is not meant to be read (`<script>`,
+//! `<style>`, `<head>`...) is dropped.
+
+/// Convert an HTML document or fragment into readable plain text.
+///
+/// Lines are only broken where the HTML breaks them: mail clients wrap plain
+/// text themselves.
+pub fn html_to_text(html: &str) -> String {
+    html2text::config::plain()
+        .allow_width_overflow()
+        .string_from_read(html.as_bytes(), usize::MAX)
+        .expect("Rendering can only fail if lines cannot overflow their width.")
+        .trim_end()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::html_to_text;
+
+    #[test]
+    fn paragraphs_are_separated_by_blank_lines() {
+        let text = html_to_text("<p>Hello,\n   world!</p><p>Second<br>paragraph</p>");
+        assert_eq!(text, "Hello, world!\n\nSecond\nparagraph");
+    }
+
+    #[test]
+    fn headings_are_marked() {
+        let text = html_to_text("<h1>Title</h1><h2>Section</h2><h3>Details</h3>");
+        assert_eq!(text, "# Title\n\n## Section\n\n### Details");
+    }
+
+    #[test]
+    fn links_become_footnotes() {
+        let text = html_to_text(
+            r#"<p>Read <a href="https://zero2prod.com">the book</a>, <a href="mailto:me@example.com">me@example.com</a> or <a href="/issues">the archive</a>.</p>"#,
+        );
+        assert_eq!(
+            text,
+            "Read [the book][1], [me@example.com][2] or [the archive][3].\n\n\
+            [1]: https://zero2prod.com\n[2]: mailto:me@example.com\n[3]: /issues"
+        );
+    }
+
+    #[test]
+    fn lists_are_preserved() {
+        let text = html_to_text(
+            "<p>Agenda:</p><ul><li>Intro</li><li>Talks<ol start=\"3\"><li>Rust</li><li>Go</li></ol></li></ul><p>See you!</p>",
+        );
+        assert_eq!(
+            text,
+            "Agenda:\n* Intro\n* Talks\n  3. Rust\n  4. Go\n\nSee you!"
+        );
+    }
+
+    #[test]
+    fn entities_are_decoded() {
+        let text = html_to_text("<p>Fish &amp; chips &lt;3</p>");
+        assert_eq!(text, "Fish & chips <3");
+    }
+
+    #[test]
+    fn invisible_content_is_dropped() {
+        let text = html_to_text(
+            "<html><head><title>Issue</title><style>p { color: red; }</style></head>\
+            <body><!-- tracking --><script>alert(1)</script><p>Visible</p></body></html>",
+        );
+        assert_eq!(text, "Visible");
+    }
+
+    #[test]
+    fn quotes_and_preformatted_text_keep_their_shape() {
+        let text = html_to_text(
+            "<blockquote><p>Quoted</p><p>twice</p></blockquote><pre>fn main() {\n    todo!()\n}</pre>",
+        );
+        assert_eq!(text, "> Quoted\n> \n> twice\n\nfn main() {\n    todo!()\n}");
+    }
+
+    #[test]
+    fn non_ascii_text_is_kept() {
+        let text = html_to_text("<p>Élan vital</p><p>«Ça va ?» 🦀 < 3</p>");
+        assert_eq!(text, "Élan vital\n\n«Ça va ?» 🦀 < 3");
+    }
+
+    #[test]
+    fn markup_inside_attributes_is_not_parsed() {
+        let text = html_to_text(
+            r#"<p title="a > b"><img alt="</script>" src="https://example.com/x.png">Hello</p>"#,
+        );
+        assert_eq!(text, "[</script>]Hello");
+    }
+}
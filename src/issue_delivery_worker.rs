@@ -1,4 +1,7 @@
-use crate::{configuration::Settings, startup::get_connection_pool};
+use crate::domain::{EmailLayout, MergeFields};
+use crate::routes::{preferences_link, unsubscribe_link};
+use crate::startup::{get_connection_pool, HmacSecret};
+use crate::{configuration::Settings, domain::EmailFormat};
 use crate::{domain::SubscriberEmail, email_client::EmailClient};
 use sqlx::{PgPool, Postgres, Transaction};
 use std::time::Duration;
@@ -8,12 +11,19 @@ use uuid::Uuid;
 pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
     let connection_pool = get_connection_pool(&configuration.database);
     let email_client = configuration.email_client.client();
-    worker_loop(connection_pool, email_client).await
+    let base_url = configuration.application.base_url;
+    let hmac_secret = HmacSecret(configuration.application.hmac_secret);
+    worker_loop(connection_pool, email_client, base_url, hmac_secret).await
 }
 
-async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyhow::Error> {
+async fn worker_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    base_url: String,
+    hmac_secret: HmacSecret,
+) -> Result<(), anyhow::Error> {
     loop {
-        match try_execute_task(&pool, &email_client).await {
+        match try_execute_task(&pool, &email_client, &base_url, &hmac_secret).await {
             Ok(ExecutionOutcome::EmptyQueue) => {
                 tokio::time::sleep(Duration::from_secs(10)).await;
             }
@@ -34,6 +44,7 @@ pub enum ExecutionOutcome {
     skip_all,
     fields(
         newsletter_issue_id=tracing::field::Empty,
+        subscriber_id=tracing::field::Empty,
         subscriber_email=tracing::field::Empty
     ),
     err
@@ -41,33 +52,54 @@ pub enum ExecutionOutcome {
 pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
+    base_url: &str,
+    hmac_secret: &HmacSecret,
 ) -> Result<ExecutionOutcome, anyhow::Error> {
     let task = dequeue_task(pool).await?;
     if task.is_none() {
         return Ok(ExecutionOutcome::EmptyQueue);
     }
-    let (transaction, issue_id, email) = task.unwrap();
+    let (mut transaction, task) = task.unwrap();
+    let issue_id = task.newsletter_issue_id;
+    let email = task.subscriber_email;
     Span::current()
-        .record("newsletter_issue_id", &display(issue_id))
-        .record("subscriber_email", &display(&email));
-    match SubscriberEmail::parse(email.clone()) {
+        .record("newsletter_issue_id", display(issue_id))
+        .record("subscriber_id", display(task.subscriber_id))
+        .record("subscriber_email", display(&email));
+    let (outcome, error) = match SubscriberEmail::parse(email.clone()) {
         Ok(email) => {
             let issue = get_issue(pool, issue_id).await?;
-            if let Err(e) = email_client
-                .send_email(
-                    &email,
-                    &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
-                )
-                .await
-            {
-                tracing::error!(
-                    error.cause_chain = ?e,
-                    error.message = %e,
-                    "Failed to deliver issue to a confirmed subscriber. \
-                        Skipping.",
-                );
+            let recipient = get_recipient(pool, task.subscriber_id).await?;
+            let (html_content, text_content) = personalise(
+                &issue.layout,
+                (&issue.html_content, &issue.text_content),
+                &recipient,
+                base_url,
+                hmac_secret,
+            );
+            let outcome = match recipient.email_format {
+                EmailFormat::Html => {
+                    email_client
+                        .send_email(&email, &issue.title, &html_content, &text_content)
+                        .await
+                }
+                EmailFormat::PlainText => {
+                    email_client
+                        .send_plain_text_email(&email, &issue.title, &text_content)
+                        .await
+                }
+            };
+            match outcome {
+                Ok(()) => (DeliveryOutcome::Sent, None),
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to deliver issue to a confirmed subscriber. \
+                            Skipping.",
+                    );
+                    (DeliveryOutcome::Failed, Some(e.to_string()))
+                }
             }
         }
         Err(e) => {
@@ -77,39 +109,96 @@ pub async fn try_execute_task(
                 "Skipping a confirmed subscriber. \
                     Their stored contact details are invalid",
             );
+            (DeliveryOutcome::SkippedInvalidEmail, Some(e))
         }
-    }
+    };
+    log_delivery(
+        &mut transaction,
+        issue_id,
+        &email,
+        outcome,
+        error.as_deref(),
+    )
+    .await?;
     delete_task(transaction, issue_id, &email).await?;
     Ok(ExecutionOutcome::TaskCompleted)
 }
 
 type PgTransaction = Transaction<'static, Postgres>;
 
+#[derive(Debug, Clone, Copy)]
+enum DeliveryOutcome {
+    Sent,
+    Failed,
+    SkippedInvalidEmail,
+}
+
+impl DeliveryOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryOutcome::Sent => "sent",
+            DeliveryOutcome::Failed => "failed",
+            DeliveryOutcome::SkippedInvalidEmail => "skipped_invalid_email",
+        }
+    }
+}
+
+/// Record what happened to a delivery, so that admins can follow
+/// the progress of an issue.
+#[tracing::instrument(skip(transaction, error))]
+async fn log_delivery(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+    email: &str,
+    outcome: DeliveryOutcome,
+    error: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_log (
+            newsletter_issue_id,
+            subscriber_email,
+            outcome,
+            error
+        )
+        VALUES ($1, $2, $3, $4)
+        "#,
+        issue_id,
+        email,
+        outcome.as_str(),
+        error
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+struct DeliveryTask {
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    subscriber_email: String,
+}
+
 #[tracing::instrument(skip_all)]
 async fn dequeue_task(
     pool: &PgPool,
-) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
+) -> Result<Option<(PgTransaction, DeliveryTask)>, anyhow::Error> {
     let mut transaction = pool.begin().await?;
-    let r = sqlx::query!(
+    let r = sqlx::query_as!(
+        DeliveryTask,
         r#"
-        SELECT newsletter_issue_id, subscriber_email
-        FROM issue_delivery_queue
-        FOR UPDATE
+        SELECT q.newsletter_issue_id, q.subscriber_id, q.subscriber_email
+        FROM issue_delivery_queue q
+        JOIN newsletter_issues i ON i.newsletter_issue_id = q.newsletter_issue_id
+        WHERE i.status = 'sending'
+        FOR UPDATE OF q
         SKIP LOCKED
         LIMIT 1
         "#,
     )
     .fetch_optional(&mut transaction)
     .await?;
-    if let Some(r) = r {
-        Ok(Some((
-            transaction,
-            r.newsletter_issue_id,
-            r.subscriber_email,
-        )))
-    } else {
-        Ok(None)
-    }
+    Ok(r.map(|task| (transaction, task)))
 }
 
 #[tracing::instrument(skip_all)]
@@ -130,6 +219,36 @@ async fn delete_task(
     )
     .execute(&mut transaction)
     .await?;
+    // The last delivery of an issue completes it.
+    // Workers finishing deliveries of the same issue take turns, so that
+    // each of them sees the others' deletions.
+    sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        FOR UPDATE
+        "#,
+        issue_id
+    )
+    .fetch_one(&mut transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'sent'
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = 'sending' AND
+            NOT EXISTS (
+                SELECT 1 FROM issue_delivery_queue
+                WHERE newsletter_issue_id = $1
+            )
+        "#,
+        issue_id
+    )
+    .execute(&mut transaction)
+    .await?;
     transaction.commit().await?;
     Ok(())
 }
@@ -138,21 +257,167 @@ struct NewsletterIssue {
     title: String,
     text_content: String,
     html_content: String,
+    layout: EmailLayout,
 }
 
 #[tracing::instrument(skip_all)]
 async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, anyhow::Error> {
-    let issue = sqlx::query_as!(
-        NewsletterIssue,
+    let r = sqlx::query!(
         r#"
-        SELECT title, text_content, html_content
-        FROM newsletter_issues
+        SELECT
+            i.title,
+            i.text_content,
+            i.html_content,
+            t.html_layout AS "html_layout?",
+            t.text_layout AS "text_layout?"
+        FROM newsletter_issues i
+        LEFT JOIN email_templates t ON t.template_id = i.template_id
         WHERE
-            newsletter_issue_id = $1
+            i.newsletter_issue_id = $1
         "#,
         issue_id
     )
     .fetch_one(pool)
     .await?;
-    Ok(issue)
+    let layout = match (r.html_layout, r.text_layout) {
+        (Some(html), Some(text)) => EmailLayout::parse(html, text).map_err(anyhow::Error::msg)?,
+        _ => EmailLayout::default_layout(),
+    };
+    Ok(NewsletterIssue {
+        title: r.title,
+        text_content: r.text_content,
+        html_content: r.html_content,
+        layout,
+    })
+}
+
+pub(crate) struct Recipient {
+    pub(crate) subscriber_id: Uuid,
+    pub(crate) name: String,
+    pub(crate) email_format: EmailFormat,
+}
+
+/// Load the subscriber's details as they are now: they may have changed
+/// their name or preferences since the issue was published.
+#[tracing::instrument(skip(pool))]
+pub(crate) async fn get_recipient(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Recipient, anyhow::Error> {
+    let r = sqlx::query!(
+        r#"
+        SELECT name, email_format
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(Recipient {
+        subscriber_id,
+        name: r.name,
+        email_format: r.email_format.try_into().map_err(anyhow::Error::msg)?,
+    })
+}
+
+/// The HTML and plain text versions of an email for a recipient: their
+/// name and their own signed unsubscribe and preference links fill in the
+/// merge fields of the content and of its layout.
+pub(crate) fn personalise(
+    layout: &EmailLayout,
+    (html_content, text_content): (&str, &str),
+    recipient: &Recipient,
+    base_url: &str,
+    hmac_secret: &HmacSecret,
+) -> (String, String) {
+    let fields = MergeFields {
+        name: recipient.name.clone(),
+        unsubscribe_url: unsubscribe_link(base_url, recipient.subscriber_id, hmac_secret),
+        preferences_url: preferences_link(base_url, recipient.subscriber_id, hmac_secret),
+        archive_url: format!("{}/issues", base_url),
+    };
+    (
+        layout.render_html(html_content, &fields),
+        layout.render_text(text_content, &fields),
+    )
+}
+
+/// Publish a draft or scheduled issue: mark it as `sending` and queue its
+/// deliveries. Returns `false`, leaving the issue untouched, if it is
+/// neither - e.g. because it has already been sent.
+#[tracing::instrument(skip(transaction))]
+pub async fn start_delivery(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let published = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'sending', published_at = now()
+        WHERE newsletter_issue_id = $1 AND status IN ('draft', 'scheduled')
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(&mut *transaction)
+    .await?;
+    if published.rows_affected() == 0 {
+        return Ok(false);
+    }
+    let n_tasks = enqueue_delivery_tasks(&mut *transaction, newsletter_issue_id).await?;
+    // Nobody is in the segment: there is nothing left for the worker to do.
+    if n_tasks == 0 {
+        sqlx::query!(
+            r#"
+            UPDATE newsletter_issues
+            SET status = 'sent'
+            WHERE newsletter_issue_id = $1
+            "#,
+            newsletter_issue_id,
+        )
+        .execute(transaction)
+        .await?;
+    }
+    Ok(true)
+}
+
+/// Queue one delivery task per confirmed subscriber in the issue's segment.
+/// The segment is resolved here, i.e. against the subscribers we have
+/// at the time the issue is published.
+#[tracing::instrument(skip_all)]
+pub async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id, 
+            subscriber_id,
+            subscriber_email
+        )
+        SELECT i.newsletter_issue_id, s.id, s.email
+        FROM newsletter_issues i
+        JOIN subscriptions s ON s.status = 'confirmed'
+        WHERE
+            i.newsletter_issue_id = $1 AND
+            (
+                cardinality(i.segment_include_tags) = 0 OR
+                EXISTS (
+                    SELECT 1 FROM subscriber_tags t
+                    WHERE t.subscriber_id = s.id AND t.tag = ANY(i.segment_include_tags)
+                )
+            ) AND
+            NOT EXISTS (
+                SELECT 1 FROM subscriber_tags t
+                WHERE t.subscriber_id = s.id AND t.tag = ANY(i.segment_exclude_tags)
+            ) AND
+            (i.segment_subscribed_after IS NULL OR s.subscribed_at >= i.segment_subscribed_after) AND
+            (i.segment_subscribed_before IS NULL OR s.subscribed_at < i.segment_subscribed_before)
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(transaction)
+    .await?;
+    Ok(result.rows_affected())
 }
@@ -0,0 +1,60 @@
+use crate::issue_delivery_worker::start_delivery;
+use crate::{configuration::Settings, startup::get_connection_pool};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{field::display, Span};
+
+pub async fn run_scheduler_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    scheduler_loop(connection_pool).await
+}
+
+async fn scheduler_loop(pool: PgPool) -> Result<(), anyhow::Error> {
+    loop {
+        match try_publish_scheduled_issue(&pool).await {
+            Ok(SchedulerOutcome::NothingDue) => {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(SchedulerOutcome::IssuePublished) => {}
+        }
+    }
+}
+
+pub enum SchedulerOutcome {
+    IssuePublished,
+    NothingDue,
+}
+
+/// Publish one scheduled issue whose time has come, if any.
+#[tracing::instrument(
+    skip_all,
+    fields(newsletter_issue_id=tracing::field::Empty),
+    err
+)]
+pub async fn try_publish_scheduled_issue(pool: &PgPool) -> Result<SchedulerOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let r = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id
+        FROM newsletter_issues
+        WHERE status = 'scheduled' AND scheduled_for <= now()
+        ORDER BY scheduled_for
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(&mut transaction)
+    .await?;
+    let issue_id = match r {
+        Some(r) => r.newsletter_issue_id,
+        None => return Ok(SchedulerOutcome::NothingDue),
+    };
+    Span::current().record("newsletter_issue_id", display(issue_id));
+    start_delivery(&mut transaction, issue_id).await?;
+    transaction.commit().await?;
+    Ok(SchedulerOutcome::IssuePublished)
+}
@@ -2,6 +2,7 @@ pub mod authentication;
 pub mod configuration;
 pub mod domain;
 pub mod email_client;
+pub mod html_to_text;
 pub mod idempotency;
 pub mod routes;
 pub mod session_state;
@@ -9,4 +10,6 @@ pub mod startup;
 pub mod telemetry;
 pub mod utils;
 pub mod issue_delivery_worker;
+pub mod issue_scheduler;
+pub mod welcome_email_worker;
 
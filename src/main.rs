@@ -3,8 +3,10 @@ use std::fmt::Display;
 use tokio::task::JoinError;
 use zero2prod::configuration::get_configuration;
 use zero2prod::issue_delivery_worker::run_worker_until_stopped;
+use zero2prod::issue_scheduler::run_scheduler_until_stopped;
 use zero2prod::startup::Application;
 use zero2prod::telemetry;
+use zero2prod::welcome_email_worker::run_welcome_worker_until_stopped;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -15,10 +17,14 @@ async fn main() -> anyhow::Result<()> {
     let conf = get_configuration().expect("Failed to read configuration.");
     let application = Application::build(conf.clone()).await?;
     let application_task = tokio::spawn(application.run_until_stopped());
+    let scheduler_task = tokio::spawn(run_scheduler_until_stopped(conf.clone()));
+    let welcome_task = tokio::spawn(run_welcome_worker_until_stopped(conf.clone()));
     let worker_task = tokio::spawn(run_worker_until_stopped(conf));
     tokio::select! {
         o = application_task => report_exit("API", o),
         o = worker_task =>  report_exit("Background worker", o),
+        o = scheduler_task => report_exit("Issue scheduler", o),
+        o = welcome_task => report_exit("Welcome sequence", o),
     };
     Ok(())
 }
@@ -0,0 +1,117 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use htmlescape::encode_attribute;
+use secrecy::Secret;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{validate_credentials, AuthError, Credentials, CsrfToken, UserId};
+use crate::domain::SubscriberEmail;
+use crate::routes::get_username;
+use crate::utils::{account_email_page, e500};
+
+/// The address password reset links are sent to.
+pub async fn account_email_form(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let email = sqlx::query!(
+        "SELECT email FROM users WHERE user_id = $1",
+        *user_id.into_inner()
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .context("Failed to retrieve the email address of the user.")
+    .map_err(e500)?
+    .email
+    .unwrap_or_default();
+    let email = encode_attribute(&email);
+    let csrf_field = csrf_token.form_field();
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Account email</title>
+</head>
+<body>
+    {msg_html}
+    <p>If you forget your password, we will send a link to reset it to this address.</p>
+    <form action="/admin/email" method="post">
+        {csrf_field}
+        <label>Email
+            <input type="email" placeholder="Enter your email address" name="email" value="{email}">
+        </label>
+        <br>
+        <label>Current password
+            <input type="password" placeholder="Enter your current password" name="current_password">
+        </label>
+        <button type="submit">Save</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+#[derive(serde::Deserialize)]
+pub struct AccountEmailFormData {
+    email: String,
+    current_password: Secret<String>,
+}
+
+/// The address receives password reset links, so changing it takes the
+/// current password: a stolen session alone must not be enough to take
+/// the account over.
+#[tracing::instrument(skip(form, pool))]
+pub async fn change_account_email(
+    form: web::Form<AccountEmailFormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // An empty address turns password resets off.
+    let email = match form.0.email.trim() {
+        "" => None,
+        email => match SubscriberEmail::parse(email.to_string()) {
+            Ok(email) => Some(email),
+            Err(e) => {
+                FlashMessage::error(e).send();
+                return Ok(account_email_page());
+            }
+        },
+    };
+    let user_id = user_id.into_inner();
+    let credentials = Credentials {
+        username: get_username(*user_id, &pool).await.map_err(e500)?,
+        password: form.0.current_password,
+    };
+    if let Err(e) = validate_credentials(credentials, &pool).await {
+        return match e {
+            AuthError::InvalidCredentials(_) => {
+                FlashMessage::error("The current password is incorrect.").send();
+                Ok(account_email_page())
+            }
+            AuthError::UnexpectedError(_) => Err(e500(e)),
+        };
+    }
+    sqlx::query!(
+        "UPDATE users SET email = $1 WHERE user_id = $2",
+        email.as_ref().map(|e| e.as_ref()),
+        *user_id
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to update the email address of the user.")
+    .map_err(e500)?;
+    FlashMessage::info("Your email address has been saved.").send();
+    Ok(account_email_page())
+}
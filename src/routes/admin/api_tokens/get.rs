@@ -0,0 +1,94 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{ApiScope, CsrfToken, UserId};
+use crate::utils::e500;
+
+/// The API tokens of the current user. The tokens themselves are only
+/// shown once, when they are created.
+pub async fn list_api_tokens(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let tokens = sqlx::query!(
+        r#"
+        SELECT api_token_id, name, scopes, created_at, last_used_at
+        FROM api_tokens
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+        **user_id
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve the API tokens.")
+    .map_err(e500)?;
+    let csrf_field = csrf_token.form_field();
+    let mut rows_html = String::new();
+    for t in tokens {
+        let last_used = t
+            .last_used_at
+            .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_else(|| "never".into());
+        writeln!(
+            rows_html,
+            r#"<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><form action="/admin/api-tokens/{}/delete" method="post">{}<button type="submit">Revoke</button></form></td></tr>"#,
+            encode_minimal(&t.name),
+            t.scopes.join(", "),
+            t.created_at.format("%Y-%m-%d %H:%M UTC"),
+            last_used,
+            t.api_token_id,
+            csrf_field,
+        )
+        .unwrap();
+    }
+    let mut scopes_html = String::new();
+    for scope in ApiScope::ALL {
+        writeln!(
+            scopes_html,
+            r#"<label><input type="checkbox" name="{scope}" value="on"> {scope}</label><br>"#
+        )
+        .unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>API tokens</title>
+</head>
+<body>
+    {msg_html}
+    <p>API tokens let other tools act on your behalf, with an
+    <code>Authorization: Bearer</code> header.</p>
+    <table>
+        <tr><th>Name</th><th>Scopes</th><th>Created</th><th>Last used</th><th></th></tr>
+        {rows_html}
+    </table>
+    <h2>New token</h2>
+    <form action="/admin/api-tokens" method="post">
+        {csrf_field}
+        <label>Name
+            <input type="text" placeholder="What the token is for" name="name">
+        </label>
+        <br>
+        {scopes_html}
+        <button type="submit">Create token</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
@@ -0,0 +1,79 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use htmlescape::encode_minimal;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::authentication::{create_api_token, revoke_api_token, ApiScope, UserId};
+use crate::utils::{api_tokens_page, e500};
+
+#[derive(serde::Deserialize)]
+pub struct ApiTokenForm {
+    name: String,
+    // One checkbox per scope, named after it.
+    #[serde(flatten)]
+    scopes: HashMap<String, String>,
+}
+
+/// Create a token, then show it - only this once.
+#[tracing::instrument(name = "Add an API token", skip(form, pool), fields(name = %form.name))]
+pub async fn add_api_token(
+    form: web::Form<ApiTokenForm>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let ApiTokenForm { name, scopes } = form.into_inner();
+    let name = name.trim();
+    if name.is_empty() {
+        FlashMessage::error("The token needs a name.").send();
+        return Ok(api_tokens_page());
+    }
+    let scopes: Vec<ApiScope> = ApiScope::ALL
+        .into_iter()
+        .filter(|scope| scopes.contains_key(scope.as_str()))
+        .collect();
+    if scopes.is_empty() {
+        FlashMessage::error("Pick at least one scope.").send();
+        return Ok(api_tokens_page());
+    }
+    let token = create_api_token(**user_id, name, &scopes, &pool)
+        .await
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>New API token</title>
+</head>
+<body>
+    <p>The token {name} has been created.</p>
+    <p>Copy it now, it will not be shown again:</p>
+    <p><code>{token}</code></p>
+    <p><a href="/admin/api-tokens">&lt;- Back</a></p>
+</body>
+</html>"#,
+            name = encode_minimal(name),
+            token = token.expose_secret(),
+        )))
+}
+
+#[tracing::instrument(name = "Remove an API token", skip(pool))]
+pub async fn remove_api_token(
+    api_token_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !revoke_api_token(**user_id, api_token_id.into_inner(), &pool)
+        .await
+        .map_err(e500)?
+    {
+        return Err(actix_web::error::ErrorNotFound("Unknown API token."));
+    }
+    FlashMessage::info("The token has been revoked.").send();
+    Ok(api_tokens_page())
+}
@@ -0,0 +1,84 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use super::get_confirmation_email;
+use super::post::ConfirmationEmailForm;
+use crate::authentication::CsrfToken;
+use crate::domain::CONFIRMATION_FIELDS;
+use crate::utils::e500;
+
+pub async fn confirmation_email_form(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let email = get_confirmation_email(&pool).await.map_err(e500)?;
+    let form = ConfirmationEmailForm {
+        subject: email.subject().into(),
+        html_body: email.html().into(),
+        text_body: email.text().into(),
+    };
+    Ok(render_confirmation_email_form(
+        &msg_html,
+        &form,
+        &csrf_token,
+    ))
+}
+
+pub(super) fn render_confirmation_email_form(
+    msg_html: &str,
+    form: &ConfirmationEmailForm,
+    csrf_token: &CsrfToken,
+) -> HttpResponse {
+    let subject = encode_attribute(&form.subject);
+    let html_body = encode_minimal(&form.html_body);
+    let text_body = encode_minimal(&form.text_body);
+    let merge_fields: Vec<String> = CONFIRMATION_FIELDS
+        .iter()
+        .map(|f| format!("<code>{{{{ {f} }}}}</code>"))
+        .collect();
+    let merge_fields = merge_fields.join(", ");
+    let csrf_field = csrf_token.form_field();
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Confirmation email</title>
+</head>
+<body>
+    {msg_html}
+    <p>New subscribers receive this email to confirm their subscription.
+    It can use these merge fields: {merge_fields}.
+    Both bodies must contain <code>{{{{ confirmation_link }}}}</code>.</p>
+    <form action="/admin/confirmation-email" method="post">
+        {csrf_field}
+        <label>Subject:<br>
+            <input type="text" placeholder="Enter the subject" name="subject" value="{subject}">
+        </label>
+        <br>
+        <label>HTML body:<br>
+            <textarea name="html_body" rows="20" cols="50">{html_body}</textarea>
+        </label>
+        <br>
+        <label>Plain text body:<br>
+            <textarea name="text_body" rows="20" cols="50">{text_body}</textarea>
+        </label>
+        <br>
+        <button type="submit" formaction="/admin/confirmation-email/preview">Preview</button>
+        <button type="submit">Save</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        ))
+}
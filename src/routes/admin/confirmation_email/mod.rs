@@ -0,0 +1,30 @@
+mod get;
+mod post;
+
+pub use get::confirmation_email_form;
+pub use post::{preview_confirmation_email, save_confirmation_email};
+
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::domain::ConfirmationEmail;
+
+/// The confirmation email sent to new subscribers, the default one unless
+/// it has been customised.
+#[tracing::instrument(skip_all)]
+pub async fn get_confirmation_email(pool: &PgPool) -> Result<ConfirmationEmail, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT subject, html_body, text_body
+        FROM confirmation_email
+        "#
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the confirmation email.")?;
+    match row {
+        Some(r) => ConfirmationEmail::parse(r.subject, r.html_body, r.text_body)
+            .map_err(anyhow::Error::msg),
+        None => Ok(ConfirmationEmail::default_email()),
+    }
+}
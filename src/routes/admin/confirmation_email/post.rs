@@ -0,0 +1,111 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+
+use super::get::render_confirmation_email_form;
+use crate::authentication::CsrfToken;
+use crate::domain::ConfirmationEmail;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{confirmation_email_page, e500};
+
+#[derive(serde::Deserialize)]
+pub struct ConfirmationEmailForm {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+impl ConfirmationEmailForm {
+    fn validate(&self) -> Result<ConfirmationEmail, String> {
+        ConfirmationEmail::parse(
+            self.subject.clone(),
+            self.html_body.clone(),
+            self.text_body.clone(),
+        )
+    }
+}
+
+#[tracing::instrument(name = "Save the confirmation email", skip_all)]
+pub async fn save_confirmation_email(
+    form: web::Form<ConfirmationEmailForm>,
+    pool: web::Data<PgPool>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email = match form.validate() {
+        Ok(email) => email,
+        Err(e) => return Ok(render_error(&e, &form, &csrf_token)),
+    };
+    sqlx::query!(
+        r#"
+        INSERT INTO confirmation_email (subject, html_body, text_body)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (id) DO UPDATE
+        SET subject = $1, html_body = $2, text_body = $3, updated_at = now()
+        "#,
+        email.subject(),
+        email.html(),
+        email.text(),
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to save the confirmation email.")
+    .map_err(e500)?;
+    FlashMessage::info("The confirmation email has been saved.").send();
+    Ok(confirmation_email_page())
+}
+
+/// Render the submitted confirmation email, without saving it, as a
+/// subscriber named "Jane Doe" would receive it.
+#[tracing::instrument(name = "Preview the confirmation email", skip_all)]
+pub async fn preview_confirmation_email(
+    form: web::Form<ConfirmationEmailForm>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> HttpResponse {
+    let email = match form.validate() {
+        Ok(email) => email,
+        Err(e) => return render_error(&e, &form, &csrf_token),
+    };
+    let name = "Jane Doe";
+    let confirmation_link = format!(
+        "{}/subscriptions/confirm?subscription_token=preview",
+        base_url.0
+    );
+    let subject = encode_minimal(&email.render_subject(name, &confirmation_link));
+    // The HTML body is rendered in a sandboxed frame, so that it cannot
+    // interfere with the admin page around it.
+    let html_body = encode_attribute(&email.render_html(name, &confirmation_link));
+    let text_body = encode_minimal(&email.render_text(name, &confirmation_link));
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Preview: confirmation email</title>
+</head>
+<body>
+    <h1>{subject}</h1>
+    <div style="display: flex; gap: 1em;">
+        <section style="flex: 1;">
+            <h2>HTML</h2>
+            <iframe sandbox srcdoc="{html_body}" style="width: 100%; height: 40em;"></iframe>
+        </section>
+        <section style="flex: 1;">
+            <h2>Plain text</h2>
+            <pre style="white-space: pre-wrap;">{text_body}</pre>
+        </section>
+    </div>
+    <p><a href="/admin/confirmation-email">&lt;- Back</a></p>
+</body>
+</html>"#,
+        ))
+}
+
+fn render_error(e: &str, form: &ConfirmationEmailForm, csrf_token: &CsrfToken) -> HttpResponse {
+    let msg_html = format!("<p><i>{}</i></p>", encode_minimal(e));
+    render_confirmation_email_form(&msg_html, form, csrf_token)
+}
@@ -1,20 +1,39 @@
 use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
 use anyhow::Context;
 use sqlx::PgPool;
+use std::fmt::Write;
 use uuid::Uuid;
 
+use crate::authentication::{get_role, CsrfToken, Role};
 use crate::session_state::TypedSession;
 use crate::utils::{e500, login_page};
 
 pub async fn admin_dashboard(
     session: TypedSession,
     pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let username = if let Some(user_id) = session.get_user_id().map_err(e500)? {
-        get_username(user_id, &pool).await.map_err(e500)?
-    } else {
+    let Some(user_id) = session.get_user_id().map_err(e500)? else {
+        return Ok(login_page());
+    };
+    // The user may have been removed since they logged in.
+    let Some(role) = get_role(user_id, &pool).await.map_err(e500)? else {
+        session.logout();
         return Ok(login_page());
     };
+    let username = get_username(user_id, &pool).await.map_err(e500)?;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let users_link = if role == Role::Owner {
+        r#"<li><a href="/admin/users">Manage users</a></li>"#
+    } else {
+        ""
+    };
+    let csrf_field = csrf_token.form_field();
 
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
@@ -27,12 +46,26 @@ pub async fn admin_dashboard(
             <title>Admin dashboard</title>
             </head>
             <body>
+                {msg_html}
                 <p>Welcome {username}!</p>
+                <p>You are signed in as {role}.</p>
                 <p>Available actions:</p>
                 <ol>
+                    <li><a href="/admin/newsletters">Send a newsletter issue</a></li>
+                    <li><a href="/admin/newsletters/issues">Drafts and past issues</a></li>
+                    <li><a href="/admin/subscribers">Manage subscribers</a></li>
+                    <li><a href="/admin/templates">Email templates</a></li>
+                    <li><a href="/admin/confirmation-email">Confirmation email</a></li>
+                    <li><a href="/admin/welcome-emails">Welcome sequence</a></li>
+                    {users_link}
                     <li><a href="/admin/password">Change password</a></li>
+                    <li><a href="/admin/email">Account email</a></li>
+                    <li><a href="/admin/two-factor">Two-factor authentication</a></li>
+                    <li><a href="/admin/sessions">Active sessions</a></li>
+                    <li><a href="/admin/api-tokens">API tokens</a></li>
                     <li>
                         <form name="logoutForm" action="/admin/logout" method="post">
+                            {csrf_field}
                             <input type="submit" value="Logout">
                         </form>
                     </li>
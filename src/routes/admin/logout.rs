@@ -1,17 +1,27 @@
-use actix_web::HttpResponse;
+use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
 
 use crate::{
+    authentication::revoke_session,
     session_state::TypedSession,
     utils::{e500, login_page},
 };
 
-pub async fn logout(session: TypedSession) -> Result<HttpResponse, actix_web::Error> {
-    if session.get_user_id().map_err(e500)?.is_none() {
-        Ok(login_page())
-    } else {
-        session.logout();
-        FlashMessage::info("You have successfully logged out.").send();
-        Ok(login_page())
+pub async fn logout(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(user_id) = session.get_user_id().map_err(e500)? else {
+        return Ok(login_page());
+    };
+    // Taken off the list of active sessions.
+    if let Some(session_id) = session.get_session_id().map_err(e500)? {
+        revoke_session(user_id, session_id, &pool)
+            .await
+            .map_err(e500)?;
     }
+    session.logout();
+    FlashMessage::info("You have successfully logged out.").send();
+    Ok(login_page())
 }
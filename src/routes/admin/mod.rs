@@ -1,9 +1,27 @@
+mod confirmation_email;
+mod account_email;
+mod api_tokens;
 mod dashboard;
 mod logout;
 mod newsletter;
 mod password;
+mod sessions;
+mod subscribers;
+mod templates;
+mod two_factor;
+mod users;
+mod welcome_emails;
 
+pub use confirmation_email::*;
+pub use account_email::*;
+pub use api_tokens::*;
 pub use dashboard::*;
 pub use logout::*;
 pub use newsletter::*;
 pub use password::*;
+pub use sessions::*;
+pub use subscribers::*;
+pub use templates::*;
+pub use two_factor::*;
+pub use users::*;
+pub use welcome_emails::*;
@@ -0,0 +1,128 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::utils::{e500, issue_preview_page};
+
+/// Put the remaining deliveries of an issue on hold.
+#[tracing::instrument(name = "Pause a newsletter delivery", skip(pool))]
+pub async fn pause_delivery(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'paused'
+        WHERE newsletter_issue_id = $1 AND status = 'sending'
+        "#,
+        issue_id,
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to pause the delivery")
+    .map_err(e500)?;
+    if updated.rows_affected() == 0 {
+        FlashMessage::error("Only issues that are being sent can be paused.").send();
+    } else {
+        FlashMessage::info("The delivery has been paused.").send();
+    }
+    Ok(issue_preview_page(issue_id))
+}
+
+#[tracing::instrument(name = "Resume a newsletter delivery", skip(pool))]
+pub async fn resume_delivery(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    // The last delivery may have gone out while we were pausing.
+    let updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = CASE
+            WHEN EXISTS (
+                SELECT 1 FROM issue_delivery_queue
+                WHERE newsletter_issue_id = $1
+            ) THEN 'sending'
+            ELSE 'sent'
+        END
+        WHERE newsletter_issue_id = $1 AND status = 'paused'
+        "#,
+        issue_id,
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to resume the delivery")
+    .map_err(e500)?;
+    if updated.rows_affected() == 0 {
+        FlashMessage::error("Only paused issues can be resumed.").send();
+    } else {
+        FlashMessage::info("The delivery has been resumed.").send();
+    }
+    Ok(issue_preview_page(issue_id))
+}
+
+/// Drop the deliveries that have not gone out yet, keeping track of
+/// how many subscribers will never receive the issue.
+#[tracing::instrument(name = "Cancel a newsletter delivery", skip(pool))]
+pub async fn cancel_delivery(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    // Queue rows are deleted before the issue row is touched: the worker
+    // locks them in the same order, so the two cannot deadlock.
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue q
+        USING newsletter_issues i
+        WHERE
+            q.newsletter_issue_id = i.newsletter_issue_id AND
+            i.newsletter_issue_id = $1 AND
+            i.status IN ('sending', 'paused')
+        "#,
+        issue_id,
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to delete the queued deliveries")
+    .map_err(e500)?;
+    let cancelled_deliveries = deleted.rows_affected() as i32;
+    let updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'cancelled', cancelled_deliveries = $2
+        WHERE newsletter_issue_id = $1 AND status IN ('sending', 'paused')
+        "#,
+        issue_id,
+        cancelled_deliveries,
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to cancel the delivery")
+    .map_err(e500)?;
+    if updated.rows_affected() == 0 {
+        FlashMessage::error("Only issues that are being sent can be cancelled.").send();
+        return Ok(issue_preview_page(issue_id));
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to cancel a delivery")
+        .map_err(e500)?;
+    FlashMessage::info(format!(
+        "The delivery has been cancelled. {} email(s) will not be sent.",
+        cancelled_deliveries
+    ))
+    .send();
+    Ok(issue_preview_page(issue_id))
+}
@@ -0,0 +1,478 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use super::form::FormData;
+use super::get::render_publish_form;
+use super::post::{assign_slug, insert_newsletter_issue, success_message};
+use super::recipients::{count_segment_recipients, recipients_message};
+use crate::authentication::CsrfToken;
+use crate::domain::IssueStatus;
+use crate::issue_delivery_worker::start_delivery;
+use crate::routes::get_template_names;
+use crate::utils::{e400, e500, issue_page, issues_page};
+
+#[tracing::instrument(name = "Save a newsletter draft", skip_all)]
+pub async fn create_draft(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let form = form.into_inner().generate_content();
+    let segment = form.segment().map_err(e400)?;
+    let template_id = form.checked_template_id(&pool).await?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let issue_id = insert_newsletter_issue(&mut transaction, &form, &segment, template_id)
+        .await
+        .context("Failed to store newsletter issue details")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store a draft")
+        .map_err(e500)?;
+    FlashMessage::info("The draft has been saved.").send();
+    Ok(issue_page(issue_id))
+}
+
+pub async fn edit_draft_form(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let issue = get_issue(&pool, issue_id).await?;
+    if issue.status != IssueStatus::Draft {
+        FlashMessage::error("Only drafts can be edited.").send();
+        return Ok(issues_page());
+    }
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let form = issue.into_form();
+    let segment = form.segment().map_err(e500)?;
+    let n = count_segment_recipients(&pool, &segment)
+        .await
+        .map_err(e500)?;
+    let templates = get_template_names(&pool).await.map_err(e500)?;
+    Ok(render_publish_form(
+        &msg_html,
+        &form,
+        &recipients_message(n),
+        &templates,
+        &csrf_token,
+    ))
+}
+
+#[tracing::instrument(name = "Update a newsletter draft", skip(form, pool, csrf_token))]
+pub async fn update_draft(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let mut form = form.into_inner();
+    let issue = get_issue(&pool, issue_id).await?;
+    if form.overwrites_html(&issue.html_content, issue.markdown_content.as_deref()) {
+        // Show the form again rather than lose what the author typed.
+        form.issue_id = Some(issue_id);
+        let msg_html = "<p><i>The HTML content has been written by hand and would be \
+            replaced by the one generated from the Markdown content. Clear the HTML \
+            content to write the issue in Markdown.</i></p>";
+        let recipients_html = match form.segment() {
+            Ok(segment) => {
+                let n = count_segment_recipients(&pool, &segment)
+                    .await
+                    .map_err(e500)?;
+                recipients_message(n)
+            }
+            Err(e) => format!("<p><i>{}</i></p>", encode_minimal(&e)),
+        };
+        let templates = get_template_names(&pool).await.map_err(e500)?;
+        return Ok(render_publish_form(
+            msg_html,
+            &form,
+            &recipients_html,
+            &templates,
+            &csrf_token,
+        ));
+    }
+    let form = form.generate_content();
+    let segment = form.segment().map_err(e400)?;
+    let template_id = form.checked_template_id(&pool).await?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let markdown = form.markdown();
+    let updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            title = $2,
+            text_content = $3,
+            html_content = $4,
+            segment_include_tags = $5,
+            segment_exclude_tags = $6,
+            segment_subscribed_after = $7,
+            segment_subscribed_before = $8,
+            is_public = $9,
+            markdown_content = $10,
+            template_id = $11
+        WHERE newsletter_issue_id = $1 AND status = 'draft'
+        "#,
+        issue_id,
+        form.title,
+        form.text_content,
+        form.html_content,
+        &segment.include_tags()[..],
+        &segment.exclude_tags()[..],
+        segment.subscribed_after,
+        segment.subscribed_before,
+        form.is_public,
+        markdown.as_ref().map(|m| m.as_ref()),
+        template_id,
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to update the draft")
+    .map_err(e500)?;
+    if updated.rows_affected() == 0 {
+        FlashMessage::error("Only drafts can be edited.").send();
+        return Ok(issues_page());
+    }
+    // The slug follows the title for as long as the issue is a draft.
+    assign_slug(&mut transaction, &form.title, issue_id)
+        .await
+        .context("Failed to generate a slug")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to update a draft")
+        .map_err(e500)?;
+    FlashMessage::info("The draft has been saved.").send();
+    Ok(issue_page(issue_id))
+}
+
+/// Show the issue as subscribers will see it, HTML and plain text side by side.
+pub async fn preview_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let issue = get_issue(&pool, issue_id).await?;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let status = issue.status;
+    let scheduled_for = issue.scheduled_for;
+    let cancelled_deliveries = issue.cancelled_deliveries;
+    let csrf_field = csrf_token.form_field();
+    let visibility_html = visibility_html(
+        issue_id,
+        status,
+        issue.is_public,
+        issue.slug.as_deref(),
+        &csrf_field,
+    );
+    let form = issue.into_form();
+    let mut actions_html = match status {
+        IssueStatus::Draft | IssueStatus::Scheduled => {
+            let segment = form.segment().map_err(e500)?;
+            let n = count_segment_recipients(&pool, &segment)
+                .await
+                .map_err(e500)?;
+            let mut actions_html = recipients_message(n);
+            if let Some(scheduled_for) = scheduled_for {
+                writeln!(
+                    actions_html,
+                    "<p>Scheduled for {}.</p>",
+                    scheduled_for.format("%Y-%m-%d %H:%M UTC")
+                )
+                .unwrap();
+            }
+            let schedule_label = if status == IssueStatus::Draft {
+                "Schedule"
+            } else {
+                "Reschedule"
+            };
+            write!(
+                actions_html,
+                r#"
+    <form action="/admin/newsletters/issues/{issue_id}/send" method="post">
+        {csrf_field}
+        <button type="submit">Send now</button>
+    </form>
+    <form action="/admin/newsletters/issues/{issue_id}/schedule" method="post">
+        {csrf_field}
+        <label>Publish on (UTC):
+            <input type="datetime-local" name="scheduled_for">
+        </label>
+        <button type="submit">{schedule_label}</button>
+    </form>"#
+            )
+            .unwrap();
+            if status == IssueStatus::Draft {
+                write!(
+                    actions_html,
+                    r#"
+    <p><a href="/admin/newsletters/issues/{issue_id}">Edit</a></p>"#
+                )
+                .unwrap();
+            } else {
+                write!(
+                    actions_html,
+                    r#"
+    <form action="/admin/newsletters/issues/{issue_id}/unschedule" method="post">
+        {csrf_field}
+        <button type="submit">Cancel schedule</button>
+    </form>"#
+                )
+                .unwrap();
+            }
+            actions_html
+        }
+        IssueStatus::Sending => format!(
+            r#"<form action="/admin/newsletters/issues/{issue_id}/pause" method="post">
+        {csrf_field}
+        <button type="submit">Pause</button>
+    </form>
+    <form action="/admin/newsletters/issues/{issue_id}/cancel" method="post">
+        {csrf_field}
+        <button type="submit">Cancel delivery</button>
+    </form>"#
+        ),
+        IssueStatus::Paused => format!(
+            r#"<form action="/admin/newsletters/issues/{issue_id}/resume" method="post">
+        {csrf_field}
+        <button type="submit">Resume</button>
+    </form>
+    <form action="/admin/newsletters/issues/{issue_id}/cancel" method="post">
+        {csrf_field}
+        <button type="submit">Cancel delivery</button>
+    </form>"#
+        ),
+        IssueStatus::Cancelled => format!(
+            "<p>{} email(s) were never sent.</p>",
+            cancelled_deliveries.unwrap_or_default()
+        ),
+        IssueStatus::Sent => String::new(),
+    };
+    if !matches!(status, IssueStatus::Draft | IssueStatus::Scheduled) {
+        write!(
+            actions_html,
+            r#"
+    <p><a href="/admin/newsletters/issues/{issue_id}/status">Delivery status</a></p>"#
+        )
+        .unwrap();
+    }
+    let title = encode_minimal(&form.title);
+    // The HTML content is rendered in a sandboxed frame, so that it cannot
+    // interfere with the admin page around it.
+    let (html_content, text_content) = form.preview_content(&pool).await.map_err(e500)?;
+    let html_content = encode_attribute(&html_content);
+    let text_content = encode_minimal(&text_content);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Preview: {title}</title>
+</head>
+<body>
+    {msg_html}
+    <h1>{title}</h1>
+    <p>Status: {status}</p>
+    {visibility_html}
+    <div style="display: flex; gap: 1em;">
+        <section style="flex: 1;">
+            <h2>HTML</h2>
+            <iframe sandbox srcdoc="{html_content}" style="width: 100%; height: 40em;"></iframe>
+        </section>
+        <section style="flex: 1;">
+            <h2>Plain text</h2>
+            <pre style="white-space: pre-wrap;">{text_content}</pre>
+        </section>
+    </div>
+    {actions_html}
+    <p><a href="/admin/newsletters/issues">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(name = "Send a newsletter draft", skip(pool))]
+pub async fn send_draft(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let started = start_delivery(&mut transaction, issue_id)
+        .await
+        .context("Failed to enqueue delivery tasks")
+        .map_err(e500)?;
+    if !started {
+        FlashMessage::error("Only drafts and scheduled issues can be sent.").send();
+        return Ok(issues_page());
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to send a draft")
+        .map_err(e500)?;
+    success_message().send();
+    Ok(issues_page())
+}
+
+fn visibility_html(
+    issue_id: Uuid,
+    status: IssueStatus,
+    is_public: bool,
+    slug: Option<&str>,
+    csrf_field: &str,
+) -> String {
+    let published = matches!(status, IssueStatus::Sending | IssueStatus::Sent);
+    let (description, button, value) = match (is_public, slug) {
+        (true, Some(slug)) if published => (
+            format!(r#"Listed in the <a href="/issues/{slug}">web archive</a>."#),
+            "Remove from the web archive",
+            false,
+        ),
+        (true, _) => (
+            "Will be listed in the web archive once published.".to_string(),
+            "Remove from the web archive",
+            false,
+        ),
+        (false, _) => (
+            "Not listed in the web archive.".to_string(),
+            "Add to the web archive",
+            true,
+        ),
+    };
+    format!(
+        r#"<form action="/admin/newsletters/issues/{issue_id}/visibility" method="post">
+        {csrf_field}
+        {description}
+        <input hidden type="text" name="is_public" value="{value}">
+        <button type="submit">{button}</button>
+    </form>"#
+    )
+}
+
+struct Issue {
+    newsletter_issue_id: Uuid,
+    title: String,
+    text_content: String,
+    html_content: String,
+    markdown_content: Option<String>,
+    template_id: Option<Uuid>,
+    status: IssueStatus,
+    scheduled_for: Option<DateTime<Utc>>,
+    cancelled_deliveries: Option<i32>,
+    is_public: bool,
+    slug: Option<String>,
+    segment_include_tags: Vec<String>,
+    segment_exclude_tags: Vec<String>,
+    segment_subscribed_after: Option<DateTime<Utc>>,
+    segment_subscribed_before: Option<DateTime<Utc>>,
+}
+
+impl Issue {
+    /// The publish form, filled in with the issue's content and segment.
+    fn into_form(self) -> FormData {
+        let format_date = |d: Option<DateTime<Utc>>| {
+            d.map(|d| d.format("%Y-%m-%d").to_string())
+                .unwrap_or_default()
+        };
+        FormData {
+            title: self.title,
+            text_content: self.text_content,
+            html_content: self.html_content,
+            markdown_content: self.markdown_content.unwrap_or_default(),
+            template_id: self
+                .template_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            include_tags: self.segment_include_tags.join(", "),
+            exclude_tags: self.segment_exclude_tags.join(", "),
+            subscribed_after: format_date(self.segment_subscribed_after),
+            subscribed_before: format_date(self.segment_subscribed_before),
+            idempotency_key: Uuid::new_v4().to_string(),
+            is_public: self.is_public,
+            issue_id: Some(self.newsletter_issue_id),
+        }
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<Issue, actix_web::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            template_id,
+            status,
+            scheduled_for,
+            cancelled_deliveries,
+            is_public,
+            slug,
+            segment_include_tags,
+            segment_exclude_tags,
+            segment_subscribed_after,
+            segment_subscribed_before
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the newsletter issue")
+    .map_err(e500)?
+    .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown newsletter issue."))?;
+    Ok(Issue {
+        newsletter_issue_id: row.newsletter_issue_id,
+        title: row.title,
+        text_content: row.text_content,
+        html_content: row.html_content,
+        markdown_content: row.markdown_content,
+        template_id: row.template_id,
+        status: row.status.try_into().map_err(e500)?,
+        scheduled_for: row.scheduled_for,
+        cancelled_deliveries: row.cancelled_deliveries,
+        is_public: row.is_public,
+        slug: row.slug,
+        segment_include_tags: row.segment_include_tags,
+        segment_exclude_tags: row.segment_exclude_tags,
+        segment_subscribed_after: row.segment_subscribed_after,
+        segment_subscribed_before: row.segment_subscribed_before,
+    })
+}
@@ -0,0 +1,164 @@
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{MarkdownContent, MergeFields, Segment};
+use crate::html_to_text::html_to_text;
+use crate::routes::{error_chain_fmt, get_template_layout, template_exists};
+
+#[derive(serde::Deserialize, Default, utoipa::ToSchema)]
+#[schema(as = IssueForm)]
+pub struct FormData {
+    pub title: String,
+    #[serde(default)]
+    pub text_content: String,
+    #[serde(default)]
+    pub html_content: String,
+    /// When set, the plain text and HTML content are generated from it.
+    #[serde(default)]
+    pub markdown_content: String,
+    /// Comma-separated tags: when set, only subscribers with one of them get it.
+    #[serde(default)]
+    pub include_tags: String,
+    /// Comma-separated tags: subscribers with one of them do not get the issue.
+    #[serde(default)]
+    pub exclude_tags: String,
+    #[serde(default)]
+    #[schema(format = Date)]
+    pub subscribed_after: String,
+    #[serde(default)]
+    #[schema(format = Date)]
+    pub subscribed_before: String,
+    /// API clients send it in the `Idempotency-Key` header instead.
+    #[serde(default)]
+    pub idempotency_key: String,
+    /// Whether the issue is listed in the public web archive.
+    #[serde(default)]
+    pub is_public: bool,
+    /// The email template wrapped around the issue, blank for none.
+    #[serde(default)]
+    pub template_id: String,
+    /// Set when editing an existing draft rather than writing a new issue.
+    #[serde(default)]
+    pub issue_id: Option<Uuid>,
+}
+
+impl FormData {
+    pub fn segment(&self) -> Result<Segment, String> {
+        Segment::parse(
+            &self.include_tags,
+            &self.exclude_tags,
+            &self.subscribed_after,
+            &self.subscribed_before,
+        )
+    }
+
+    pub fn template_id(&self) -> Result<Option<Uuid>, String> {
+        let template_id = self.template_id.trim();
+        if template_id.is_empty() {
+            return Ok(None);
+        }
+        Uuid::parse_str(template_id)
+            .map(Some)
+            .map_err(|_| format!("{} is not a valid template id.", template_id))
+    }
+
+    /// Like `template_id`, but also checks that the template exists.
+    pub async fn checked_template_id(
+        &self,
+        pool: &PgPool,
+    ) -> Result<Option<Uuid>, TemplateIdError> {
+        let template_id = match self
+            .template_id()
+            .map_err(TemplateIdError::ValidationError)?
+        {
+            Some(template_id) => template_id,
+            None => return Ok(None),
+        };
+        if template_exists(pool, template_id).await? {
+            Ok(Some(template_id))
+        } else {
+            Err(TemplateIdError::ValidationError(format!(
+                "There is no email template with id {}.",
+                template_id
+            )))
+        }
+    }
+
+    pub fn markdown(&self) -> Option<MarkdownContent> {
+        MarkdownContent::parse(self.markdown_content.clone()).ok()
+    }
+
+    /// Whether saving the form would replace HTML content written by hand
+    /// with the one generated from the Markdown content. `previous_html` and
+    /// `previous_markdown` are what the issue held before the edit.
+    pub fn overwrites_html(&self, previous_html: &str, previous_markdown: Option<&str>) -> bool {
+        if self.markdown().is_none() || self.html_content.trim().is_empty() {
+            return false;
+        }
+        match previous_markdown {
+            // The HTML was generated: it is only lost if it has been edited.
+            // Browsers send the content of text areas back with `\r\n` line
+            // breaks.
+            Some(_) => {
+                self.html_content.replace("\r\n", "\n") != previous_html.replace("\r\n", "\n")
+            }
+            None => true,
+        }
+    }
+
+    /// Fill in the content the server generates: both versions are rendered
+    /// from the Markdown source if the issue has one, otherwise a blank plain
+    /// text version is derived from the HTML content.
+    pub fn generate_content(mut self) -> Self {
+        if let Some(markdown) = self.markdown() {
+            self.text_content = markdown.to_plain_text();
+            self.html_content = markdown.to_html();
+        } else if self.text_content.trim().is_empty() {
+            self.text_content = html_to_text(&self.html_content);
+        }
+        self
+    }
+
+    /// The HTML and plain text content wrapped in the issue's template, if
+    /// it has one. Merge fields are shown as placeholders, since there is no
+    /// recipient yet.
+    pub async fn preview_content(&self, pool: &PgPool) -> Result<(String, String), anyhow::Error> {
+        let template_id = match self.template_id().map_err(anyhow::Error::msg)? {
+            Some(template_id) => template_id,
+            None => return Ok((self.html_content.clone(), self.text_content.clone())),
+        };
+        let layout = get_template_layout(pool, template_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Unknown email template."))?;
+        let fields = MergeFields::placeholders();
+        Ok((
+            layout.render_html(&self.html_content, &fields),
+            layout.render_text(&self.text_content, &fields),
+        ))
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum TemplateIdError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for TemplateIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for TemplateIdError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TemplateIdError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            TemplateIdError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
@@ -1,17 +1,94 @@
 use actix_web::http::header::ContentType;
-use actix_web::HttpResponse;
+use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
 use std::fmt::Write;
 
+use super::form::FormData;
+use super::recipients::{count_segment_recipients, recipients_message};
+use crate::authentication::CsrfToken;
+use crate::domain::Segment;
+use crate::routes::{get_template_names, TemplateName};
+use crate::utils::e500;
+
 pub async fn publish_newsletter_form(
     flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    csrf_token: web::ReqData<CsrfToken>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let mut msg_html = String::new();
     for m in flash_messages.iter() {
         writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
     }
-    let idempotency_key = uuid::Uuid::new_v4();
-    Ok(HttpResponse::Ok()
+    let n = count_segment_recipients(&pool, &Segment::default())
+        .await
+        .map_err(e500)?;
+    let templates = get_template_names(&pool).await.map_err(e500)?;
+    let form = FormData {
+        idempotency_key: uuid::Uuid::new_v4().to_string(),
+        ..Default::default()
+    };
+    Ok(render_publish_form(
+        &msg_html,
+        &form,
+        &recipients_message(n),
+        &templates,
+        &csrf_token,
+    ))
+}
+
+pub fn render_publish_form(
+    msg_html: &str,
+    form: &FormData,
+    recipients_html: &str,
+    templates: &[TemplateName],
+    csrf_token: &CsrfToken,
+) -> HttpResponse {
+    let title = encode_attribute(&form.title);
+    let markdown_content = encode_minimal(&form.markdown_content);
+    let text_content = encode_minimal(&form.text_content);
+    let html_content = encode_minimal(&form.html_content);
+    let include_tags = encode_attribute(&form.include_tags);
+    let exclude_tags = encode_attribute(&form.exclude_tags);
+    let subscribed_after = encode_attribute(&form.subscribed_after);
+    let subscribed_before = encode_attribute(&form.subscribed_before);
+    let idempotency_key = encode_attribute(&form.idempotency_key);
+    let is_public = if form.is_public { "checked" } else { "" };
+    let csrf_field = csrf_token.form_field();
+    let mut templates_html = String::from(r#"<option value="">No template</option>"#);
+    for t in templates {
+        let selected = if form.template_id == t.template_id.to_string() {
+            "selected"
+        } else {
+            ""
+        };
+        write!(
+            templates_html,
+            r#"<option value="{}" {selected}>{}</option>"#,
+            t.template_id,
+            encode_minimal(&t.name)
+        )
+        .unwrap();
+    }
+    let (action, issue_id_html, buttons_html) = match form.issue_id {
+        Some(issue_id) => (
+            format!("/admin/newsletters/issues/{issue_id}"),
+            format!(r#"<input hidden type="text" name="issue_id" value="{issue_id}">"#),
+            format!(
+                r#"<button type="submit">Save draft</button>
+        <a href="/admin/newsletters/issues/{issue_id}/preview">Preview and send</a>"#
+            ),
+        ),
+        None => (
+            "/admin/newsletters".to_string(),
+            String::new(),
+            r#"<button type="submit" formaction="/admin/newsletters/issues">Save as draft</button>
+        <button type="submit">Publish</button>"#
+                .to_string(),
+        ),
+    };
+    HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(format!(
             r#"<!DOCTYPE html>
@@ -22,22 +99,36 @@ pub async fn publish_newsletter_form(
 </head>
 <body>
     {msg_html}
-    <form action="/admin/newsletters" method="post">
+    <form action="{action}" method="post">
+        {csrf_field}
         <label>Title:<br>
             <input
                 type="text"
                 placeholder="Enter the issue title"
                 name="title"
+                value="{title}"
             >
         </label>
         <br>
+        <label>Markdown content:<br>
+            <textarea
+                placeholder="Write the issue in Markdown"
+                name="markdown_content"
+                rows="20"
+                cols="50"
+            >{markdown_content}</textarea>
+        </label>
+        <p>When the issue is written in Markdown, the plain text and HTML content
+        below are generated from it, and edits made to them are lost. To switch an
+        issue written in HTML to Markdown, clear its HTML content. Otherwise, the
+        plain text content can be left blank to generate it from the HTML content.</p>
         <label>Plain text content:<br>
             <textarea
-                placeholder="Enter the content in plain text"
+                placeholder="Enter the content in plain text, or leave blank"
                 name="text_content"
                 rows="20"
                 cols="50"
-            ></textarea>
+            >{text_content}</textarea>
         </label>
         <br>
         <label>HTML content:<br>
@@ -46,14 +137,45 @@ pub async fn publish_newsletter_form(
                 name="html_content"
                 rows="20"
                 cols="50"
-            ></textarea>
+            >{html_content}</textarea>
+        </label>
+        <br>
+        <label>Template:
+            <select name="template_id">{templates_html}</select>
+        </label>
+        <br>
+        <fieldset>
+            <legend>Recipients (leave blank to send to every confirmed subscriber)</legend>
+            <label>Only subscribers tagged with any of:
+                <input type="text" placeholder="e.g. rust, beta-readers" name="include_tags" value="{include_tags}">
+            </label>
+            <br>
+            <label>Except subscribers tagged with any of:
+                <input type="text" placeholder="e.g. unengaged" name="exclude_tags" value="{exclude_tags}">
+            </label>
+            <br>
+            <label>Signed up on or after:
+                <input type="date" name="subscribed_after" value="{subscribed_after}">
+            </label>
+            <label>Signed up before:
+                <input type="date" name="subscribed_before" value="{subscribed_before}">
+            </label>
+            {recipients_html}
+            <button type="submit" formaction="/admin/newsletters/recipients">Count recipients</button>
+        </fieldset>
+        <label>
+            <input type="checkbox" name="is_public" value="true" {is_public}>
+            List this issue in the public web archive
         </label>
         <br>
+        <button type="submit" formaction="/admin/newsletters/test">Send a test copy</button>
         <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
-        <button type="submit">Publish</button>
+        {issue_id_html}
+        {buttons_html}
     </form>
+    <p><a href="/admin/newsletters/issues">All issues</a></p>
     <p><a href="/admin/dashboard">&lt;- Back</a></p>
 </body>
 </html>"#,
-        )))
+        ))
 }
@@ -0,0 +1,97 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::domain::IssueStatus;
+use crate::utils::e500;
+
+struct IssueRow {
+    newsletter_issue_id: Uuid,
+    title: String,
+    status: String,
+    scheduled_for: Option<DateTime<Utc>>,
+    published_at: Option<DateTime<Utc>>,
+}
+
+pub async fn list_issues(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let issues = get_issues(&pool).await.map_err(e500)?;
+    let mut rows_html = String::new();
+    for issue in issues {
+        let status: IssueStatus = issue.status.try_into().map_err(e500)?;
+        let id = issue.newsletter_issue_id;
+        // Only drafts can still be changed.
+        let actions = match status {
+            IssueStatus::Draft => format!(
+                r#"<a href="/admin/newsletters/issues/{id}">Edit</a> <a href="/admin/newsletters/issues/{id}/preview">Preview</a>"#
+            ),
+            IssueStatus::Scheduled => {
+                format!(r#"<a href="/admin/newsletters/issues/{id}/preview">Preview</a>"#)
+            }
+            _ => format!(
+                r#"<a href="/admin/newsletters/issues/{id}/preview">View</a> <a href="/admin/newsletters/issues/{id}/status">Delivery status</a>"#
+            ),
+        };
+        let format_time = |d: Option<DateTime<Utc>>| {
+            d.map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default()
+        };
+        writeln!(
+            rows_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            encode_minimal(&issue.title),
+            status,
+            format_time(issue.scheduled_for),
+            format_time(issue.published_at),
+            actions,
+        )
+        .unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Newsletter issues</title>
+</head>
+<body>
+    {msg_html}
+    <table>
+        <tr><th>Title</th><th>Status</th><th>Scheduled for (UTC)</th><th>Published (UTC)</th><th></th></tr>
+        {rows_html}
+    </table>
+    <p><a href="/admin/newsletters">Write a new issue</a></p>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_issues(pool: &PgPool) -> Result<Vec<IssueRow>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        IssueRow,
+        r#"
+        SELECT newsletter_issue_id, title, status, scheduled_for, published_at
+        FROM newsletter_issues
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the list of newsletter issues.")?;
+    Ok(rows)
+}
@@ -1,5 +1,29 @@
+mod delivery;
+mod drafts;
+mod form;
 mod get;
+mod issues;
 mod post;
+mod recipients;
+mod schedule;
+mod status;
+mod test_copy;
+mod visibility;
 
+pub use delivery::{cancel_delivery, pause_delivery, resume_delivery};
+pub use drafts::{create_draft, edit_draft_form, preview_issue, send_draft, update_draft};
+pub(crate) use form::{FormData as IssueFormData, TemplateIdError};
 pub use get::publish_newsletter_form;
-pub use post::publish_newsletter;
+pub use issues::list_issues;
+pub(crate) use post::insert_newsletter_issue;
+// The `__path_` structs describe the routes in the OpenAPI document.
+pub use post::{
+    __path_publish_newsletter, __path_publish_newsletter_outside_admin, publish_newsletter,
+    publish_newsletter_outside_admin,
+};
+pub use recipients::count_newsletter_recipients;
+pub use schedule::{schedule_issue, unschedule_issue};
+pub use status::delivery_status;
+pub(crate) use status::{get_issue_delivery, IssueDelivery};
+pub use test_copy::send_test_copy;
+pub use visibility::set_issue_visibility;
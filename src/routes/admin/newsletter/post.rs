@@ -1,27 +1,45 @@
+use super::form::FormData;
 use crate::authentication::UserId;
+use crate::domain::{IssueSlug, Segment};
 use crate::idempotency::{save_response, try_processing, IdempotencyKey, NextAction};
+use crate::issue_delivery_worker::start_delivery;
 use crate::utils::{e400, e500, newsletters_page};
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
-use sqlx::{PgPool, Postgres, Transaction};
+use sqlx::{Acquire, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
-#[derive(serde::Deserialize)]
-pub struct FormData {
-    title: String,
-    text_content: String,
-    html_content: String,
-    idempotency_key: String,
-}
-
-fn success_message() -> FlashMessage {
+pub(super) fn success_message() -> FlashMessage {
     FlashMessage::info(
         "The newsletter issue has been accepted - \
         emails will go out shortly.",
     )
 }
 
+#[utoipa::path(
+    post,
+    path = "/admin/newsletters",
+    summary = "Publish an issue right away, from the admin area.",
+    description = "Needs an editor or owner to be logged in, and the CSRF token of the \
+        session: in the `X-CSRF-Token` header or in the `csrf_token` field of the form.",
+    params(("X-CSRF-Token" = Option<String>, Header)),
+    request_body(content = FormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (
+            status = 303,
+            description = "Back to the form, or to the login page for anonymous users.",
+        ),
+        (
+            status = 400,
+            description = "The segment, template or idempotency key is invalid.",
+            body = String,
+        ),
+        (status = 403, description = "The CSRF token is missing or stale.", body = String),
+        (status = 500, description = "Something went wrong."),
+    ),
+    security(("cookieAuth" = [])),
+)]
 #[tracing::instrument(
     name = "Publish a newsletter issue",
     skip_all,
@@ -33,13 +51,10 @@ pub async fn publish_newsletter(
     user_id: web::ReqData<UserId>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
-    let FormData {
-        title,
-        text_content,
-        html_content,
-        idempotency_key,
-    } = form.0;
-    let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(e400)?;
+    let form = form.into_inner().generate_content();
+    let segment = form.segment().map_err(e400)?;
+    let template_id = form.checked_template_id(&pool).await?;
+    let idempotency_key: IdempotencyKey = form.idempotency_key.clone().try_into().map_err(e400)?;
     let mut transaction = match try_processing(&pool, &idempotency_key, *user_id)
         .await
         .map_err(e500)?
@@ -50,11 +65,11 @@ pub async fn publish_newsletter(
             return Ok(saved_response);
         }
     };
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text_content, &html_content)
+    let issue_id = insert_newsletter_issue(&mut transaction, &form, &segment, template_id)
         .await
         .context("Failed to store newsletter issue details")
         .map_err(e500)?;
-    enqueue_delivery_tasks(&mut transaction, issue_id)
+    start_delivery(&mut transaction, issue_id)
         .await
         .context("Failed to enqueue delivery tasks")
         .map_err(e500)?;
@@ -66,14 +81,44 @@ pub async fn publish_newsletter(
     Ok(response)
 }
 
+/// `POST /newsletters`, kept from before the admin area. The session
+/// middleware of `/admin` does not run for it, so there is never a user and
+/// the request fails once the form has been read.
+#[utoipa::path(
+    post,
+    path = "/newsletters",
+    summary = "Publish an issue right away, outside the admin area.",
+    description = "Kept from before the admin area, and deprecated: no user is logged in \
+        outside of `/admin`, so the issue is never published. Use `POST /admin/newsletters` \
+        or `POST /api/v1/newsletters` instead.",
+    request_body(content = FormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 400, description = "The form cannot be read.", body = String),
+        (
+            status = 500,
+            description = "The form is valid, but there is no logged-in user.",
+            body = String,
+        ),
+    ),
+)]
+pub async fn publish_newsletter_outside_admin(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    publish_newsletter(form, pool, user_id).await
+}
+
+/// Store a new issue as a draft.
 #[tracing::instrument(skip_all)]
-async fn insert_newsletter_issue(
+pub(crate) async fn insert_newsletter_issue(
     transaction: &mut Transaction<'_, Postgres>,
-    title: &str,
-    text_content: &str,
-    html_content: &str,
+    form: &FormData,
+    segment: &Segment,
+    template_id: Option<Uuid>,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
+    let markdown = form.markdown();
     sqlx::query!(
         r#"
         INSERT INTO newsletter_issues (
@@ -81,38 +126,103 @@ async fn insert_newsletter_issue(
             title, 
             text_content, 
             html_content,
-            published_at
+            status,
+            segment_include_tags,
+            segment_exclude_tags,
+            segment_subscribed_after,
+            segment_subscribed_before,
+            is_public,
+            markdown_content,
+            template_id
         )
-        VALUES ($1, $2, $3, $4, now())
+        VALUES ($1, $2, $3, $4, 'draft', $5, $6, $7, $8, $9, $10, $11)
         "#,
         newsletter_issue_id,
-        title,
-        text_content,
-        html_content
+        form.title,
+        form.text_content,
+        form.html_content,
+        &segment.include_tags()[..],
+        &segment.exclude_tags()[..],
+        segment.subscribed_after,
+        segment.subscribed_before,
+        form.is_public,
+        markdown.as_ref().map(|m| m.as_ref()),
+        template_id,
     )
-    .execute(transaction)
+    .execute(&mut *transaction)
     .await?;
+    assign_slug(transaction, &form.title, newsletter_issue_id).await?;
     Ok(newsletter_issue_id)
 }
 
-#[tracing::instrument(skip_all)]
-async fn enqueue_delivery_tasks(
+/// Give the issue a slug for the web archive, derived from its title.
+/// Two issues with the same title can be saved at the same time: the
+/// loser of the race hits the unique index and tries the next suffix.
+#[tracing::instrument(skip(transaction))]
+pub(super) async fn assign_slug(
     transaction: &mut Transaction<'_, Postgres>,
+    title: &str,
     newsletter_issue_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"
-        INSERT INTO issue_delivery_queue (
-            newsletter_issue_id, 
-            subscriber_email
+) -> Result<IssueSlug, sqlx::Error> {
+    loop {
+        let slug = unique_slug(transaction, title, newsletter_issue_id).await?;
+        // A failed statement aborts the whole transaction, unless it ran
+        // within a savepoint.
+        let mut savepoint = transaction.begin().await?;
+        let result = sqlx::query!(
+            r#"
+            UPDATE newsletter_issues
+            SET slug = $2
+            WHERE newsletter_issue_id = $1
+            "#,
+            newsletter_issue_id,
+            slug.as_ref(),
         )
-        SELECT $1, email
-        FROM subscriptions
-        WHERE status = 'confirmed'
+        .execute(&mut savepoint)
+        .await;
+        match result {
+            Ok(_) => {
+                savepoint.commit().await?;
+                return Ok(slug);
+            }
+            Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23505") => {
+                savepoint.rollback().await?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Derive a slug for the web archive from the issue title,
+/// adding a numeric suffix if another issue already uses it.
+#[tracing::instrument(skip(transaction))]
+async fn unique_slug(
+    transaction: &mut Transaction<'_, Postgres>,
+    title: &str,
+    newsletter_issue_id: Uuid,
+) -> Result<IssueSlug, sqlx::Error> {
+    let base = IssueSlug::from_title(title);
+    let taken: Vec<String> = sqlx::query!(
+        r#"
+        SELECT slug AS "slug!"
+        FROM newsletter_issues
+        WHERE
+            newsletter_issue_id <> $1 AND
+            (slug = $2 OR slug LIKE $2 || '-%')
         "#,
         newsletter_issue_id,
+        base.as_ref(),
     )
-    .execute(transaction)
-    .await?;
-    Ok(())
+    .fetch_all(&mut *transaction)
+    .await?
+    .into_iter()
+    .map(|r| r.slug)
+    .collect();
+    let mut slug = base.clone();
+    let mut n = 2;
+    while taken.iter().any(|t| t == slug.as_ref()) {
+        slug = base.with_suffix(n);
+        n += 1;
+    }
+    Ok(slug)
 }
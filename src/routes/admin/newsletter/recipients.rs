@@ -0,0 +1,74 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use super::form::FormData;
+use super::get::render_publish_form;
+use crate::authentication::CsrfToken;
+use crate::domain::Segment;
+use crate::routes::get_template_names;
+use crate::utils::e500;
+
+/// Re-render the publish form, keeping what the author typed,
+/// together with the number of subscribers matching the chosen segment.
+pub async fn count_newsletter_recipients(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let recipients_html = match form.segment() {
+        Ok(segment) => {
+            let n = count_segment_recipients(&pool, &segment)
+                .await
+                .map_err(e500)?;
+            recipients_message(n)
+        }
+        Err(e) => format!("<p><i>{}</i></p>", htmlescape::encode_minimal(&e)),
+    };
+    let templates = get_template_names(&pool).await.map_err(e500)?;
+    Ok(render_publish_form(
+        "",
+        &form,
+        &recipients_html,
+        &templates,
+        &csrf_token,
+    ))
+}
+
+pub fn recipients_message(n: i64) -> String {
+    format!("<p>This issue will be delivered to <b>{n}</b> confirmed subscriber(s).</p>")
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn count_segment_recipients(
+    pool: &PgPool,
+    segment: &Segment,
+) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM subscriptions s
+        WHERE
+            s.status = 'confirmed' AND
+            (
+                cardinality($1::text[]) = 0 OR
+                EXISTS (
+                    SELECT 1 FROM subscriber_tags t
+                    WHERE t.subscriber_id = s.id AND t.tag = ANY($1)
+                )
+            ) AND
+            NOT EXISTS (
+                SELECT 1 FROM subscriber_tags t
+                WHERE t.subscriber_id = s.id AND t.tag = ANY($2)
+            ) AND
+            ($3::timestamptz IS NULL OR s.subscribed_at >= $3) AND
+            ($4::timestamptz IS NULL OR s.subscribed_at < $4)
+        "#,
+        &segment.include_tags()[..],
+        &segment.exclude_tags()[..],
+        segment.subscribed_after,
+        segment.subscribed_before,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(row.count)
+}
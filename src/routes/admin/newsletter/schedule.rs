@@ -0,0 +1,121 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::utils::{e500, issue_preview_page};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    scheduled_for: String,
+}
+
+/// Schedule a draft, or move an already scheduled issue to a new time.
+#[tracing::instrument(name = "Schedule a newsletter issue", skip(form, pool))]
+pub async fn schedule_issue(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let scheduled_for = match parse_scheduled_for(&form.scheduled_for, Utc::now()) {
+        Ok(t) => t,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(issue_preview_page(issue_id));
+        }
+    };
+    let updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'scheduled', scheduled_for = $2
+        WHERE newsletter_issue_id = $1 AND status IN ('draft', 'scheduled')
+        "#,
+        issue_id,
+        scheduled_for,
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to schedule the newsletter issue")
+    .map_err(e500)?;
+    if updated.rows_affected() == 0 {
+        FlashMessage::error("Only drafts and scheduled issues can be scheduled.").send();
+    } else {
+        FlashMessage::info(format!(
+            "The issue will be published on {}.",
+            scheduled_for.format("%Y-%m-%d at %H:%M UTC")
+        ))
+        .send();
+    }
+    Ok(issue_preview_page(issue_id))
+}
+
+/// Cancel a scheduled publication, turning the issue back into a draft.
+#[tracing::instrument(name = "Unschedule a newsletter issue", skip(pool))]
+pub async fn unschedule_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'draft', scheduled_for = NULL
+        WHERE newsletter_issue_id = $1 AND status = 'scheduled'
+        "#,
+        issue_id,
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to unschedule the newsletter issue")
+    .map_err(e500)?;
+    if updated.rows_affected() == 0 {
+        FlashMessage::error("The issue is not scheduled anymore.").send();
+    } else {
+        FlashMessage::info("The schedule has been cancelled. The issue is a draft again.").send();
+    }
+    Ok(issue_preview_page(issue_id))
+}
+
+/// Parse the value of a `datetime-local` input, interpreted as UTC.
+fn parse_scheduled_for(s: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let s = s.trim();
+    let t = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S"))
+        .map_err(|_| format!("{} is not a valid date and time.", s))?;
+    let t = Utc.from_utc_datetime(&t);
+    if t <= now {
+        return Err("Issues can only be scheduled in the future.".into());
+    }
+    Ok(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_scheduled_for;
+    use chrono::{TimeZone, Utc};
+    use claim::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn datetime_local_values_are_parsed_as_utc() {
+        let now = Utc.with_ymd_and_hms(2022, 10, 14, 17, 0, 0).unwrap();
+        let monday = Utc.with_ymd_and_hms(2022, 10, 17, 8, 0, 0).unwrap();
+        assert_ok_eq!(parse_scheduled_for("2022-10-17T08:00", now), monday);
+        assert_ok_eq!(parse_scheduled_for("2022-10-17T08:00:00", now), monday);
+    }
+
+    #[test]
+    fn times_in_the_past_are_rejected() {
+        let now = Utc.with_ymd_and_hms(2022, 10, 14, 17, 0, 0).unwrap();
+        assert_err!(parse_scheduled_for("2022-10-14T17:00", now));
+        assert_err!(parse_scheduled_for("2022-10-13T08:00", now));
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let now = Utc.with_ymd_and_hms(2022, 10, 14, 17, 0, 0).unwrap();
+        assert_err!(parse_scheduled_for("next monday", now));
+    }
+}
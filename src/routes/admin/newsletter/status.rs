@@ -0,0 +1,284 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::domain::IssueStatus;
+use crate::utils::e500;
+
+/// How far along the delivery of an issue is.
+#[derive(Debug, Default)]
+pub(crate) struct DeliveryProgress {
+    pub(crate) sent: i64,
+    pub(crate) failed: i64,
+    pub(crate) skipped: i64,
+    pub(crate) queued: i64,
+    pub(crate) cancelled: i64,
+}
+
+impl DeliveryProgress {
+    fn attempted(&self) -> i64 {
+        self.sent + self.failed + self.skipped
+    }
+
+    pub(crate) fn total(&self) -> i64 {
+        self.attempted() + self.queued + self.cancelled
+    }
+
+    /// Share of the deliveries that are done with, either way.
+    pub(crate) fn percentage(&self) -> i64 {
+        match self.total() {
+            0 => 100,
+            total => (self.attempted() + self.cancelled) * 100 / total,
+        }
+    }
+
+    /// Estimate the time left from the pace kept so far.
+    fn eta(&self, elapsed: Duration) -> Option<Duration> {
+        if self.attempted() == 0 || self.queued == 0 {
+            return None;
+        }
+        let per_delivery = elapsed.num_milliseconds() / self.attempted();
+        Some(Duration::milliseconds(per_delivery * self.queued))
+    }
+}
+
+/// An issue, along with how far along its delivery is.
+pub(crate) struct IssueDelivery {
+    pub(crate) title: String,
+    pub(crate) status: IssueStatus,
+    pub(crate) published_at: Option<DateTime<Utc>>,
+    pub(crate) progress: DeliveryProgress,
+}
+
+struct FailedDelivery {
+    subscriber_email: String,
+    outcome: String,
+    error: Option<String>,
+}
+
+pub async fn delivery_status(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let issue = get_issue_delivery(&pool, issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown newsletter issue."))?;
+    let failures = get_failed_deliveries(&pool, issue_id).await.map_err(e500)?;
+    let IssueDelivery {
+        title,
+        status,
+        published_at,
+        progress,
+    } = issue;
+    let eta_html = match (status, published_at) {
+        (IssueStatus::Sending, Some(published_at)) => {
+            match progress.eta(Utc::now() - published_at) {
+                Some(eta) => format!("<p>Estimated time left: {}.</p>", format_duration(eta)),
+                None => String::new(),
+            }
+        }
+        _ => String::new(),
+    };
+    let published_at = published_at
+        .map(|d: DateTime<Utc>| d.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| "not yet".into());
+    let mut failures_html = String::new();
+    for f in failures {
+        writeln!(
+            failures_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+            encode_minimal(&f.subscriber_email),
+            f.outcome,
+            encode_minimal(f.error.as_deref().unwrap_or_default()),
+        )
+        .unwrap();
+    }
+    let title = encode_minimal(&title);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Delivery status: {title}</title>
+</head>
+<body>
+    <h1>{title}</h1>
+    <p>Status: {status}. Published: {published_at}.</p>
+    <p>Progress: {percentage}% of {total} email(s).</p>
+    {eta_html}
+    <ul>
+        <li>Sent: {sent}</li>
+        <li>Failed: {failed}</li>
+        <li>Skipped (invalid email): {skipped}</li>
+        <li>Queued: {queued}</li>
+        <li>Cancelled: {cancelled}</li>
+    </ul>
+    <h2>Failed recipients</h2>
+    <table>
+        <tr><th>Email</th><th>Outcome</th><th>Error</th></tr>
+        {failures_html}
+    </table>
+    <p><a href="/admin/newsletters/issues/{issue_id}/preview">View the issue</a></p>
+    <p><a href="/admin/newsletters/issues">&lt;- Back</a></p>
+</body>
+</html>"#,
+            percentage = progress.percentage(),
+            total = progress.total(),
+            sent = progress.sent,
+            failed = progress.failed,
+            skipped = progress.skipped,
+            queued = progress.queued,
+            cancelled = progress.cancelled,
+        )))
+}
+
+fn format_duration(d: Duration) -> String {
+    if d.num_minutes() < 1 {
+        "less than a minute".into()
+    } else if d.num_hours() < 1 {
+        format!("about {} minute(s)", d.num_minutes())
+    } else {
+        format!(
+            "about {} hour(s) and {} minute(s)",
+            d.num_hours(),
+            d.num_minutes() % 60
+        )
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub(crate) async fn get_issue_delivery(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Option<IssueDelivery>, anyhow::Error> {
+    let issue = sqlx::query!(
+        r#"
+        SELECT
+            i.title,
+            i.status,
+            i.published_at,
+            COALESCE(i.cancelled_deliveries, 0) AS "cancelled!",
+            (
+                SELECT COUNT(*) FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.newsletter_issue_id
+            ) AS "queued!",
+            (
+                SELECT COUNT(*) FROM issue_delivery_log l
+                WHERE l.newsletter_issue_id = i.newsletter_issue_id AND l.outcome = 'sent'
+            ) AS "sent!",
+            (
+                SELECT COUNT(*) FROM issue_delivery_log l
+                WHERE l.newsletter_issue_id = i.newsletter_issue_id AND l.outcome = 'failed'
+            ) AS "failed!",
+            (
+                SELECT COUNT(*) FROM issue_delivery_log l
+                WHERE
+                    l.newsletter_issue_id = i.newsletter_issue_id AND
+                    l.outcome = 'skipped_invalid_email'
+            ) AS "skipped!"
+        FROM newsletter_issues i
+        WHERE i.newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the delivery status")?;
+    let Some(issue) = issue else {
+        return Ok(None);
+    };
+    Ok(Some(IssueDelivery {
+        title: issue.title,
+        status: issue.status.try_into().map_err(anyhow::Error::msg)?,
+        published_at: issue.published_at,
+        progress: DeliveryProgress {
+            sent: issue.sent,
+            failed: issue.failed,
+            skipped: issue.skipped,
+            queued: issue.queued,
+            cancelled: issue.cancelled.into(),
+        },
+    }))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_failed_deliveries(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Vec<FailedDelivery>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        FailedDelivery,
+        r#"
+        SELECT subscriber_email, outcome, error
+        FROM issue_delivery_log
+        WHERE newsletter_issue_id = $1 AND outcome <> 'sent'
+        ORDER BY attempted_at
+        "#,
+        issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the failed deliveries")?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_duration, DeliveryProgress};
+    use chrono::Duration;
+    use claim::{assert_none, assert_some_eq};
+
+    #[test]
+    fn progress_counts_every_delivery_that_is_done_with() {
+        let progress = DeliveryProgress {
+            sent: 5,
+            failed: 2,
+            skipped: 1,
+            queued: 8,
+            cancelled: 0,
+        };
+        assert_eq!(progress.total(), 16);
+        assert_eq!(progress.percentage(), 50);
+    }
+
+    #[test]
+    fn an_issue_without_deliveries_is_complete() {
+        assert_eq!(DeliveryProgress::default().percentage(), 100);
+    }
+
+    #[test]
+    fn eta_extrapolates_the_pace_so_far() {
+        let progress = DeliveryProgress {
+            sent: 10,
+            queued: 30,
+            ..Default::default()
+        };
+        assert_some_eq!(progress.eta(Duration::minutes(5)), Duration::minutes(15));
+    }
+
+    #[test]
+    fn there_is_no_eta_before_the_first_delivery() {
+        let progress = DeliveryProgress {
+            queued: 30,
+            ..Default::default()
+        };
+        assert_none!(progress.eta(Duration::minutes(5)));
+    }
+
+    #[test]
+    fn durations_are_rounded_for_humans() {
+        assert_eq!(format_duration(Duration::seconds(42)), "less than a minute");
+        assert_eq!(
+            format_duration(Duration::minutes(75)),
+            "about 1 hour(s) and 15 minute(s)"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use actix_web::{web, HttpResponse};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+
+use super::form::FormData;
+use super::get::render_publish_form;
+use crate::authentication::CsrfToken;
+use crate::configuration::NewsletterSettings;
+use crate::email_client::EmailClient;
+use crate::routes::get_template_names;
+use crate::utils::e500;
+
+/// Email what is currently in the publish form to the configured reviewers.
+/// Nothing is stored: no issue is created and no delivery is queued.
+#[tracing::instrument(name = "Send a test copy of a newsletter issue", skip_all)]
+pub async fn send_test_copy(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    newsletter: web::Data<NewsletterSettings>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let form = form.into_inner().generate_content();
+    form.checked_template_id(&pool).await?;
+    let reviewers = newsletter.reviewer_emails().map_err(e500)?;
+    let templates = get_template_names(&pool).await.map_err(e500)?;
+    if reviewers.is_empty() {
+        return Ok(render_publish_form(
+            "<p><i>No reviewer addresses are configured.</i></p>",
+            &form,
+            "",
+            &templates,
+            &csrf_token,
+        ));
+    }
+    let (html_content, text_content) = form.preview_content(&pool).await.map_err(e500)?;
+    let subject = format!("[TEST] {}", form.title);
+    let mut failed = Vec::new();
+    for reviewer in &reviewers {
+        if let Err(e) = email_client
+            .send_email(reviewer, &subject, &html_content, &text_content)
+            .await
+        {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send a test copy to {}",
+                reviewer
+            );
+            failed.push(reviewer.as_ref());
+        }
+    }
+    let msg_html = if failed.is_empty() {
+        let reviewers: Vec<&str> = reviewers.iter().map(|r| r.as_ref()).collect();
+        format!(
+            "<p><i>A test copy has been sent to {}.</i></p>",
+            encode_minimal(&reviewers.join(", "))
+        )
+    } else {
+        format!(
+            "<p><i>Failed to send a test copy to {}.</i></p>",
+            encode_minimal(&failed.join(", "))
+        )
+    };
+    Ok(render_publish_form(
+        &msg_html,
+        &form,
+        "",
+        &templates,
+        &csrf_token,
+    ))
+}
@@ -0,0 +1,78 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::post::assign_slug;
+use crate::utils::{e500, issue_preview_page};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    is_public: bool,
+}
+
+/// Add an issue to the public web archive, or remove it.
+#[tracing::instrument(name = "Change the visibility of a newsletter issue", skip(form, pool))]
+pub async fn set_issue_visibility(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let issue = sqlx::query!(
+        r#"
+        SELECT title, slug
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        FOR UPDATE
+        "#,
+        issue_id
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to retrieve the newsletter issue")
+    .map_err(e500)?
+    .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown newsletter issue."))?;
+    // Issues published before the web archive existed have no slug yet.
+    if issue.slug.is_none() {
+        assign_slug(&mut transaction, &issue.title, issue_id)
+            .await
+            .context("Failed to generate a slug")
+            .map_err(e500)?;
+    }
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            is_public = $2,
+            visibility_changed_at = CASE
+                WHEN is_public = $2 THEN visibility_changed_at
+                ELSE now()
+            END
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id,
+        form.is_public,
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to change the visibility of the issue")
+    .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to change the visibility of an issue")
+        .map_err(e500)?;
+    if form.is_public {
+        FlashMessage::info("The issue is now listed in the web archive.").send();
+    } else {
+        FlashMessage::info("The issue has been removed from the web archive.").send();
+    }
+    Ok(issue_preview_page(issue_id))
+}
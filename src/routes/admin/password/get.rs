@@ -1,8 +1,9 @@
-use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web::{http::header::ContentType, web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
 use std::fmt::Write;
 
 use crate::{
+    authentication::CsrfToken,
     session_state::TypedSession,
     utils::{e500, login_page},
 };
@@ -10,6 +11,7 @@ use crate::{
 pub async fn change_password_form(
     session: TypedSession,
     flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
 ) -> Result<HttpResponse, actix_web::Error> {
     if session.get_user_id().map_err(e500)?.is_none() {
         return Ok(login_page());
@@ -18,6 +20,7 @@ pub async fn change_password_form(
     for m in flash_messages.iter() {
         writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
     }
+    let csrf_field = csrf_token.form_field();
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(format!(
@@ -31,6 +34,7 @@ pub async fn change_password_form(
         <body>
         {msg_html}
         <form action="/admin/password" method="post">
+        {csrf_field}
         <label>Current password
         <input
         type="password"
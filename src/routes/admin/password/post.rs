@@ -1,11 +1,15 @@
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
-use secrecy::{ExposeSecret, Secret};
+use secrecy::Secret;
 use sqlx::PgPool;
 
 use crate::{
-    authentication::{validate_credentials, AuthError, Credentials, UserId},
+    authentication::{
+        revoke_other_sessions, validate_credentials, validate_new_password, AuthError, Credentials,
+        UserId,
+    },
     routes::get_username,
+    session_state::TypedSession,
     utils::{change_password_page, e500},
 };
 
@@ -20,24 +24,10 @@ pub async fn change_password(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
     user_id: web::ReqData<UserId>,
+    session: TypedSession,
 ) -> Result<HttpResponse, actix_web::Error> {
-    // passwords should be longer than 12 characters but shorter than 128 characters.
-    let password_len = form.new_password.expose_secret().len();
-    if password_len < 12 || password_len > 128 {
-        FlashMessage::error(
-            "new passwords should be longer than 12 characters but shorter than 128 characters.",
-        )
-        .send();
-        return Ok(change_password_page());
-    }
-
-    // `Secret<String>` does not implement `Eq`,
-    // therefore we need to compare the underlying `String`.
-    if form.new_password.expose_secret() != form.new_password_check.expose_secret() {
-        FlashMessage::error(
-            "You entered two different new passwords - the field values must match.",
-        )
-        .send();
+    if let Err(e) = validate_new_password(&form.new_password, &form.new_password_check) {
+        FlashMessage::error(e).send();
         return Ok(change_password_page());
     }
     let user_id = user_id.into_inner();
@@ -53,13 +43,18 @@ pub async fn change_password(
                 FlashMessage::error("The current password is incorrect.").send();
                 Ok(change_password_page())
             }
-            AuthError::UnexpectedError(_) => Err(e500(e).into()),
+            AuthError::UnexpectedError(_) => Err(e500(e)),
         };
     }
 
     crate::authentication::change_password(*user_id, form.0.new_password, &pool)
         .await
         .map_err(e500)?;
+    // Whoever else was logged in with the old password is logged out.
+    let current_session = session.get_session_id().map_err(e500)?;
+    revoke_other_sessions(*user_id, current_session, &pool)
+        .await
+        .map_err(e500)?;
     FlashMessage::error("Your password has been changed.").send();
     Ok(change_password_page())
 }
@@ -0,0 +1,74 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{list_user_sessions, CsrfToken, UserId};
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+
+/// The browsers the current user is logged in with.
+pub async fn list_sessions(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let current_session = session.get_session_id().map_err(e500)?;
+    let sessions = list_user_sessions(**user_id, &pool).await.map_err(e500)?;
+    let csrf_field = csrf_token.form_field();
+    let mut rows_html = String::new();
+    for s in sessions {
+        // The current session is ended with the logout button instead.
+        let action = if Some(s.session_id) == current_session {
+            "This browser".to_string()
+        } else {
+            format!(
+                r#"<form action="/admin/sessions/{}/delete" method="post">{}<button type="submit">Revoke</button></form>"#,
+                s.session_id, csrf_field
+            )
+        };
+        writeln!(
+            rows_html,
+            r#"<tr><td title="{}">{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+            encode_attribute(&s.user_agent),
+            encode_minimal(&s.device),
+            encode_minimal(&s.ip_address),
+            s.created_at.format("%Y-%m-%d %H:%M UTC"),
+            s.last_seen_at.format("%Y-%m-%d %H:%M UTC"),
+            action,
+        )
+        .unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Active sessions</title>
+</head>
+<body>
+    {msg_html}
+    <p>You are logged in with these browsers. Revoke the ones you do not
+    recognise, and change your password.</p>
+    <table>
+        <tr><th>Device</th><th>IP address</th><th>Logged in</th><th>Last seen</th><th></th></tr>
+        {rows_html}
+    </table>
+    <form action="/admin/sessions/delete" method="post">
+        {csrf_field}
+        <button type="submit">Log out everywhere</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
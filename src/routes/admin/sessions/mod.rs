@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::list_sessions;
+pub use post::{log_out_everywhere, remove_session};
@@ -0,0 +1,38 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{revoke_other_sessions, revoke_session, UserId};
+use crate::session_state::TypedSession;
+use crate::utils::{e500, login_page, sessions_page};
+
+/// Log one of the user's other browsers out, on its next request.
+pub async fn remove_session(
+    session_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !revoke_session(**user_id, session_id.into_inner(), &pool)
+        .await
+        .map_err(e500)?
+    {
+        return Err(actix_web::error::ErrorNotFound("Unknown session."));
+    }
+    FlashMessage::info("The session has been revoked.").send();
+    Ok(sessions_page())
+}
+
+/// Revoke every session of the user, this one included.
+pub async fn log_out_everywhere(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    revoke_other_sessions(**user_id, None, &pool)
+        .await
+        .map_err(e500)?;
+    session.logout();
+    FlashMessage::info("You have been logged out everywhere.").send();
+    Ok(login_page())
+}
@@ -0,0 +1,114 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::CsrfToken;
+use crate::utils::e500;
+
+struct SubscriberRow {
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    tags: Vec<String>,
+}
+
+pub async fn list_subscribers(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let subscribers = get_subscribers(&pool).await.map_err(e500)?;
+    let mut rows_html = String::new();
+    for s in subscribers {
+        writeln!(
+            rows_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            encode_minimal(&s.email),
+            encode_minimal(&s.name),
+            s.status,
+            s.subscribed_at.format("%Y-%m-%d"),
+            encode_minimal(&s.tags.join(", ")),
+        )
+        .unwrap();
+    }
+    let csrf_field = csrf_token.form_field();
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Subscribers</title>
+</head>
+<body>
+    {msg_html}
+    <table>
+        <tr><th>Email</th><th>Name</th><th>Status</th><th>Signed up</th><th>Tags</th></tr>
+        {rows_html}
+    </table>
+    <form action="/admin/subscribers/tags" method="post">
+        {csrf_field}
+        <label>Subscriber email
+            <input type="text" placeholder="Enter the subscriber email" name="email">
+        </label>
+        <br>
+        <label>Tags
+            <input type="text" placeholder="Comma-separated, e.g. rust, beta-readers" name="tags">
+        </label>
+        <br>
+        <button type="submit" name="action" value="add">Add tags</button>
+        <button type="submit" name="action" value="remove">Remove tags</button>
+    </form>
+    <form action="/admin/subscribers/import" method="post">
+        {csrf_field}
+        <label>Subscribers to import
+            <textarea placeholder="One per line, e.g. ursula@example.com, Ursula" name="subscribers" rows="10" cols="50"></textarea>
+        </label>
+        <br>
+        <label>Tag them with
+            <input type="text" placeholder="Comma-separated, e.g. rust, beta-readers" name="tags">
+        </label>
+        <br>
+        <button type="submit">Import</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_subscribers(pool: &PgPool) -> Result<Vec<SubscriberRow>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        SubscriberRow,
+        r#"
+        SELECT
+            s.email,
+            s.name,
+            s.status,
+            s.subscribed_at,
+            COALESCE(
+                array_agg(t.tag ORDER BY t.tag) FILTER (WHERE t.tag IS NOT NULL),
+                '{}'
+            ) AS "tags!"
+        FROM subscriptions s
+        LEFT JOIN subscriber_tags t ON t.subscriber_id = s.id
+        GROUP BY s.id
+        ORDER BY s.subscribed_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the list of subscribers.")?;
+    Ok(rows)
+}
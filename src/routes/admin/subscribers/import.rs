@@ -0,0 +1,101 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+
+use super::post::get_subscriber_id;
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriberTag};
+use crate::email_client::EmailClient;
+use crate::routes::{add_subscriber_tags, register_subscriber};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{e500, subscribers_page};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    // One subscriber per line, as `email, name`.
+    subscribers: String,
+    tags: String,
+}
+
+/// Add a list of subscribers, tagging all of them. New subscribers get the
+/// confirmation email, as if they had used the subscribe form; the ones
+/// already on the list only get the tags.
+#[tracing::instrument(name = "Import subscribers", skip_all)]
+pub async fn import_subscribers(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let tags = match SubscriberTag::parse_list(&form.tags) {
+        Ok(tags) => tags,
+        Err(e) => {
+            FlashMessage::error(encode_minimal(&e)).send();
+            return Ok(subscribers_page());
+        }
+    };
+    let mut imported = 0;
+    let mut tagged = 0;
+    let mut rejected = Vec::new();
+    for (i, line) in form.subscribers.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let new_subscriber = match parse_line(line, &tags) {
+            Ok(new_subscriber) => new_subscriber,
+            Err(e) => {
+                rejected.push(format!("Line {}: {}", i + 1, encode_minimal(&e)));
+                continue;
+            }
+        };
+        match get_subscriber_id(&pool, new_subscriber.email.as_ref())
+            .await
+            .map_err(e500)?
+        {
+            Some(subscriber_id) => {
+                let mut transaction = pool
+                    .begin()
+                    .await
+                    .context("Failed to acquire a Postgres connection from the pool")
+                    .map_err(e500)?;
+                add_subscriber_tags(&mut transaction, subscriber_id, &tags)
+                    .await
+                    .context("Failed to tag the subscriber")
+                    .map_err(e500)?;
+                transaction
+                    .commit()
+                    .await
+                    .context("Failed to commit SQL transaction to tag a subscriber")
+                    .map_err(e500)?;
+                tagged += 1;
+            }
+            None => {
+                register_subscriber(&pool, &email_client, &base_url.0, new_subscriber)
+                    .await
+                    .map_err(e500)?;
+                imported += 1;
+            }
+        }
+    }
+    FlashMessage::info(format!(
+        "{} subscribers have been imported and {} existing ones tagged.",
+        imported, tagged
+    ))
+    .send();
+    for message in rejected {
+        FlashMessage::error(message).send();
+    }
+    Ok(subscribers_page())
+}
+
+fn parse_line(line: &str, tags: &[SubscriberTag]) -> Result<NewSubscriber, String> {
+    let (email, name) = line
+        .split_once(',')
+        .ok_or_else(|| "expected `email, name`.".to_string())?;
+    Ok(NewSubscriber {
+        email: SubscriberEmail::parse(email.trim().to_string())?,
+        name: SubscriberName::parse(name.trim().to_string())?,
+        tags: tags.to_vec(),
+    })
+}
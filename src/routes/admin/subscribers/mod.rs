@@ -0,0 +1,7 @@
+mod get;
+mod import;
+mod post;
+
+pub use get::list_subscribers;
+pub use import::import_subscribers;
+pub use post::tag_subscriber;
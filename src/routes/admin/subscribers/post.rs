@@ -0,0 +1,112 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::SubscriberTag;
+use crate::routes::add_subscriber_tags;
+use crate::utils::{e500, subscribers_page};
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagAction {
+    Add,
+    Remove,
+}
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+    tags: String,
+    action: TagAction,
+}
+
+#[tracing::instrument(name = "Tag a subscriber manually", skip_all, fields(subscriber_email=%form.email))]
+pub async fn tag_subscriber(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let tags = match SubscriberTag::parse_list(&form.tags) {
+        Ok(tags) if !tags.is_empty() => tags,
+        Ok(_) => {
+            FlashMessage::error("Please provide at least one tag.").send();
+            return Ok(subscribers_page());
+        }
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(subscribers_page());
+        }
+    };
+    let subscriber_id = match get_subscriber_id(&pool, form.email.trim())
+        .await
+        .map_err(e500)?
+    {
+        Some(id) => id,
+        None => {
+            FlashMessage::error(format!("There is no subscriber with email {}.", form.email))
+                .send();
+            return Ok(subscribers_page());
+        }
+    };
+    match form.action {
+        TagAction::Add => {
+            let mut transaction = pool
+                .begin()
+                .await
+                .context("Failed to acquire a Postgres connection from the pool")
+                .map_err(e500)?;
+            add_subscriber_tags(&mut transaction, subscriber_id, &tags)
+                .await
+                .context("Failed to tag the subscriber")
+                .map_err(e500)?;
+            transaction
+                .commit()
+                .await
+                .context("Failed to commit SQL transaction to tag a subscriber")
+                .map_err(e500)?;
+            FlashMessage::info("The tags have been added.").send();
+        }
+        TagAction::Remove => {
+            remove_subscriber_tags(&pool, subscriber_id, &tags)
+                .await
+                .context("Failed to untag the subscriber")
+                .map_err(e500)?;
+            FlashMessage::info("The tags have been removed.").send();
+        }
+    }
+    Ok(subscribers_page())
+}
+
+#[tracing::instrument(skip(pool))]
+pub(super) async fn get_subscriber_id(pool: &PgPool, email: &str) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id FROM subscriptions WHERE email = $1
+        "#,
+        email
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.id))
+}
+
+#[tracing::instrument(skip(pool, tags))]
+async fn remove_subscriber_tags(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    tags: &[SubscriberTag],
+) -> Result<(), sqlx::Error> {
+    let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+    sqlx::query!(
+        r#"
+        DELETE FROM subscriber_tags
+        WHERE subscriber_id = $1 AND tag = ANY($2)
+        "#,
+        subscriber_id,
+        &tags[..],
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use super::post::TemplateForm;
+use crate::authentication::CsrfToken;
+use crate::domain::MERGE_FIELDS;
+use crate::utils::e500;
+
+struct TemplateRow {
+    template_id: Uuid,
+    name: String,
+    updated_at: DateTime<Utc>,
+}
+
+pub async fn list_templates(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let templates = sqlx::query_as!(
+        TemplateRow,
+        r#"
+        SELECT template_id, name, updated_at
+        FROM email_templates
+        ORDER BY name
+        "#
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve the email templates.")
+    .map_err(e500)?;
+    let mut rows_html = String::new();
+    for t in templates {
+        writeln!(
+            rows_html,
+            r#"<tr><td>{}</td><td>{}</td><td><a href="/admin/templates/{}">Edit</a></td></tr>"#,
+            encode_minimal(&t.name),
+            t.updated_at.format("%Y-%m-%d %H:%M UTC"),
+            t.template_id,
+        )
+        .unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Email templates</title>
+</head>
+<body>
+    {msg_html}
+    <table>
+        <tr><th>Name</th><th>Last updated</th><th></th></tr>
+        {rows_html}
+    </table>
+    <p><a href="/admin/templates/new">New template</a></p>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+pub async fn new_template_form(csrf_token: web::ReqData<CsrfToken>) -> HttpResponse {
+    let form = TemplateForm {
+        name: String::new(),
+        html_layout: r#"{{ content }}
+<p><a href="{{ unsubscribe_url }}">Unsubscribe</a></p>"#
+            .into(),
+        text_layout: "{{ content }}\n\nUnsubscribe: {{ unsubscribe_url }}".into(),
+    };
+    render_template_form("", &form, None, &csrf_token)
+}
+
+pub async fn edit_template_form(
+    template_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let template_id = template_id.into_inner();
+    let form = sqlx::query_as!(
+        TemplateForm,
+        r#"
+        SELECT name, html_layout, text_layout
+        FROM email_templates
+        WHERE template_id = $1
+        "#,
+        template_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to retrieve the email template.")
+    .map_err(e500)?
+    .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown email template."))?;
+    Ok(render_template_form("", &form, Some(template_id), &csrf_token))
+}
+
+pub(super) fn render_template_form(
+    msg_html: &str,
+    form: &TemplateForm,
+    template_id: Option<Uuid>,
+    csrf_token: &CsrfToken,
+) -> HttpResponse {
+    let name = encode_attribute(&form.name);
+    let html_layout = encode_minimal(&form.html_layout);
+    let text_layout = encode_minimal(&form.text_layout);
+    let action = match template_id {
+        Some(template_id) => format!("/admin/templates/{template_id}"),
+        None => "/admin/templates".into(),
+    };
+    let merge_fields: Vec<String> = MERGE_FIELDS
+        .iter()
+        .map(|f| format!("<code>{{{{ {f} }}}}</code>"))
+        .collect();
+    let merge_fields = merge_fields.join(", ");
+    let csrf_field = csrf_token.form_field();
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Email template</title>
+</head>
+<body>
+    {msg_html}
+    <p>Layouts wrap the content of issues, which replaces <code>{{{{ content }}}}</code>.
+    Layouts and issues can use these merge fields: {merge_fields}.
+    Every layout must link to <code>{{{{ unsubscribe_url }}}}</code> or <code>{{{{ preferences_url }}}}</code>.</p>
+    <form action="{action}" method="post">
+        {csrf_field}
+        <label>Name:<br>
+            <input type="text" placeholder="Enter the template name" name="name" value="{name}">
+        </label>
+        <br>
+        <label>HTML layout:<br>
+            <textarea name="html_layout" rows="20" cols="50">{html_layout}</textarea>
+        </label>
+        <br>
+        <label>Plain text layout:<br>
+            <textarea name="text_layout" rows="20" cols="50">{text_layout}</textarea>
+        </label>
+        <br>
+        <button type="submit">Save template</button>
+    </form>
+    <p><a href="/admin/templates">&lt;- Back</a></p>
+</body>
+</html>"#,
+        ))
+}
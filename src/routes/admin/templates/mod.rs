@@ -0,0 +1,67 @@
+mod get;
+mod post;
+
+pub use get::{edit_template_form, list_templates, new_template_form};
+pub use post::{create_template, update_template};
+
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::EmailLayout;
+
+pub struct TemplateName {
+    pub template_id: Uuid,
+    pub name: String,
+}
+
+/// Every email template, to pick one for an issue.
+#[tracing::instrument(skip_all)]
+pub async fn get_template_names(pool: &PgPool) -> Result<Vec<TemplateName>, anyhow::Error> {
+    let names = sqlx::query_as!(
+        TemplateName,
+        r#"
+        SELECT template_id, name
+        FROM email_templates
+        ORDER BY name
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the email templates.")?;
+    Ok(names)
+}
+
+/// Whether there is an email template with this id.
+#[tracing::instrument(skip(pool))]
+pub async fn template_exists(pool: &PgPool, template_id: Uuid) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM email_templates WHERE template_id = $1) AS "exists!""#,
+        template_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to look up the email template.")?;
+    Ok(row.exists)
+}
+
+/// The layout of an email template, `None` if there is no such template.
+#[tracing::instrument(skip(pool))]
+pub async fn get_template_layout(
+    pool: &PgPool,
+    template_id: Uuid,
+) -> Result<Option<EmailLayout>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT html_layout, text_layout
+        FROM email_templates
+        WHERE template_id = $1
+        "#,
+        template_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the email template.")?;
+    row.map(|r| EmailLayout::parse(r.html_layout, r.text_layout).map_err(anyhow::Error::msg))
+        .transpose()
+}
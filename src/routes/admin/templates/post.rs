@@ -0,0 +1,114 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::get::render_template_form;
+use crate::authentication::CsrfToken;
+use crate::domain::EmailLayout;
+use crate::utils::{e500, templates_page};
+
+#[derive(serde::Deserialize)]
+pub struct TemplateForm {
+    pub name: String,
+    pub html_layout: String,
+    pub text_layout: String,
+}
+
+impl TemplateForm {
+    fn validate(&self) -> Result<EmailLayout, String> {
+        if self.name.trim().is_empty() {
+            return Err("Please give the template a name.".into());
+        }
+        EmailLayout::parse(self.html_layout.clone(), self.text_layout.clone())
+    }
+}
+
+#[tracing::instrument(name = "Create an email template", skip_all, fields(name=%form.name))]
+pub async fn create_template(
+    form: web::Form<TemplateForm>,
+    pool: web::Data<PgPool>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let layout = match form.validate() {
+        Ok(layout) => layout,
+        Err(e) => return Ok(render_error(&e, &form, None, &csrf_token)),
+    };
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO email_templates (template_id, name, html_layout, text_layout)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        Uuid::new_v4(),
+        form.name.trim(),
+        layout.html(),
+        layout.text(),
+    )
+    .execute(pool.get_ref())
+    .await;
+    saved(result, &form, None, &csrf_token)
+}
+
+#[tracing::instrument(name = "Update an email template", skip(form, pool))]
+pub async fn update_template(
+    template_id: web::Path<Uuid>,
+    form: web::Form<TemplateForm>,
+    pool: web::Data<PgPool>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let template_id = template_id.into_inner();
+    let layout = match form.validate() {
+        Ok(layout) => layout,
+        Err(e) => return Ok(render_error(&e, &form, Some(template_id), &csrf_token)),
+    };
+    let result = sqlx::query!(
+        r#"
+        UPDATE email_templates
+        SET name = $2, html_layout = $3, text_layout = $4, updated_at = now()
+        WHERE template_id = $1
+        "#,
+        template_id,
+        form.name.trim(),
+        layout.html(),
+        layout.text(),
+    )
+    .execute(pool.get_ref())
+    .await;
+    if matches!(&result, Ok(r) if r.rows_affected() == 0) {
+        return Err(actix_web::error::ErrorNotFound("Unknown email template."));
+    }
+    saved(result, &form, Some(template_id), &csrf_token)
+}
+
+/// Go back to the list of templates, unless the name is already taken.
+fn saved(
+    result: Result<sqlx::postgres::PgQueryResult, sqlx::Error>,
+    form: &TemplateForm,
+    template_id: Option<Uuid>,
+    csrf_token: &CsrfToken,
+) -> Result<HttpResponse, actix_web::Error> {
+    match result {
+        Ok(_) => {
+            FlashMessage::info("The template has been saved.").send();
+            Ok(templates_page())
+        }
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23505") => {
+            let e = format!("There is already a template named {}.", form.name.trim());
+            Ok(render_error(&e, form, template_id, csrf_token))
+        }
+        Err(e) => Err(e500(
+            anyhow::Error::new(e).context("Failed to save the email template"),
+        )),
+    }
+}
+
+fn render_error(
+    e: &str,
+    form: &TemplateForm,
+    template_id: Option<Uuid>,
+    csrf_token: &CsrfToken,
+) -> HttpResponse {
+    let msg_html = format!("<p><i>{}</i></p>", encode_minimal(e));
+    render_template_form(&msg_html, form, template_id, csrf_token)
+}
@@ -0,0 +1,86 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{
+    count_recovery_codes, generate_totp_secret, has_two_factor, qr_code_svg, totp_uri, CsrfToken,
+    UserId,
+};
+use crate::routes::get_username;
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+
+pub async fn two_factor_settings(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    user_id: web::ReqData<UserId>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let csrf_field = csrf_token.form_field();
+    let body_html = if has_two_factor(*user_id, &pool).await.map_err(e500)? {
+        let recovery_codes = count_recovery_codes(*user_id, &pool).await.map_err(e500)?;
+        format!(
+            r#"<p>Two-factor authentication is enabled. You have {recovery_codes} unused recovery code(s).</p>
+    <form action="/admin/two-factor/disable" method="post">
+        {csrf_field}
+        <label>Enter a code from your authenticator app, or a recovery code, to disable it:<br>
+            <input type="text" name="code" autocomplete="one-time-code">
+        </label>
+        <button type="submit">Disable two-factor authentication</button>
+    </form>"#
+        )
+    } else {
+        // Keep showing the same secret until it is confirmed, so that a
+        // mistyped code does not mean scanning a new QR code.
+        let secret = match session.get_totp_enrolment_secret().map_err(e500)? {
+            Some(secret) => secret,
+            None => {
+                let secret = generate_totp_secret();
+                session
+                    .insert_totp_enrolment_secret(&secret)
+                    .map_err(e500)?;
+                secret
+            }
+        };
+        let username = get_username(*user_id, &pool).await.map_err(e500)?;
+        let qr_code = qr_code_svg(&totp_uri(&secret, "zero2prod", &username)).map_err(e500)?;
+        format!(
+            r#"<p>Two-factor authentication is disabled.
+    To enable it, scan this QR code with your authenticator app, or enter the secret by hand.</p>
+    {qr_code}
+    <p>Secret: <code id="totp-secret">{}</code></p>
+    <form action="/admin/two-factor" method="post">
+        {csrf_field}
+        <label>Enter the code shown by the app:<br>
+            <input type="text" name="code" autocomplete="one-time-code">
+        </label>
+        <button type="submit">Enable two-factor authentication</button>
+    </form>"#,
+            secret.expose_secret()
+        )
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Two-factor authentication</title>
+</head>
+<body>
+    {msg_html}
+    {body_html}
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::two_factor_settings;
+pub use post::{enrol_two_factor, unenrol_two_factor};
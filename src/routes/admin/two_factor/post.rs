@@ -0,0 +1,95 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{
+    disable_two_factor, enable_two_factor, validate_second_factor, UserId,
+};
+use crate::session_state::TypedSession;
+use crate::startup::HmacSecret;
+use crate::utils::{e500, two_factor_page};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    code: Secret<String>,
+}
+
+/// Confirm the secret shown on the settings page with a code from the
+/// authenticator app, then show the recovery codes - only this once.
+#[tracing::instrument(skip(form, pool, session, hmac_secret))]
+pub async fn enrol_two_factor(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    hmac_secret: web::Data<HmacSecret>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let secret = match session.get_totp_enrolment_secret().map_err(e500)? {
+        Some(secret) => secret,
+        None => return Ok(two_factor_page()),
+    };
+    let recovery_codes = match enable_two_factor(
+        *user_id,
+        &secret,
+        form.code.expose_secret(),
+        &hmac_secret,
+        &pool,
+    )
+    .await
+    .map_err(e500)?
+    {
+        Some(recovery_codes) => recovery_codes,
+        None => {
+            FlashMessage::error("The code is invalid, please try again.").send();
+            return Ok(two_factor_page());
+        }
+    };
+    session.remove_totp_enrolment_secret();
+    let mut codes_html = String::new();
+    for code in recovery_codes {
+        writeln!(codes_html, "<li><code>{code}</code></li>").unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Recovery codes</title>
+</head>
+<body>
+    <p>Two-factor authentication is enabled.</p>
+    <p>Keep these recovery codes somewhere safe. Each of them lets you log in once
+    without your authenticator app, and they will not be shown again.</p>
+    <ul>
+        {codes_html}
+    </ul>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(skip(form, pool, hmac_secret))]
+pub async fn unenrol_two_factor(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    if !validate_second_factor(*user_id, &form.code, &hmac_secret, &pool)
+        .await
+        .map_err(e500)?
+    {
+        FlashMessage::error("The code is invalid, please try again.").send();
+        return Ok(two_factor_page());
+    }
+    disable_two_factor(*user_id, &pool).await.map_err(e500)?;
+    FlashMessage::info("Two-factor authentication has been disabled.").send();
+    Ok(two_factor_page())
+}
@@ -0,0 +1,155 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{CsrfToken, Role, UserId, INVITATION_LIFETIME_DAYS};
+use crate::utils::e500;
+
+/// The admin users and their roles. Owners cannot change their own role or
+/// remove themselves, so there is always at least one owner left.
+pub async fn list_users(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let users = sqlx::query!(
+        r#"
+        SELECT user_id, username, role
+        FROM users
+        ORDER BY username
+        "#
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve the users.")
+    .map_err(e500)?;
+    let csrf_field = csrf_token.form_field();
+    let mut rows_html = String::new();
+    for u in users {
+        let username = encode_minimal(&u.username);
+        if u.user_id == **user_id {
+            writeln!(
+                rows_html,
+                "<tr><td>{} (you)</td><td>{}</td><td></td><td></td></tr>",
+                username, u.role
+            )
+            .unwrap();
+            continue;
+        }
+        writeln!(
+            rows_html,
+            r#"<tr><td>{username}</td><td>{role}</td><td><form action="/admin/users/{id}/role" method="post">{csrf_field}<select name="role">{options}</select><button type="submit">Change role</button></form></td><td><form action="/admin/users/{id}/delete" method="post">{csrf_field}<button type="submit">Remove</button></form></td></tr>"#,
+            role = u.role,
+            options = role_options(&u.role),
+            id = u.user_id,
+        )
+        .unwrap();
+    }
+    let invitations = sqlx::query!(
+        r#"
+        SELECT invitation_id, email, role, expires_at
+        FROM invitations
+        WHERE accepted_at IS NULL AND expires_at > now()
+        ORDER BY expires_at
+        "#
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve the pending invitations.")
+    .map_err(e500)?;
+    let mut invitations_html = String::new();
+    for i in invitations {
+        writeln!(
+            invitations_html,
+            r#"<tr><td>{}</td><td>{}</td><td>{}</td><td><form action="/admin/users/invitations/{}/delete" method="post">{}<button type="submit">Revoke</button></form></td></tr>"#,
+            encode_minimal(&i.email),
+            i.role,
+            i.expires_at.format("%Y-%m-%d %H:%M UTC"),
+            i.invitation_id,
+            csrf_field,
+        )
+        .unwrap();
+    }
+    let options = role_options(Role::Editor.as_str());
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Users</title>
+</head>
+<body>
+    {msg_html}
+    <p>Viewers can look at everything but only change their own account.
+    Editors can also write and send issues and manage subscribers and emails.
+    Owners can also manage users.</p>
+    <table>
+        <tr><th>Username</th><th>Role</th><th></th><th></th></tr>
+        {rows_html}
+    </table>
+    <h2>Invite a user</h2>
+    <p>We email them a link to pick their username and password.
+    It expires after {lifetime} days.</p>
+    <form action="/admin/users/invitations" method="post">
+        {csrf_field}
+        <label>Email
+            <input type="email" placeholder="Enter their email address" name="email">
+        </label>
+        <br>
+        <label>Role
+            <select name="role">{options}</select>
+        </label>
+        <br>
+        <button type="submit">Send invitation</button>
+    </form>
+    <table>
+        <tr><th>Invited</th><th>Role</th><th>Expires</th><th></th></tr>
+        {invitations_html}
+    </table>
+    <h2>Add a user</h2>
+    <form action="/admin/users" method="post">
+        {csrf_field}
+        <label>Username
+            <input type="text" placeholder="Enter a username" name="username">
+        </label>
+        <br>
+        <label>Password
+            <input type="password" placeholder="Enter a password" name="password">
+        </label>
+        <br>
+        <label>Role
+            <select name="role">{options}</select>
+        </label>
+        <br>
+        <button type="submit">Add</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+            lifetime = INVITATION_LIFETIME_DAYS,
+        )))
+}
+
+fn role_options(selected: &str) -> String {
+    Role::ALL
+        .iter()
+        .map(|role| {
+            let selected = if role.as_str() == selected {
+                " selected"
+            } else {
+                ""
+            };
+            format!(r#"<option value="{role}"{selected}>{role}</option>"#)
+        })
+        .collect()
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::list_users;
+pub use post::{add_user, change_user_role, invite_user, remove_user, withdraw_invitation};
@@ -0,0 +1,203 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use htmlescape::encode_minimal;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{
+    create_invitation, create_user, revoke_invitation, validate_new_password, Role, UserId,
+    INVITATION_LIFETIME_DAYS,
+};
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{e500, users_page};
+
+#[derive(serde::Deserialize)]
+pub struct NewUserForm {
+    username: String,
+    password: Secret<String>,
+    role: String,
+}
+
+#[tracing::instrument(name = "Add a user", skip(form, pool), fields(username = %form.username))]
+pub async fn add_user(
+    form: web::Form<NewUserForm>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let NewUserForm {
+        username,
+        password,
+        role,
+    } = form.into_inner();
+    let username = username.trim();
+    if username.is_empty() {
+        FlashMessage::error("The username cannot be empty.").send();
+        return Ok(users_page());
+    }
+    if let Err(e) = validate_new_password(&password, &password) {
+        FlashMessage::error(e).send();
+        return Ok(users_page());
+    }
+    let role = match Role::parse(&role) {
+        Ok(role) => role,
+        Err(e) => {
+            FlashMessage::error(encode_minimal(&e)).send();
+            return Ok(users_page());
+        }
+    };
+    match create_user(username, password, role, &pool)
+        .await
+        .map_err(e500)?
+    {
+        Some(_) => {
+            FlashMessage::info(format!("{} has been added.", encode_minimal(username))).send()
+        }
+        None => FlashMessage::error(format!(
+            "The username {} is already taken.",
+            encode_minimal(username)
+        ))
+        .send(),
+    }
+    Ok(users_page())
+}
+
+#[derive(serde::Deserialize)]
+pub struct UserRoleForm {
+    role: String,
+}
+
+#[tracing::instrument(name = "Change the role of a user", skip(form, pool, current_user_id))]
+pub async fn change_user_role(
+    user_id: web::Path<Uuid>,
+    form: web::Form<UserRoleForm>,
+    pool: web::Data<PgPool>,
+    current_user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    if user_id == **current_user_id {
+        FlashMessage::error("You cannot change your own role.").send();
+        return Ok(users_page());
+    }
+    let role = match Role::parse(&form.role) {
+        Ok(role) => role,
+        Err(e) => {
+            FlashMessage::error(encode_minimal(&e)).send();
+            return Ok(users_page());
+        }
+    };
+    let result = sqlx::query!(
+        "UPDATE users SET role = $1 WHERE user_id = $2",
+        role.as_str(),
+        user_id
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to change the role of the user.")
+    .map_err(e500)?;
+    if result.rows_affected() == 0 {
+        return Err(actix_web::error::ErrorNotFound("Unknown user."));
+    }
+    FlashMessage::info("The role has been changed.").send();
+    Ok(users_page())
+}
+
+#[tracing::instrument(name = "Remove a user", skip(pool, current_user_id))]
+pub async fn remove_user(
+    user_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    current_user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    if user_id == **current_user_id {
+        FlashMessage::error("You cannot remove yourself.").send();
+        return Ok(users_page());
+    }
+    sqlx::query!("DELETE FROM users WHERE user_id = $1", user_id)
+        .execute(pool.get_ref())
+        .await
+        .context("Failed to remove the user.")
+        .map_err(e500)?;
+    FlashMessage::info("The user has been removed.").send();
+    Ok(users_page())
+}
+
+#[derive(serde::Deserialize)]
+pub struct InvitationForm {
+    email: String,
+    role: String,
+}
+
+#[tracing::instrument(
+    name = "Invite a user",
+    skip(form, pool, email_client, base_url, user_id),
+    fields(email = %form.email)
+)]
+pub async fn invite_user(
+    form: web::Form<InvitationForm>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let InvitationForm { email, role } = form.into_inner();
+    let email = match SubscriberEmail::parse(email.trim().to_string()) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(users_page());
+        }
+    };
+    let role = match Role::parse(&role) {
+        Ok(role) => role,
+        Err(e) => {
+            FlashMessage::error(encode_minimal(&e)).send();
+            return Ok(users_page());
+        }
+    };
+    let token = create_invitation(&email, role, **user_id, &pool)
+        .await
+        .map_err(e500)?;
+    let invitation_link = format!(
+        "{}/login/accept-invitation?token={}",
+        base_url.0,
+        token.expose_secret()
+    );
+    let html_body = format!(
+        r#"<p>You have been invited to help run our newsletter, as {role}.</p>
+<p><a href="{invitation_link}">Create your account</a>. The link expires in {INVITATION_LIFETIME_DAYS} days.</p>"#
+    );
+    let text_body = format!(
+        "You have been invited to help run our newsletter, as {role}.\n\n\
+        Visit {invitation_link} to create your account. \
+        The link expires in {INVITATION_LIFETIME_DAYS} days."
+    );
+    if let Err(e) = email_client
+        .send_email(&email, "You are invited", &html_body, &text_body)
+        .await
+    {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to send an invitation.",
+        );
+        FlashMessage::error(format!("The invitation could not be sent to {}.", email)).send();
+    } else {
+        FlashMessage::info(format!("An invitation has been sent to {}.", email)).send();
+    }
+    Ok(users_page())
+}
+
+#[tracing::instrument(name = "Withdraw an invitation", skip(pool))]
+pub async fn withdraw_invitation(
+    invitation_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    revoke_invitation(invitation_id.into_inner(), &pool)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("The invitation has been revoked.").send();
+    Ok(users_page())
+}
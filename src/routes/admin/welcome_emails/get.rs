@@ -0,0 +1,172 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use super::post::WelcomeEmailForm;
+use crate::authentication::CsrfToken;
+use crate::domain::MERGE_FIELDS;
+use crate::utils::e500;
+
+struct WelcomeEmailRow {
+    welcome_email_id: Uuid,
+    delay_days: i32,
+    subject: String,
+}
+
+pub async fn list_welcome_emails(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let emails = sqlx::query_as!(
+        WelcomeEmailRow,
+        r#"
+        SELECT welcome_email_id, delay_days, subject
+        FROM welcome_emails
+        ORDER BY delay_days, subject
+        "#
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve the welcome emails.")
+    .map_err(e500)?;
+    let csrf_field = csrf_token.form_field();
+    let mut rows_html = String::new();
+    for e in emails {
+        writeln!(
+            rows_html,
+            r#"<tr><td>{}</td><td>{}</td><td><a href="/admin/welcome-emails/{id}">Edit</a></td><td><form action="/admin/welcome-emails/{id}/delete" method="post">{csrf_field}<button type="submit">Delete</button></form></td></tr>"#,
+            e.delay_days,
+            encode_minimal(&e.subject),
+            id = e.welcome_email_id,
+        )
+        .unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Welcome sequence</title>
+</head>
+<body>
+    {msg_html}
+    <p>New subscribers receive these emails once they confirm their subscription,
+    after the given number of days. The sequence stops if they unsubscribe.</p>
+    <table>
+        <tr><th>Days after confirmation</th><th>Subject</th><th></th><th></th></tr>
+        {rows_html}
+    </table>
+    <p><a href="/admin/welcome-emails/new">New welcome email</a></p>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+pub async fn new_welcome_email_form(csrf_token: web::ReqData<CsrfToken>) -> HttpResponse {
+    let form = WelcomeEmailForm {
+        delay_days: "0".into(),
+        subject: String::new(),
+        html_content: String::new(),
+        text_content: String::new(),
+    };
+    render_welcome_email_form("", &form, None, &csrf_token)
+}
+
+pub async fn edit_welcome_email_form(
+    welcome_email_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let welcome_email_id = welcome_email_id.into_inner();
+    let r = sqlx::query!(
+        r#"
+        SELECT delay_days, subject, html_content, text_content
+        FROM welcome_emails
+        WHERE welcome_email_id = $1
+        "#,
+        welcome_email_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to retrieve the welcome email.")
+    .map_err(e500)?
+    .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown welcome email."))?;
+    let form = WelcomeEmailForm {
+        delay_days: r.delay_days.to_string(),
+        subject: r.subject,
+        html_content: r.html_content,
+        text_content: r.text_content,
+    };
+    Ok(render_welcome_email_form("", &form, Some(welcome_email_id), &csrf_token))
+}
+
+pub(super) fn render_welcome_email_form(
+    msg_html: &str,
+    form: &WelcomeEmailForm,
+    welcome_email_id: Option<Uuid>,
+    csrf_token: &CsrfToken,
+) -> HttpResponse {
+    let delay_days = encode_attribute(&form.delay_days);
+    let subject = encode_attribute(&form.subject);
+    let html_content = encode_minimal(&form.html_content);
+    let text_content = encode_minimal(&form.text_content);
+    let action = match welcome_email_id {
+        Some(welcome_email_id) => format!("/admin/welcome-emails/{welcome_email_id}"),
+        None => "/admin/welcome-emails".into(),
+    };
+    let merge_fields: Vec<String> = MERGE_FIELDS
+        .iter()
+        .map(|f| format!("<code>{{{{ {f} }}}}</code>"))
+        .collect();
+    let merge_fields = merge_fields.join(", ");
+    let csrf_field = csrf_token.form_field();
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Welcome email</title>
+</head>
+<body>
+    {msg_html}
+    <p>Welcome emails can use these merge fields: {merge_fields}.
+    Leave the plain text content empty to generate it from the HTML content.</p>
+    <form action="{action}" method="post">
+        {csrf_field}
+        <label>Days after confirmation:<br>
+            <input type="number" min="0" name="delay_days" value="{delay_days}">
+        </label>
+        <br>
+        <label>Subject:<br>
+            <input type="text" placeholder="Enter the subject" name="subject" value="{subject}">
+        </label>
+        <br>
+        <label>HTML content:<br>
+            <textarea name="html_content" rows="20" cols="50">{html_content}</textarea>
+        </label>
+        <br>
+        <label>Plain text content:<br>
+            <textarea name="text_content" rows="20" cols="50">{text_content}</textarea>
+        </label>
+        <br>
+        <button type="submit">Save welcome email</button>
+    </form>
+    <p><a href="/admin/welcome-emails">&lt;- Back</a></p>
+</body>
+</html>"#,
+        ))
+}
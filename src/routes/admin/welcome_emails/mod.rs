@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::{edit_welcome_email_form, list_welcome_emails, new_welcome_email_form};
+pub use post::{create_welcome_email, delete_welcome_email, update_welcome_email};
@@ -0,0 +1,152 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::get::render_welcome_email_form;
+use crate::authentication::CsrfToken;
+use crate::html_to_text::html_to_text;
+use crate::utils::{e500, welcome_emails_page};
+
+#[derive(serde::Deserialize)]
+pub struct WelcomeEmailForm {
+    pub delay_days: String,
+    pub subject: String,
+    pub html_content: String,
+    #[serde(default)]
+    pub text_content: String,
+}
+
+struct WelcomeEmail {
+    delay_days: i32,
+    subject: String,
+    html_content: String,
+    text_content: String,
+}
+
+impl WelcomeEmailForm {
+    fn validate(&self) -> Result<WelcomeEmail, String> {
+        let delay_days = match self.delay_days.trim().parse() {
+            Ok(days) if days >= 0 => days,
+            _ => return Err("The delay must be a whole number of days.".into()),
+        };
+        if self.subject.trim().is_empty() {
+            return Err("The subject cannot be empty.".into());
+        }
+        if self.html_content.trim().is_empty() {
+            return Err("The HTML content cannot be empty.".into());
+        }
+        let text_content = match self.text_content.trim() {
+            "" => html_to_text(&self.html_content),
+            _ => self.text_content.clone(),
+        };
+        Ok(WelcomeEmail {
+            delay_days,
+            subject: self.subject.trim().into(),
+            html_content: self.html_content.clone(),
+            text_content,
+        })
+    }
+}
+
+#[tracing::instrument(name = "Create a welcome email", skip_all)]
+pub async fn create_welcome_email(
+    form: web::Form<WelcomeEmailForm>,
+    pool: web::Data<PgPool>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email = match form.validate() {
+        Ok(email) => email,
+        Err(e) => return Ok(render_error(&e, &form, None, &csrf_token)),
+    };
+    sqlx::query!(
+        r#"
+        INSERT INTO welcome_emails (
+            welcome_email_id, delay_days, subject, html_content, text_content
+        )
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        Uuid::new_v4(),
+        email.delay_days,
+        email.subject,
+        email.html_content,
+        email.text_content,
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to save the welcome email.")
+    .map_err(e500)?;
+    FlashMessage::info("The welcome email has been saved.").send();
+    Ok(welcome_emails_page())
+}
+
+/// Subscribers already in the sequence keep the date their email was
+/// queued for; only its content changes.
+#[tracing::instrument(name = "Update a welcome email", skip(form, pool))]
+pub async fn update_welcome_email(
+    welcome_email_id: web::Path<Uuid>,
+    form: web::Form<WelcomeEmailForm>,
+    pool: web::Data<PgPool>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let welcome_email_id = welcome_email_id.into_inner();
+    let email = match form.validate() {
+        Ok(email) => email,
+        Err(e) => return Ok(render_error(&e, &form, Some(welcome_email_id), &csrf_token)),
+    };
+    let result = sqlx::query!(
+        r#"
+        UPDATE welcome_emails
+        SET delay_days = $2, subject = $3, html_content = $4, text_content = $5
+        WHERE welcome_email_id = $1
+        "#,
+        welcome_email_id,
+        email.delay_days,
+        email.subject,
+        email.html_content,
+        email.text_content,
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to save the welcome email.")
+    .map_err(e500)?;
+    if result.rows_affected() == 0 {
+        return Err(actix_web::error::ErrorNotFound("Unknown welcome email."));
+    }
+    FlashMessage::info("The welcome email has been saved.").send();
+    Ok(welcome_emails_page())
+}
+
+/// Remove an email from the sequence, including for subscribers who are
+/// still waiting for it.
+#[tracing::instrument(name = "Delete a welcome email", skip(pool))]
+pub async fn delete_welcome_email(
+    welcome_email_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM welcome_emails
+        WHERE welcome_email_id = $1
+        "#,
+        welcome_email_id.into_inner(),
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to delete the welcome email.")
+    .map_err(e500)?;
+    FlashMessage::info("The welcome email has been deleted.").send();
+    Ok(welcome_emails_page())
+}
+
+fn render_error(
+    e: &str,
+    form: &WelcomeEmailForm,
+    welcome_email_id: Option<Uuid>,
+    csrf_token: &CsrfToken,
+) -> HttpResponse {
+    let msg_html = format!("<p><i>{}</i></p>", encode_minimal(e));
+    render_welcome_email_form(&msg_html, form, welcome_email_id, csrf_token)
+}
@@ -0,0 +1,128 @@
+use actix_web::http::header::WWW_AUTHENTICATE;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+
+use crate::routes::{error_chain_fmt, SubscriberError, TemplateIdError};
+
+/// The errors of the JSON API. Clients get them as
+/// `{"error": {"code": ..., "message": ...}}`.
+#[derive(thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    ValidationError(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ApiError {
+    /// One error of each kind, to describe them in the OpenAPI document.
+    pub(crate) fn kinds() -> [ApiError; 6] {
+        [
+            ApiError::Unauthorized(String::new()),
+            ApiError::Forbidden(String::new()),
+            ApiError::NotFound(String::new()),
+            ApiError::Conflict(String::new()),
+            ApiError::ValidationError(String::new()),
+            ApiError::UnexpectedError(anyhow::anyhow!("")),
+        ]
+    }
+
+    pub(crate) fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::Unauthorized(_) => ErrorCode::Unauthorized,
+            ApiError::Forbidden(_) => ErrorCode::Forbidden,
+            ApiError::NotFound(_) => ErrorCode::NotFound,
+            ApiError::Conflict(_) => ErrorCode::Conflict,
+            ApiError::ValidationError(_) => ErrorCode::ValidationError,
+            ApiError::UnexpectedError(_) => ErrorCode::UnexpectedError,
+        }
+    }
+}
+
+/// The body of every error response of the API.
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct ErrorResponse {
+    error: ErrorDetails,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct ErrorDetails {
+    code: ErrorCode,
+    message: String,
+}
+
+/// What went wrong, for clients to act upon: the message is meant for
+/// humans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    ValidationError,
+    UnexpectedError,
+}
+
+impl std::fmt::Debug for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            ApiError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        let mut resp = HttpResponse::build(self.status_code());
+        if let ApiError::Unauthorized(_) = self {
+            resp.insert_header((WWW_AUTHENTICATE, r#"Bearer realm="api""#));
+        }
+        // The cause of unexpected errors is logged, not shown to the client.
+        let message = match self {
+            ApiError::UnexpectedError(_) => "Something went wrong.".to_string(),
+            e => e.to_string(),
+        };
+        resp.json(ErrorResponse {
+            error: ErrorDetails {
+                code: self.code(),
+                message,
+            },
+        })
+    }
+}
+
+impl From<SubscriberError> for ApiError {
+    fn from(e: SubscriberError) -> Self {
+        match e {
+            SubscriberError::ValidationError(e) => ApiError::ValidationError(e),
+            e => ApiError::UnexpectedError(anyhow::Error::new(e)),
+        }
+    }
+}
+
+impl From<TemplateIdError> for ApiError {
+    fn from(e: TemplateIdError) -> Self {
+        match e {
+            TemplateIdError::ValidationError(e) => ApiError::ValidationError(e),
+            TemplateIdError::UnexpectedError(e) => ApiError::UnexpectedError(e),
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use actix_web::http::header::LOCATION;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{authorize, ApiError, ErrorResponse, Page, Pagination};
+use crate::authentication::{ApiScope, ApiToken, UserId};
+use crate::domain::IssueStatus;
+use crate::routes::{get_issue_delivery, insert_newsletter_issue, IssueDelivery, IssueFormData};
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct IssueSummary {
+    id: Uuid,
+    title: String,
+    #[schema(value_type = IssueStatus)]
+    status: String,
+    slug: Option<String>,
+    is_public: bool,
+    #[schema(format = DateTime)]
+    created_at: String,
+    #[schema(format = DateTime)]
+    scheduled_for: Option<String>,
+    #[schema(format = DateTime)]
+    published_at: Option<String>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct Issue {
+    #[serde(flatten)]
+    summary: IssueSummary,
+    text_content: String,
+    html_content: String,
+    markdown_content: Option<String>,
+    template_id: Option<Uuid>,
+    segment: IssueSegment,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct IssueSegment {
+    include_tags: Vec<String>,
+    exclude_tags: Vec<String>,
+    #[schema(format = DateTime)]
+    subscribed_after: Option<String>,
+    #[schema(format = DateTime)]
+    subscribed_before: Option<String>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct Delivery {
+    #[schema(value_type = IssueStatus)]
+    status: String,
+    #[schema(format = DateTime)]
+    published_at: Option<String>,
+    sent: i64,
+    failed: i64,
+    skipped: i64,
+    queued: i64,
+    cancelled: i64,
+    total: i64,
+    #[schema(minimum = 0, maximum = 100)]
+    percentage: i64,
+}
+
+impl From<IssueDelivery> for Delivery {
+    fn from(d: IssueDelivery) -> Self {
+        Self {
+            status: d.status.to_string(),
+            published_at: d.published_at.as_ref().map(DateTime::to_rfc3339),
+            total: d.progress.total(),
+            percentage: d.progress.percentage(),
+            sent: d.progress.sent,
+            failed: d.progress.failed,
+            skipped: d.progress.skipped,
+            queued: d.progress.queued,
+            cancelled: d.progress.cancelled,
+        }
+    }
+}
+
+struct IssueSummaryRow {
+    newsletter_issue_id: Uuid,
+    title: String,
+    status: String,
+    slug: Option<String>,
+    is_public: bool,
+    created_at: DateTime<Utc>,
+    scheduled_for: Option<DateTime<Utc>>,
+    published_at: Option<DateTime<Utc>>,
+}
+
+impl From<IssueSummaryRow> for IssueSummary {
+    fn from(row: IssueSummaryRow) -> Self {
+        Self {
+            id: row.newsletter_issue_id,
+            title: row.title,
+            status: row.status,
+            slug: row.slug,
+            is_public: row.is_public,
+            created_at: row.created_at.to_rfc3339(),
+            scheduled_for: row.scheduled_for.as_ref().map(DateTime::to_rfc3339),
+            published_at: row.published_at.as_ref().map(DateTime::to_rfc3339),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/issues",
+    summary = "List newsletter issues, most recent first.",
+    params(Pagination),
+    responses(
+        (status = 200, description = "OK.", body = Page<IssueSummary>),
+        (status = 400, description = "The page is out of range.", body = ErrorResponse),
+    ),
+    security(("bearerAuth" = ["issues:read"])),
+)]
+#[tracing::instrument(name = "List issues through the API", skip_all)]
+pub async fn api_list_issues(
+    pagination: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&api_token, **user_id, ApiScope::ReadIssues, &pool).await?;
+    pagination.validate()?;
+    let issues = sqlx::query_as!(
+        IssueSummaryRow,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            status,
+            slug,
+            is_public,
+            created_at,
+            scheduled_for,
+            published_at
+        FROM newsletter_issues
+        ORDER BY created_at DESC, newsletter_issue_id
+        LIMIT $1 OFFSET $2
+        "#,
+        pagination.per_page,
+        pagination.offset(),
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve the issues.")?
+    .into_iter()
+    .map(IssueSummary::from)
+    .collect();
+    let total = sqlx::query!(r#"SELECT COUNT(*) AS "n!" FROM newsletter_issues"#)
+        .fetch_one(pool.get_ref())
+        .await
+        .context("Failed to count the issues.")?
+        .n;
+    Ok(HttpResponse::Ok().json(Page::new(issues, &pagination, total)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/issues/{issue_id}",
+    summary = "Get a newsletter issue.",
+    params(("issue_id" = Uuid, Path)),
+    responses(
+        (status = 200, description = "OK.", body = Issue),
+        (status = 400, description = "The id is not a UUID.", body = ErrorResponse),
+        (status = 404, description = "There is no such issue.", body = ErrorResponse),
+    ),
+    security(("bearerAuth" = ["issues:read"])),
+)]
+#[tracing::instrument(name = "Get an issue through the API", skip(pool, user_id, api_token))]
+pub async fn api_get_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&api_token, **user_id, ApiScope::ReadIssues, &pool).await?;
+    let issue = get_issue(&pool, issue_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(issue))
+}
+
+/// Store a new draft, to be reviewed and sent from the admin area - or
+/// through `api_publish_newsletter` for issues that go out right away.
+#[utoipa::path(
+    post,
+    path = "/issues",
+    summary = "Store a draft.",
+    request_body = IssueFormData,
+    responses(
+        (status = 201, description = "The draft.", body = Issue),
+        (status = 400, description = "The title, segment or template is invalid.", body = ErrorResponse),
+    ),
+    security(("bearerAuth" = ["issues:write"])),
+)]
+#[tracing::instrument(
+    name = "Create an issue through the API",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn api_create_issue(
+    body: web::Json<IssueFormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&api_token, **user_id, ApiScope::WriteIssues, &pool).await?;
+    let form = body.into_inner().generate_content();
+    if form.title.trim().is_empty() {
+        return Err(ApiError::ValidationError("The issue needs a title.".into()));
+    }
+    let segment = form.segment().map_err(ApiError::ValidationError)?;
+    let template_id = form.checked_template_id(&pool).await?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a transaction.")?;
+    let issue_id = insert_newsletter_issue(&mut transaction, &form, &segment, template_id)
+        .await
+        .context("Failed to store newsletter issue details")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the newsletter issue.")?;
+    let issue = get_issue(&pool, issue_id).await?;
+    Ok(HttpResponse::Created()
+        .insert_header((LOCATION, format!("/api/v1/issues/{}", issue_id)))
+        .json(issue))
+}
+
+#[utoipa::path(
+    get,
+    path = "/issues/{issue_id}/delivery",
+    summary = "Follow the delivery of a newsletter issue.",
+    params(("issue_id" = Uuid, Path)),
+    responses(
+        (status = 200, description = "OK.", body = Delivery),
+        (status = 400, description = "The id is not a UUID.", body = ErrorResponse),
+        (status = 404, description = "There is no such issue.", body = ErrorResponse),
+    ),
+    security(("bearerAuth" = ["issues:read"])),
+)]
+#[tracing::instrument(
+    name = "Get the delivery of an issue through the API",
+    skip(pool, user_id, api_token)
+)]
+pub async fn api_get_issue_delivery(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&api_token, **user_id, ApiScope::ReadIssues, &pool).await?;
+    let delivery = get_issue_delivery(&pool, issue_id.into_inner())
+        .await?
+        .ok_or_else(unknown_issue)?;
+    Ok(HttpResponse::Ok().json(Delivery::from(delivery)))
+}
+
+fn unknown_issue() -> ApiError {
+    ApiError::NotFound("Unknown newsletter issue.".into())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<Issue, ApiError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            status,
+            slug,
+            is_public,
+            created_at,
+            scheduled_for,
+            published_at,
+            text_content,
+            html_content,
+            markdown_content,
+            template_id,
+            segment_include_tags,
+            segment_exclude_tags,
+            segment_subscribed_after,
+            segment_subscribed_before
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the issue.")?
+    .ok_or_else(unknown_issue)?;
+    Ok(Issue {
+        summary: IssueSummaryRow {
+            newsletter_issue_id: row.newsletter_issue_id,
+            title: row.title,
+            status: row.status,
+            slug: row.slug,
+            is_public: row.is_public,
+            created_at: row.created_at,
+            scheduled_for: row.scheduled_for,
+            published_at: row.published_at,
+        }
+        .into(),
+        text_content: row.text_content,
+        html_content: row.html_content,
+        markdown_content: row.markdown_content,
+        template_id: row.template_id,
+        segment: IssueSegment {
+            include_tags: row.segment_include_tags,
+            exclude_tags: row.segment_exclude_tags,
+            subscribed_after: row
+                .segment_subscribed_after
+                .as_ref()
+                .map(DateTime::to_rfc3339),
+            subscribed_before: row
+                .segment_subscribed_before
+                .as_ref()
+                .map(DateTime::to_rfc3339),
+        },
+    })
+}
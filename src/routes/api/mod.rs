@@ -0,0 +1,167 @@
+mod errors;
+mod issues;
+mod newsletters;
+mod openapi;
+mod subscribers;
+
+pub use errors::ApiError;
+use errors::ErrorResponse;
+pub use issues::{api_create_issue, api_get_issue, api_get_issue_delivery, api_list_issues};
+pub use newsletters::api_publish_newsletter;
+pub use openapi::{openapi_document, openapi_json};
+pub use subscribers::{
+    api_create_subscriber, api_delete_subscriber, api_get_subscriber, api_list_subscribers,
+    api_update_subscriber,
+};
+
+use actix_web::http::Method;
+use actix_web::{web, FromRequest, Handler, HttpRequest, Responder, Route};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{get_role, ApiScope, ApiToken};
+
+/// The routes of the JSON API, relative to `/api/v1`. They are listed here
+/// rather than in `startup`, so that the tests can check that the OpenAPI
+/// document describes each of them.
+pub fn api_routes() -> Vec<(Method, &'static str, Route)> {
+    vec![
+        route(Method::POST, "/newsletters", api_publish_newsletter),
+        route(Method::GET, "/subscribers", api_list_subscribers),
+        route(Method::POST, "/subscribers", api_create_subscriber),
+        route(
+            Method::GET,
+            "/subscribers/{subscriber_id}",
+            api_get_subscriber,
+        ),
+        route(
+            Method::PATCH,
+            "/subscribers/{subscriber_id}",
+            api_update_subscriber,
+        ),
+        route(
+            Method::DELETE,
+            "/subscribers/{subscriber_id}",
+            api_delete_subscriber,
+        ),
+        route(Method::GET, "/issues", api_list_issues),
+        route(Method::POST, "/issues", api_create_issue),
+        route(Method::GET, "/issues/{issue_id}", api_get_issue),
+        route(
+            Method::GET,
+            "/issues/{issue_id}/delivery",
+            api_get_issue_delivery,
+        ),
+    ]
+}
+
+fn route<F, Args>(method: Method, path: &'static str, handler: F) -> (Method, &'static str, Route)
+where
+    F: Handler<Args>,
+    Args: FromRequest + 'static,
+    F::Output: Responder + 'static,
+{
+    (method.clone(), path, web::method(method).to(handler))
+}
+
+/// Check that the token has the scope, and that its user still has the role
+/// the scope requires.
+async fn authorize(
+    api_token: &ApiToken,
+    user_id: Uuid,
+    scope: ApiScope,
+    pool: &PgPool,
+) -> Result<(), ApiError> {
+    if !api_token.has_scope(scope) {
+        return Err(ApiError::Forbidden(format!(
+            "The token does not have the `{}` scope.",
+            scope
+        )));
+    }
+    let role = get_role(user_id, pool)
+        .await?
+        .ok_or_else(|| ApiError::Unauthorized("The user of the token has been removed.".into()))?;
+    if role < scope.required_role() {
+        return Err(ApiError::Forbidden(format!(
+            "The `{}` scope needs the {} role, not {}.",
+            scope,
+            scope.required_role(),
+            role
+        )));
+    }
+    Ok(())
+}
+
+/// Reject malformed bodies, paths and query strings with a JSON error, like
+/// every other error of the API.
+pub fn reject_invalid_request<E: std::fmt::Display>(
+    e: E,
+    _request: &HttpRequest,
+) -> actix_web::Error {
+    ApiError::ValidationError(e.to_string()).into()
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Pagination {
+    #[serde(default = "Pagination::first_page")]
+    #[param(minimum = 1, default = Pagination::first_page)]
+    page: i64,
+    // `maximum` only takes a literal: keep it in line with `MAX_PER_PAGE`.
+    #[serde(default = "Pagination::default_per_page")]
+    #[param(minimum = 1, maximum = 100, default = Pagination::default_per_page)]
+    per_page: i64,
+}
+
+impl Pagination {
+    const MAX_PER_PAGE: i64 = 100;
+
+    fn first_page() -> i64 {
+        1
+    }
+
+    fn default_per_page() -> i64 {
+        20
+    }
+
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.page < 1 {
+            return Err(ApiError::ValidationError("`page` starts at 1.".into()));
+        }
+        if !(1..=Self::MAX_PER_PAGE).contains(&self.per_page) {
+            return Err(ApiError::ValidationError(format!(
+                "`per_page` must be between 1 and {}.",
+                Self::MAX_PER_PAGE
+            )));
+        }
+        // Past this, the offset of the page would not fit in an `i64`.
+        if self.page.checked_mul(self.per_page).is_none() {
+            return Err(ApiError::ValidationError("`page` is too large.".into()));
+        }
+        Ok(())
+    }
+
+    fn offset(&self) -> i64 {
+        (self.page - 1) * self.per_page
+    }
+}
+
+/// One page of a list, along with what is needed to fetch the others.
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct Page<T> {
+    items: Vec<T>,
+    page: i64,
+    per_page: i64,
+    total: i64,
+}
+
+impl<T> Page<T> {
+    fn new(items: Vec<T>, pagination: &Pagination, total: i64) -> Self {
+        Self {
+            items,
+            page: pagination.page,
+            per_page: pagination.per_page,
+            total,
+        }
+    }
+}
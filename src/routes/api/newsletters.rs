@@ -0,0 +1,102 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{authorize, ApiError, ErrorResponse};
+use crate::authentication::{ApiScope, ApiToken, UserId};
+use crate::idempotency::{save_response, try_processing, IdempotencyKey, NextAction};
+use crate::issue_delivery_worker::start_delivery;
+use crate::routes::{insert_newsletter_issue, IssueFormData};
+
+/// The JSON counterpart of `publish_newsletter`, for clients holding an API
+/// token. Requests repeated with the same `Idempotency-Key` header get the
+/// first response back instead of publishing the issue again.
+#[utoipa::path(
+    post,
+    path = "/newsletters",
+    summary = "Publish an issue right away.",
+    params(
+        (
+            "Idempotency-Key" = Option<String>,
+            Header,
+            description = "Retries with the same key get the first response back.",
+        ),
+    ),
+    request_body = IssueFormData,
+    responses(
+        (status = 202, description = "The issue is being delivered.", body = PublishedIssue),
+        (
+            status = 400,
+            description = "The segment, template or idempotency key is invalid.",
+            body = ErrorResponse,
+        ),
+    ),
+    security(("bearerAuth" = ["newsletters:publish"])),
+)]
+#[tracing::instrument(
+    name = "Publish a newsletter issue through the API",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn api_publish_newsletter(
+    request: HttpRequest,
+    body: web::Json<IssueFormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = user_id.into_inner();
+    authorize(&api_token, *user_id, ApiScope::PublishNewsletters, &pool).await?;
+    let form = body.into_inner().generate_content();
+    let segment = form.segment().map_err(ApiError::ValidationError)?;
+    let template_id = form.checked_template_id(&pool).await?;
+    let idempotency_key = idempotency_key(&request)?;
+    let mut transaction = match &idempotency_key {
+        Some(idempotency_key) => match try_processing(&pool, idempotency_key, *user_id).await? {
+            NextAction::StartProcessing(t) => t,
+            NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+        },
+        None => pool
+            .begin()
+            .await
+            .context("Failed to start a transaction.")?,
+    };
+    let issue_id = insert_newsletter_issue(&mut transaction, &form, &segment, template_id)
+        .await
+        .context("Failed to store newsletter issue details")?;
+    start_delivery(&mut transaction, issue_id)
+        .await
+        .context("Failed to enqueue delivery tasks")?;
+    let response = HttpResponse::Accepted().json(PublishedIssue { issue_id });
+    match idempotency_key {
+        Some(idempotency_key) => {
+            Ok(save_response(transaction, &idempotency_key, *user_id, response).await?)
+        }
+        None => {
+            transaction
+                .commit()
+                .await
+                .context("Failed to commit the newsletter issue.")?;
+            Ok(response)
+        }
+    }
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct PublishedIssue {
+    issue_id: Uuid,
+}
+
+fn idempotency_key(request: &HttpRequest) -> Result<Option<IdempotencyKey>, ApiError> {
+    let Some(header) = request.headers().get("Idempotency-Key") else {
+        return Ok(None);
+    };
+    let key = header
+        .to_str()
+        .map_err(|_| ApiError::ValidationError("The idempotency key must be ASCII.".into()))?
+        .to_owned()
+        .try_into()
+        .map_err(|e: anyhow::Error| ApiError::ValidationError(e.to_string()))?;
+    Ok(Some(key))
+}
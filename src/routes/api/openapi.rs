@@ -0,0 +1,164 @@
+use actix_web::{HttpResponse, ResponseError};
+use serde_json::Value;
+use std::sync::OnceLock;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{Content, Deprecated, Info, Ref, ResponseBuilder};
+use utoipa::{Modify, OpenApi, Path, ToSchema};
+
+use super::errors::ErrorResponse;
+use super::{issues, newsletters, subscribers, ApiError};
+use crate::authentication::ApiScope;
+use crate::routes::{
+    __path_confirm, __path_publish_newsletter, __path_publish_newsletter_outside_admin,
+    __path_subscribe,
+};
+
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(openapi_document())
+}
+
+/// The OpenAPI 3 description of the subscription endpoints and of the JSON
+/// API. Operations come from the `#[utoipa::path]` attribute of each handler
+/// and schemas from the structs the handlers read and write, so neither can
+/// drift from the code.
+///
+/// Built once, when the server starts, so that an operation asking for a
+/// scope that does not exist stops the server rather than a request.
+pub fn openapi_document() -> &'static Value {
+    static DOCUMENT: OnceLock<Value> = OnceLock::new();
+    DOCUMENT.get_or_init(|| {
+        let mut document = Document::openapi();
+        // Rather than the blank description and license of `Cargo.toml`.
+        document.info = Info::new("zero2prod", env!("CARGO_PKG_VERSION"));
+        serde_json::to_value(document).expect("The OpenAPI document is valid JSON.")
+    })
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        subscribe,
+        confirm,
+        publish_newsletter,
+        publish_newsletter_outside_admin,
+    ),
+    nest((path = "/api/v1", api = ApiV1)),
+    modifiers(&SecuritySchemes)
+)]
+struct Document;
+
+/// The routes of `api_routes`, relative to `/api/v1`.
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        newsletters::api_publish_newsletter,
+        subscribers::api_list_subscribers,
+        subscribers::api_create_subscriber,
+        subscribers::api_get_subscriber,
+        subscribers::api_update_subscriber,
+        subscribers::api_delete_subscriber,
+        issues::api_list_issues,
+        issues::api_create_issue,
+        issues::api_get_issue,
+        issues::api_get_issue_delivery,
+    ),
+    modifiers(&ApiTokens)
+)]
+struct ApiV1;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "cookieAuth",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::with_description(
+                "id",
+                "The session cookie set by `POST /login`.",
+            ))),
+        );
+        components.add_security_scheme(
+            "bearerAuth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("An API token, created from the admin area."))
+                    .build(),
+            ),
+        );
+        // `#[deprecated]` would also warn about the route serving it.
+        let legacy = openapi
+            .paths
+            .paths
+            .get_mut(&__path_publish_newsletter_outside_admin::path())
+            .and_then(|item| item.post.as_mut())
+            .expect("The route outside of `/admin` is documented.");
+        legacy.deprecated = Some(Deprecated::True);
+    }
+}
+
+/// Spell out what the scope of each operation asks of the token and of its
+/// user, and add the responses every API call can end with: a missing
+/// token, a missing scope or role, and unexpected errors.
+struct ApiTokens;
+
+impl Modify for ApiTokens {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for item in openapi.paths.paths.values_mut() {
+            let operations = [
+                &mut item.get,
+                &mut item.post,
+                &mut item.put,
+                &mut item.patch,
+                &mut item.delete,
+            ];
+            for operation in operations.into_iter().flatten() {
+                // The scopes of a security requirement are not readable
+                // from the outside: go through its JSON form.
+                let security = serde_json::to_value(&operation.security).unwrap();
+                let scope = security[0]["bearerAuth"][0]
+                    .as_str()
+                    .and_then(|scope| ApiScope::parse(scope).ok())
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "{:?} does not ask for the scope of an API token.",
+                            operation.operation_id
+                        )
+                    });
+                operation.description = Some(format!(
+                    "Needs the `{}` scope, and a token of a user with the {} role or above.",
+                    scope,
+                    scope.required_role()
+                ));
+                for error in ApiError::kinds() {
+                    if !matches!(
+                        error,
+                        ApiError::Unauthorized(_)
+                            | ApiError::Forbidden(_)
+                            | ApiError::UnexpectedError(_)
+                    ) {
+                        continue;
+                    }
+                    let status = error.status_code();
+                    let code = serde_json::to_value(error.code()).unwrap();
+                    let response = ResponseBuilder::new()
+                        .description(format!(
+                            "{} (`{}`).",
+                            status.canonical_reason().unwrap_or_default(),
+                            code.as_str().unwrap_or_default()
+                        ))
+                        .content(
+                            "application/json",
+                            Content::new(Some(Ref::from_schema_name(ErrorResponse::name()))),
+                        )
+                        .build();
+                    operation
+                        .responses
+                        .responses
+                        .insert(status.as_str().to_string(), response.into());
+                }
+            }
+        }
+    }
+}
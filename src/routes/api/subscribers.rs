@@ -0,0 +1,404 @@
+use actix_web::http::header::LOCATION;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{authorize, ApiError, ErrorResponse, Page, Pagination};
+use crate::authentication::{ApiScope, ApiToken, UserId};
+use crate::domain::{EmailFormat, NewSubscriber, SubscriberEmail, SubscriberName, SubscriberTag};
+use crate::email_client::EmailClient;
+use crate::routes::{add_subscriber_tags, register_subscriber, SubscriberError};
+use crate::startup::ApplicationBaseUrl;
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct Subscriber {
+    id: Uuid,
+    #[schema(format = Email)]
+    email: String,
+    name: String,
+    /// `pending_confirmation` or `confirmed`.
+    status: String,
+    #[schema(value_type = EmailFormat)]
+    email_format: String,
+    #[schema(format = DateTime)]
+    subscribed_at: String,
+    tags: Vec<String>,
+}
+
+struct SubscriberRow {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    email_format: String,
+    subscribed_at: DateTime<Utc>,
+    tags: Vec<String>,
+}
+
+impl From<SubscriberRow> for Subscriber {
+    fn from(row: SubscriberRow) -> Self {
+        Self {
+            id: row.id,
+            email: row.email,
+            name: row.name,
+            status: row.status,
+            email_format: row.email_format,
+            subscribed_at: row.subscribed_at.to_rfc3339(),
+            tags: row.tags,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/subscribers",
+    summary = "List subscribers, most recent first.",
+    params(Pagination),
+    responses(
+        (status = 200, description = "OK.", body = Page<Subscriber>),
+        (status = 400, description = "The page is out of range.", body = ErrorResponse),
+    ),
+    security(("bearerAuth" = ["subscribers:read"])),
+)]
+#[tracing::instrument(name = "List subscribers through the API", skip_all)]
+pub async fn api_list_subscribers(
+    pagination: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&api_token, **user_id, ApiScope::ReadSubscribers, &pool).await?;
+    pagination.validate()?;
+    let subscribers = get_subscribers(&pool, None, pagination.per_page, pagination.offset())
+        .await?
+        .into_iter()
+        .map(Subscriber::from)
+        .collect();
+    let total = sqlx::query!(r#"SELECT COUNT(*) AS "n!" FROM subscriptions"#)
+        .fetch_one(pool.get_ref())
+        .await
+        .context("Failed to count the subscribers.")?
+        .n;
+    Ok(HttpResponse::Ok().json(Page::new(subscribers, &pagination, total)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/subscribers/{subscriber_id}",
+    summary = "Get a subscriber.",
+    params(("subscriber_id" = Uuid, Path)),
+    responses(
+        (status = 200, description = "OK.", body = Subscriber),
+        (status = 400, description = "The id is not a UUID.", body = ErrorResponse),
+        (status = 404, description = "There is no such subscriber.", body = ErrorResponse),
+    ),
+    security(("bearerAuth" = ["subscribers:read"])),
+)]
+#[tracing::instrument(
+    name = "Get a subscriber through the API",
+    skip(pool, user_id, api_token)
+)]
+pub async fn api_get_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&api_token, **user_id, ApiScope::ReadSubscribers, &pool).await?;
+    let subscriber = get_subscriber(&pool, subscriber_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(subscriber))
+}
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct CreateSubscriber {
+    email: String,
+    name: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl TryFrom<CreateSubscriber> for NewSubscriber {
+    type Error = String;
+
+    fn try_from(value: CreateSubscriber) -> Result<Self, Self::Error> {
+        let name = SubscriberName::parse(value.name)?;
+        let email = SubscriberEmail::parse(value.email)?;
+        let tags = parse_tags(value.tags)?;
+        Ok(Self { email, name, tags })
+    }
+}
+
+/// Add a subscriber, who gets the confirmation email as if they had used the
+/// subscribe form.
+#[utoipa::path(
+    post,
+    path = "/subscribers",
+    summary = "Add a subscriber, who is sent the confirmation email.",
+    request_body = CreateSubscriber,
+    responses(
+        (status = 201, description = "The subscriber, pending confirmation.", body = Subscriber),
+        (status = 400, description = "The email, name or tags are invalid.", body = ErrorResponse),
+        (status = 409, description = "The email is already subscribed.", body = ErrorResponse),
+    ),
+    security(("bearerAuth" = ["subscribers:write"])),
+)]
+#[tracing::instrument(
+    name = "Create a subscriber through the API",
+    skip_all,
+    fields(subscriber_email = %body.email)
+)]
+pub async fn api_create_subscriber(
+    body: web::Json<CreateSubscriber>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    user_id: web::ReqData<UserId>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&api_token, **user_id, ApiScope::WriteSubscribers, &pool).await?;
+    let new_subscriber: NewSubscriber = body
+        .into_inner()
+        .try_into()
+        .map_err(ApiError::ValidationError)?;
+    let existing = sqlx::query!(
+        "SELECT id FROM subscriptions WHERE email = $1",
+        new_subscriber.email.as_ref()
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to look for an existing subscriber.")?;
+    let already_subscribed =
+        ApiError::Conflict(format!("{} is already subscribed.", new_subscriber.email));
+    if existing.is_some() {
+        return Err(already_subscribed);
+    }
+    let subscriber_id =
+        match register_subscriber(&pool, &email_client, &base_url.0, new_subscriber).await {
+            Ok(subscriber_id) => subscriber_id,
+            // Subscribed by another request since the check above.
+            Err(SubscriberError::InsertSubscriberError(sqlx::Error::Database(e)))
+                if e.code().as_deref() == Some("23505") =>
+            {
+                return Err(already_subscribed);
+            }
+            Err(e) => return Err(e.into()),
+        };
+    let subscriber = get_subscriber(&pool, subscriber_id).await?;
+    Ok(HttpResponse::Created()
+        .insert_header((LOCATION, format!("/api/v1/subscribers/{}", subscriber_id)))
+        .json(subscriber))
+}
+
+/// The fields to change. Tags, when given, replace the current ones.
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct UpdateSubscriber {
+    name: Option<String>,
+    #[schema(value_type = Option<EmailFormat>)]
+    email_format: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/subscribers/{subscriber_id}",
+    summary = "Change the name, email format or tags of a subscriber.",
+    params(("subscriber_id" = Uuid, Path)),
+    request_body = UpdateSubscriber,
+    responses(
+        (status = 200, description = "OK.", body = Subscriber),
+        (status = 400, description = "The id or one of the fields is invalid.", body = ErrorResponse),
+        (status = 404, description = "There is no such subscriber.", body = ErrorResponse),
+    ),
+    security(("bearerAuth" = ["subscribers:write"])),
+)]
+#[tracing::instrument(
+    name = "Update a subscriber through the API",
+    skip(body, pool, user_id, api_token)
+)]
+pub async fn api_update_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    body: web::Json<UpdateSubscriber>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&api_token, **user_id, ApiScope::WriteSubscribers, &pool).await?;
+    let subscriber_id = subscriber_id.into_inner();
+    let UpdateSubscriber {
+        name,
+        email_format,
+        tags,
+    } = body.into_inner();
+    let name = name
+        .map(SubscriberName::parse)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+    let email_format = email_format
+        .map(EmailFormat::try_from)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+    let tags = tags
+        .map(parse_tags)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a transaction.")?;
+    let updated = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET
+            name = COALESCE($2, name),
+            email_format = COALESCE($3, email_format)
+        WHERE id = $1
+        "#,
+        subscriber_id,
+        name.as_ref().map(|n| n.as_ref()),
+        email_format.map(|f| f.as_str()),
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to update the subscriber.")?;
+    if updated.rows_affected() == 0 {
+        return Err(unknown_subscriber());
+    }
+    if let Some(tags) = tags {
+        sqlx::query!(
+            "DELETE FROM subscriber_tags WHERE subscriber_id = $1",
+            subscriber_id
+        )
+        .execute(&mut transaction)
+        .await
+        .context("Failed to remove the tags of the subscriber.")?;
+        add_subscriber_tags(&mut transaction, subscriber_id, &tags)
+            .await
+            .context("Failed to tag the subscriber.")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the subscriber changes.")?;
+    let subscriber = get_subscriber(&pool, subscriber_id).await?;
+    Ok(HttpResponse::Ok().json(subscriber))
+}
+
+/// Forget a subscriber altogether, along with the emails still queued for
+/// them. The delivery log keeps their past deliveries.
+#[utoipa::path(
+    delete,
+    path = "/subscribers/{subscriber_id}",
+    summary = "Delete a subscriber and the emails still queued for them.",
+    params(("subscriber_id" = Uuid, Path)),
+    responses(
+        (status = 204, description = "The subscriber is gone."),
+        (status = 400, description = "The id is not a UUID.", body = ErrorResponse),
+        (status = 404, description = "There is no such subscriber.", body = ErrorResponse),
+    ),
+    security(("bearerAuth" = ["subscribers:write"])),
+)]
+#[tracing::instrument(
+    name = "Delete a subscriber through the API",
+    skip(pool, user_id, api_token)
+)]
+pub async fn api_delete_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&api_token, **user_id, ApiScope::WriteSubscribers, &pool).await?;
+    let subscriber_id = subscriber_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a transaction.")?;
+    for query in [
+        sqlx::query!(
+            "DELETE FROM subscription_tokens WHERE subscriber_id = $1",
+            subscriber_id
+        ),
+        sqlx::query!(
+            "DELETE FROM issue_delivery_queue WHERE subscriber_id = $1",
+            subscriber_id
+        ),
+        sqlx::query!(
+            "DELETE FROM welcome_email_queue WHERE subscriber_id = $1",
+            subscriber_id
+        ),
+    ] {
+        query
+            .execute(&mut transaction)
+            .await
+            .context("Failed to remove what is queued for the subscriber.")?;
+    }
+    let deleted = sqlx::query!("DELETE FROM subscriptions WHERE id = $1", subscriber_id)
+        .execute(&mut transaction)
+        .await
+        .context("Failed to delete the subscriber.")?;
+    if deleted.rows_affected() == 0 {
+        return Err(unknown_subscriber());
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the deletion of the subscriber.")?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+fn unknown_subscriber() -> ApiError {
+    ApiError::NotFound("Unknown subscriber.".into())
+}
+
+fn parse_tags(tags: Vec<String>) -> Result<Vec<SubscriberTag>, String> {
+    tags.into_iter().map(SubscriberTag::parse).collect()
+}
+
+async fn get_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<Subscriber, ApiError> {
+    get_subscribers(pool, Some(subscriber_id), 1, 0)
+        .await?
+        .pop()
+        .map(Subscriber::from)
+        .ok_or_else(unknown_subscriber)
+}
+
+/// Subscribers from the most recent, or just the one with this id.
+#[tracing::instrument(skip(pool))]
+async fn get_subscribers(
+    pool: &PgPool,
+    subscriber_id: Option<Uuid>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<SubscriberRow>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        SubscriberRow,
+        r#"
+        SELECT
+            s.id,
+            s.email,
+            s.name,
+            s.status,
+            s.email_format,
+            s.subscribed_at,
+            COALESCE(
+                array_agg(t.tag ORDER BY t.tag) FILTER (WHERE t.tag IS NOT NULL),
+                '{}'
+            ) AS "tags!"
+        FROM subscriptions s
+        LEFT JOIN subscriber_tags t ON t.subscriber_id = s.id
+        WHERE $1::uuid IS NULL OR s.id = $1
+        GROUP BY s.id
+        ORDER BY s.subscribed_at DESC, s.id
+        LIMIT $2 OFFSET $3
+        "#,
+        subscriber_id,
+        limit,
+        offset,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the subscribers.")?;
+    Ok(rows)
+}
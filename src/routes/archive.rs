@@ -0,0 +1,124 @@
+use actix_web::http::header::{ContentType, CONTENT_SECURITY_POLICY};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::utils::e500;
+
+struct ArchivedIssue {
+    title: String,
+    slug: String,
+    published_at: DateTime<Utc>,
+}
+
+/// The list of past issues, open to everyone.
+pub async fn issue_archive(pool: web::Data<PgPool>) -> Result<HttpResponse, actix_web::Error> {
+    let issues = get_archived_issues(&pool).await.map_err(e500)?;
+    let mut issues_html = String::new();
+    for issue in issues {
+        writeln!(
+            issues_html,
+            r#"<li><a href="/issues/{}">{}</a> - {}</li>"#,
+            issue.slug,
+            encode_minimal(&issue.title),
+            issue.published_at.format("%Y-%m-%d"),
+        )
+        .unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Past issues</title>
+    <link rel="alternate" type="application/rss+xml" title="RSS" href="/feed.rss">
+    <link rel="alternate" type="application/atom+xml" title="Atom" href="/feed.atom">
+</head>
+<body>
+    <h1>Past issues</h1>
+    <ul>
+        {issues_html}
+    </ul>
+    <p><a href="/">Subscribe to the newsletter</a>, or follow the <a href="/feed.rss">RSS</a> or <a href="/feed.atom">Atom</a> feed.</p>
+</body>
+</html>"#,
+        )))
+}
+
+pub async fn public_issue(
+    slug: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue = sqlx::query!(
+        r#"
+        SELECT title, html_content, published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE
+            slug = $1 AND
+            is_public AND
+            status IN ('sending', 'sent')
+        "#,
+        slug.as_str()
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to retrieve the newsletter issue")
+    .map_err(e500)?
+    .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown newsletter issue."))?;
+    let title = encode_minimal(&issue.title);
+    let published_at = issue.published_at.format("%Y-%m-%d");
+    let html_content = issue.html_content;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        // The content is written by editors and served as is, on the same
+        // origin as the admin area: it must not be able to run scripts or
+        // reach the session cookie.
+        .insert_header((
+            CONTENT_SECURITY_POLICY,
+            "sandbox allow-popups allow-popups-to-escape-sandbox; \
+            default-src 'none'; img-src * data:; style-src 'unsafe-inline'",
+        ))
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{title}</title>
+</head>
+<body>
+    <h1>{title}</h1>
+    <p>Published on {published_at}</p>
+    <article>
+{html_content}
+    </article>
+    <p><a href="/issues">All past issues</a> - <a href="/">Subscribe to the newsletter</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+/// Public issues that have been published, most recent first.
+#[tracing::instrument(skip_all)]
+async fn get_archived_issues(pool: &PgPool) -> Result<Vec<ArchivedIssue>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        ArchivedIssue,
+        r#"
+        SELECT title, slug AS "slug!", published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE
+            is_public AND
+            slug IS NOT NULL AND
+            status IN ('sending', 'sent')
+        ORDER BY published_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the archived issues.")?;
+    Ok(rows)
+}
@@ -0,0 +1,219 @@
+use actix_web::http::header::{
+    self, ContentType, EntityTag, Header, HttpDate, IfModifiedSince, IfNoneMatch,
+};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, TimeZone, Utc};
+use htmlescape::encode_minimal;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::e500;
+
+const FEED_TITLE: &str = "Zero 2 Prod";
+/// How many of the most recent issues feeds carry.
+const FEED_LENGTH: i64 = 20;
+
+struct FeedEntry {
+    title: String,
+    slug: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+pub async fn rss_feed(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let entries = get_feed_entries(&pool).await.map_err(e500)?;
+    let last_modified = get_last_modified(&pool).await.map_err(e500)?;
+    let base_url = &base_url.0;
+    let mut items = String::new();
+    for entry in &entries {
+        let link = format!("{}/issues/{}", base_url, entry.slug);
+        write!(
+            items,
+            r#"
+    <item>
+      <title>{}</title>
+      <link>{link}</link>
+      <guid isPermaLink="true">{link}</guid>
+      <pubDate>{}</pubDate>
+      <description>{}</description>
+    </item>"#,
+            encode_minimal(&entry.title),
+            entry.published_at.to_rfc2822(),
+            encode_minimal(&entry.html_content),
+        )
+        .unwrap();
+    }
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0">
+  <channel>
+    <title>{FEED_TITLE}</title>
+    <link>{base_url}/issues</link>
+    <description>Past issues of the {FEED_TITLE} newsletter</description>{items}
+  </channel>
+</rss>
+"#
+    );
+    Ok(cached_response(
+        &req,
+        "application/rss+xml; charset=utf-8",
+        body,
+        last_modified,
+    ))
+}
+
+pub async fn atom_feed(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let entries = get_feed_entries(&pool).await.map_err(e500)?;
+    let last_modified = get_last_modified(&pool).await.map_err(e500)?;
+    let base_url = &base_url.0;
+    let mut items = String::new();
+    for entry in &entries {
+        let link = format!("{}/issues/{}", base_url, entry.slug);
+        write!(
+            items,
+            r#"
+  <entry>
+    <title>{}</title>
+    <link href="{link}"/>
+    <id>{link}</id>
+    <updated>{published_at}</updated>
+    <published>{published_at}</published>
+    <content type="html">{}</content>
+  </entry>"#,
+            encode_minimal(&entry.title),
+            encode_minimal(&entry.html_content),
+            published_at = entry.published_at.to_rfc3339(),
+        )
+        .unwrap();
+    }
+    // Atom requires an update time even for an empty feed.
+    let updated = last_modified
+        .unwrap_or_else(|| Utc.timestamp_opt(0, 0).unwrap())
+        .to_rfc3339();
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>{FEED_TITLE}</title>
+  <link href="{base_url}/issues"/>
+  <link rel="self" href="{base_url}/feed.atom"/>
+  <id>{base_url}/issues</id>
+  <updated>{updated}</updated>
+  <author><name>{FEED_TITLE}</name></author>{items}
+</feed>
+"#
+    );
+    Ok(cached_response(
+        &req,
+        "application/atom+xml; charset=utf-8",
+        body,
+        last_modified,
+    ))
+}
+
+/// Answer with `304 Not Modified` if the client already has this version
+/// of the feed, otherwise send it along with its validators.
+fn cached_response(
+    req: &HttpRequest,
+    content_type: &str,
+    body: String,
+    last_modified: Option<DateTime<Utc>>,
+) -> HttpResponse {
+    let etag = EntityTag::new_strong(hex::encode(&Sha256::digest(body.as_bytes())[..16]));
+    // HTTP dates have a one second resolution.
+    let last_modified = last_modified.map(|t| {
+        HttpDate::from(SystemTime::from(
+            Utc.timestamp_opt(t.timestamp(), 0).unwrap(),
+        ))
+    });
+
+    // `If-None-Match` takes precedence over `If-Modified-Since` when both are sent.
+    let not_modified = match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        // A missing header parses as an empty list of tags.
+        Ok(IfNoneMatch::Items(tags)) if !tags.is_empty() => tags.iter().any(|t| t.weak_eq(&etag)),
+        _ => match (IfModifiedSince::parse(req), last_modified) {
+            (Ok(IfModifiedSince(since)), Some(last_modified)) => {
+                SystemTime::from(last_modified) <= SystemTime::from(since)
+            }
+            _ => false,
+        },
+    };
+
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response.insert_header(header::ETag(etag));
+    if let Some(last_modified) = last_modified {
+        response.insert_header(header::LastModified(last_modified));
+    }
+    if not_modified {
+        response.finish()
+    } else {
+        response
+            .content_type(ContentType(content_type.parse().unwrap()))
+            .body(body)
+    }
+}
+
+/// The most recent issues of the web archive.
+#[tracing::instrument(skip_all)]
+async fn get_feed_entries(pool: &PgPool) -> Result<Vec<FeedEntry>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        FeedEntry,
+        r#"
+        SELECT
+            title,
+            slug AS "slug!",
+            html_content,
+            published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE
+            is_public AND
+            slug IS NOT NULL AND
+            status IN ('sending', 'sent')
+        ORDER BY published_at DESC
+        LIMIT $1
+        "#,
+        FEED_LENGTH
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the feed entries.")?;
+    Ok(rows)
+}
+
+/// When the archive last changed: an issue was published in it, or one was
+/// added to or removed from it.
+#[tracing::instrument(skip_all)]
+async fn get_last_modified(pool: &PgPool) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT max(
+            GREATEST(
+                CASE WHEN is_public THEN published_at END,
+                visibility_changed_at
+            )
+        ) AS last_modified
+        FROM newsletter_issues
+        WHERE status IN ('sending', 'sent')
+        "#
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to retrieve when the archive last changed.")?;
+    Ok(row.last_modified)
+}
@@ -57,6 +57,7 @@ name="password"
 </label>
 <button type="submit">Login</button>
 </form>
+<p><a href="/login/forgot-password">Forgot your password?</a></p>
 </body>
 </html>
         "#
@@ -0,0 +1,124 @@
+use actix_web::http::header::{ContentType, LOCATION};
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use htmlescape::encode_attribute;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{invitation_role, validate_new_password, InvitationError};
+use crate::utils::{e500, login_page};
+
+#[derive(serde::Deserialize)]
+pub struct AcceptInvitationParameters {
+    token: Secret<String>,
+}
+
+pub async fn accept_invitation_form(
+    parameters: web::Query<AcceptInvitationParameters>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let role = match invitation_role(&parameters.token, &pool)
+        .await
+        .map_err(e500)?
+    {
+        Some(role) => role,
+        None => {
+            FlashMessage::error(InvitationError::InvalidToken.to_string()).send();
+            return Ok(login_page());
+        }
+    };
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let token = encode_attribute(parameters.token.expose_secret());
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Create your account</title>
+</head>
+<body>
+    {msg_html}
+    <p>You have been invited to join as {role}. Pick a username and a password.</p>
+    <form action="/login/accept-invitation" method="post">
+        <input hidden type="text" name="token" value="{token}">
+        <label>Username
+            <input type="text" placeholder="Enter Username" name="username">
+        </label>
+        <br>
+        <label>Password
+            <input type="password" placeholder="Enter Password" name="password">
+        </label>
+        <br>
+        <label>Confirm password
+            <input type="password" placeholder="Type the password again" name="password_check">
+        </label>
+        <br>
+        <button type="submit">Create account</button>
+    </form>
+</body>
+</html>"#,
+        )))
+}
+
+#[derive(serde::Deserialize)]
+pub struct AcceptInvitationFormData {
+    token: Secret<String>,
+    username: String,
+    password: Secret<String>,
+    password_check: Secret<String>,
+}
+
+#[tracing::instrument(skip_all, fields(username=%form.username, user_id=tracing::field::Empty))]
+pub async fn accept_invitation(
+    form: web::Form<AcceptInvitationFormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let form = form.into_inner();
+    let username = form.username.trim();
+    let validation = if username.is_empty() {
+        Err("The username cannot be empty.")
+    } else {
+        validate_new_password(&form.password, &form.password_check)
+    };
+    if let Err(e) = validation {
+        FlashMessage::error(e).send();
+        return Ok(accept_invitation_page(&form.token));
+    }
+    match crate::authentication::accept_invitation(&form.token, username, form.password, &pool)
+        .await
+    {
+        Ok(user_id) => {
+            tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+            FlashMessage::info("Your account has been created. You can now log in.").send();
+            Ok(login_page())
+        }
+        Err(e @ InvitationError::UsernameTaken) => {
+            FlashMessage::error(e.to_string()).send();
+            Ok(accept_invitation_page(&form.token))
+        }
+        Err(e @ InvitationError::InvalidToken) => {
+            FlashMessage::error(e.to_string()).send();
+            Ok(login_page())
+        }
+        Err(e @ InvitationError::UnexpectedError(_)) => Err(e500(e)),
+    }
+}
+
+fn accept_invitation_page(token: &Secret<String>) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .insert_header((
+            LOCATION,
+            format!(
+                "/login/accept-invitation?token={}",
+                urlencoding::encode(token.expose_secret())
+            ),
+        ))
+        .finish()
+}
@@ -1,5 +1,11 @@
 mod get;
+mod invitation;
+mod password_reset;
 mod post;
+mod two_factor;
 
 pub use get::login_form;
+pub use invitation::{accept_invitation, accept_invitation_form};
+pub use password_reset::{forgot_password, forgot_password_form, reset_password, reset_password_form};
 pub use post::login;
+pub use two_factor::{login_two_factor, login_two_factor_form};
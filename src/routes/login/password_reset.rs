@@ -0,0 +1,216 @@
+use actix_web::http::header::{ContentType, LOCATION};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use htmlescape::encode_attribute;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{
+    change_password, client_ip, create_password_reset_token, password_reset_token_is_valid,
+    revoke_other_sessions, use_password_reset_token, validate_new_password, LoginThrottle,
+};
+use crate::email_client::EmailClient;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{e500, login_page};
+
+const INVALID_LINK: &str = "This password reset link is invalid or has expired.";
+
+pub async fn forgot_password_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Forgot your password?</title>
+</head>
+<body>
+    {msg_html}
+    <p>Enter your username: we will email you a link to choose a new password.</p>
+    <form action="/login/forgot-password" method="post">
+        <label>Username
+            <input type="text" placeholder="Enter Username" name="username">
+        </label>
+        <button type="submit">Send reset link</button>
+    </form>
+    <p><a href="/login">&lt;- Back to login</a></p>
+</body>
+</html>"#,
+        ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct ForgotPasswordFormData {
+    username: String,
+}
+
+/// Email a reset link to the user, if they exist and gave us an address.
+/// The response is the same either way, so that it does not reveal which
+/// usernames exist. Requests are throttled per username and per IP address,
+/// so that the form cannot be used to flood someone's inbox.
+#[tracing::instrument(
+    skip(form, pool, email_client, base_url, throttle, request),
+    fields(username=%form.username)
+)]
+pub async fn forgot_password(
+    form: web::Form<ForgotPasswordFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    throttle: web::Data<LoginThrottle>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let username = form.username.trim();
+    if !throttle
+        .allow_reset_request(username, &client_ip(&request))
+        .await
+        .map_err(e500)?
+    {
+        FlashMessage::error("Too many password reset requests. Try again in an hour.").send();
+        return Ok(login_page());
+    }
+    if let Some((token, email)) = create_password_reset_token(username, &pool)
+        .await
+        .map_err(e500)?
+    {
+        let reset_link = format!(
+            "{}/login/reset-password?token={}",
+            base_url.0,
+            token.expose_secret()
+        );
+        let html_body = format!(
+            r#"<p>Someone asked to reset the password of your account.</p>
+<p><a href="{reset_link}">Choose a new password</a>. The link expires in an hour.</p>
+<p>If it was not you, you can ignore this email.</p>"#
+        );
+        let text_body = format!(
+            "Someone asked to reset the password of your account.\n\n\
+            Visit {reset_link} to choose a new password. The link expires in an hour.\n\n\
+            If it was not you, you can ignore this email."
+        );
+        // Sending in the background keeps the slow call to the email API
+        // out of the response time. Storing the token still makes this path
+        // a little slower than the one for usernames that do not exist.
+        let email_client = email_client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = email_client
+                .send_email(&email, "Reset your password", &html_body, &text_body)
+                .await
+            {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to send a password reset email.",
+                );
+            }
+        });
+    }
+    FlashMessage::info(
+        "If this account has an email address, we have sent it a link to reset your password.",
+    )
+    .send();
+    Ok(login_page())
+}
+
+#[derive(serde::Deserialize)]
+pub struct ResetPasswordParameters {
+    token: Secret<String>,
+}
+
+pub async fn reset_password_form(
+    parameters: web::Query<ResetPasswordParameters>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !password_reset_token_is_valid(&parameters.token, &pool)
+        .await
+        .map_err(e500)?
+    {
+        FlashMessage::error(INVALID_LINK).send();
+        return Ok(login_page());
+    }
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let token = encode_attribute(parameters.token.expose_secret());
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Reset your password</title>
+</head>
+<body>
+    {msg_html}
+    <form action="/login/reset-password" method="post">
+        <input hidden type="text" name="token" value="{token}">
+        <label>New password
+            <input type="password" placeholder="Enter new password" name="new_password">
+        </label>
+        <br>
+        <label>Confirm new password
+            <input type="password" placeholder="Type the new password again" name="new_password_check">
+        </label>
+        <br>
+        <button type="submit">Reset password</button>
+    </form>
+</body>
+</html>"#,
+        )))
+}
+
+#[derive(serde::Deserialize)]
+pub struct ResetPasswordFormData {
+    token: Secret<String>,
+    new_password: Secret<String>,
+    new_password_check: Secret<String>,
+}
+
+#[tracing::instrument(skip_all, fields(user_id=tracing::field::Empty))]
+pub async fn reset_password(
+    form: web::Form<ResetPasswordFormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let form = form.into_inner();
+    if let Err(e) = validate_new_password(&form.new_password, &form.new_password_check) {
+        FlashMessage::error(e).send();
+        return Ok(HttpResponse::SeeOther()
+            .insert_header((
+                LOCATION,
+                format!(
+                    "/login/reset-password?token={}",
+                    urlencoding::encode(form.token.expose_secret())
+                ),
+            ))
+            .finish());
+    }
+    let user_id = match use_password_reset_token(&form.token, &pool)
+        .await
+        .map_err(e500)?
+    {
+        Some(user_id) => user_id,
+        None => {
+            FlashMessage::error(INVALID_LINK).send();
+            return Ok(login_page());
+        }
+    };
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+    change_password(user_id, form.new_password, &pool)
+        .await
+        .map_err(e500)?;
+    // Whoever was logged in with the old password is logged out.
+    revoke_other_sessions(user_id, None, &pool)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("Your password has been reset. You can now log in.").send();
+    Ok(login_page())
+}
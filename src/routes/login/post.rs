@@ -1,13 +1,18 @@
 use actix_web::error::InternalError;
 use actix_web::http::header::LOCATION;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use secrecy::Secret;
 use sqlx::PgPool;
+use std::time::Duration;
 
-use crate::authentication::{validate_credentials, AuthError, Credentials};
+use crate::authentication::{
+    client_ip, has_two_factor, start_session, validate_credentials, AuthError, Credentials,
+    LoginThrottle,
+};
 use crate::routes::error_chain_fmt;
 use crate::session_state::TypedSession;
+use crate::utils::login_two_factor_page;
 
 #[derive(Debug, serde::Deserialize)]
 pub struct FormData {
@@ -16,43 +21,85 @@ pub struct FormData {
 }
 
 #[tracing::instrument(
-    skip(form, pool, session),
+    skip(form, pool, session, throttle, request),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn login(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
     session: TypedSession,
+    throttle: web::Data<LoginThrottle>,
+    request: HttpRequest,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
     let credentials = Credentials {
         username: form.0.username,
         password: form.0.password,
     };
+    let username = credentials.username.clone();
+    let ip = client_ip(&request);
 
-    tracing::Span::current().record("username", &tracing::field::display(&credentials.username));
+    tracing::Span::current().record("username", tracing::field::display(&username));
+    // Locked out attempts are turned away before spending any time on
+    // hashing their password.
+    if let Some(remaining) = throttle
+        .lockout(&username, &ip)
+        .await
+        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?
+    {
+        return Err(login_redirect(LoginError::LockedOut(remaining)));
+    }
+    // The attempt counts as a failure until the user is fully logged in.
+    if let Some(remaining) = throttle
+        .record_attempt(&username, &ip)
+        .await
+        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?
+    {
+        return Err(login_redirect(LoginError::LockedOut(remaining)));
+    }
     match validate_credentials(credentials, &pool).await {
         Ok(user_id) => {
-            tracing::Span::current().record("user_id", &tracing::field::display(&user_id));
+            tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+            let two_factor = has_two_factor(user_id, &pool)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
             session.renew();
-            session
-                .insert_user_id(user_id)
-                .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
+            // The session is only granted, and the failures forgotten, once
+            // the second factor is checked.
+            if two_factor {
+                session
+                    .insert_pending_user_id(user_id)
+                    .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
+                return Ok(login_two_factor_page());
+            }
+            throttle
+                .record_success(&username, &ip)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+            start_session(&session, user_id, &request, &pool)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
             Ok(HttpResponse::SeeOther()
                 .insert_header((LOCATION, "/admin/dashboard"))
                 .finish())
         }
         Err(e) => {
             let e = match e {
-                AuthError::InvalidCredentials(_) => LoginError::AuthError(e.into()),
+                AuthError::InvalidCredentials(_) => {
+                    // The attempt may have been the one that reached the
+                    // limit.
+                    let lockout = throttle
+                        .lockout(&username, &ip)
+                        .await
+                        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+                    match lockout {
+                        Some(duration) => LoginError::LockedOut(duration),
+                        None => LoginError::AuthError(e.into()),
+                    }
+                }
                 AuthError::UnexpectedError(_) => LoginError::UnexpectedError(e.into()),
             };
 
-            FlashMessage::error(e.to_string()).send();
-            let resp = HttpResponse::SeeOther()
-                .insert_header((LOCATION, "/login"))
-                .finish();
-
-            Err(InternalError::from_response(e, resp))
+            Err(login_redirect(e))
         }
     }
 }
@@ -62,6 +109,12 @@ pub enum LoginError {
     #[error("Authentication failed")]
     AuthError(#[source] anyhow::Error),
 
+    #[error(
+        "Too many failed login attempts. Try again in {}.",
+        format_minutes(.0)
+    )]
+    LockedOut(Duration),
+
     #[error("Something went wrong")]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -72,6 +125,16 @@ impl std::fmt::Debug for LoginError {
     }
 }
 
+/// A lockout rounded up to the minute, e.g. `2 minutes`.
+fn format_minutes(duration: &Duration) -> String {
+    let minutes = duration.as_secs().div_ceil(60);
+    if minutes == 1 {
+        "1 minute".into()
+    } else {
+        format!("{} minutes", minutes)
+    }
+}
+
 // Redirect to the login page with an error message
 fn login_redirect(e: LoginError) -> InternalError<LoginError> {
     FlashMessage::error(e.to_string()).send();
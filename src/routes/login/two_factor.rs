@@ -0,0 +1,102 @@
+use actix_web::http::header::{ContentType, LOCATION};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use secrecy::Secret;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{client_ip, start_session, validate_second_factor, LoginThrottle};
+use crate::routes::get_username;
+use crate::session_state::TypedSession;
+use crate::startup::HmacSecret;
+use crate::utils::{e500, login_page, login_two_factor_page};
+
+/// Invalid codes after which the password must be entered again.
+const MAX_CODE_ATTEMPTS: u32 = 5;
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    code: Secret<String>,
+}
+
+/// The second step of the login of users who enabled two-factor
+/// authentication, once their password has been checked.
+pub async fn login_two_factor_form(
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_pending_user_id().map_err(e500)?.is_none() {
+        return Ok(login_page());
+    }
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Two-factor authentication</title>
+</head>
+<body>
+    {msg_html}
+    <form action="/login/two-factor" method="post">
+        <label>Enter the code shown by your authenticator app, or one of your recovery codes:<br>
+            <input type="text" name="code" autocomplete="one-time-code" autofocus>
+        </label>
+        <button type="submit">Verify</button>
+    </form>
+    <p><a href="/login">&lt;- Back to login</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(
+    skip(form, pool, session, throttle, request, hmac_secret),
+    fields(user_id=tracing::field::Empty)
+)]
+pub async fn login_two_factor(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    throttle: web::Data<LoginThrottle>,
+    request: HttpRequest,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = match session.get_pending_user_id().map_err(e500)? {
+        Some(user_id) => user_id,
+        None => return Ok(login_page()),
+    };
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+    if !validate_second_factor(user_id, &form.code, &hmac_secret, &pool)
+        .await
+        .map_err(e500)?
+    {
+        // The login attempt stays counted as a failure by the throttle.
+        if session.record_two_factor_failure().map_err(e500)? >= MAX_CODE_ATTEMPTS {
+            session.remove_pending_user_id();
+            FlashMessage::error("Too many invalid authentication codes. Please log in again.")
+                .send();
+            return Ok(login_page());
+        }
+        FlashMessage::error("Invalid authentication code.").send();
+        return Ok(login_two_factor_page());
+    }
+    let username = get_username(user_id, &pool).await.map_err(e500)?;
+    throttle
+        .record_success(&username, &client_ip(&request))
+        .await
+        .map_err(e500)?;
+    session.renew();
+    session.remove_pending_user_id();
+    start_session(&session, user_id, &request, &pool)
+        .await
+        .map_err(e500)?;
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/dashboard"))
+        .finish())
+}
@@ -1,13 +1,21 @@
 mod admin;
+mod api;
+mod archive;
+mod feeds;
 mod health_check;
 mod home;
 mod login;
+mod preferences;
 mod subscriptions;
 mod subscriptions_confirm;
 
 pub use admin::*;
+pub use api::*;
+pub use archive::*;
+pub use feeds::*;
 pub use home::*;
 pub use login::*;
+pub use preferences::*;
 
 use actix_web::{
     http::{header, StatusCode},
@@ -44,6 +52,8 @@ pub enum SubscriberError {
     ValidationError(String),
     #[error("Failed to store the confirmation token for a new subscriber.")]
     StoreTokenError(#[from] StoreTokenError),
+    #[error("Failed to retrieve the confirmation email.")]
+    ConfirmationEmailError(#[source] anyhow::Error),
     #[error("Failed to send a confirmation email.")]
     SendEmailError(#[from] reqwest::Error),
 
@@ -69,6 +79,7 @@ impl ResponseError for SubscriberError {
             | SubscriberError::TransactionCommitError(_)
             | SubscriberError::InsertSubscriberError(_)
             | SubscriberError::StoreTokenError(_)
+            | SubscriberError::ConfirmationEmailError(_)
             | SubscriberError::SendEmailError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -0,0 +1,182 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use super::SignedSubscriber;
+use crate::configuration::NewsletterSettings;
+use crate::domain::EmailFormat;
+use crate::startup::HmacSecret;
+use crate::utils::e500;
+
+struct SubscriberPreferences {
+    email: String,
+    name: String,
+    status: String,
+    email_format: String,
+    tags: Vec<String>,
+}
+
+#[tracing::instrument(
+    name = "Show the preference center",
+    skip_all,
+    fields(subscriber_id=%query.subscriber_id)
+)]
+pub async fn preferences_form(
+    query: web::Query<SignedSubscriber>,
+    pool: web::Data<PgPool>,
+    secret: web::Data<HmacSecret>,
+    newsletter: web::Data<NewsletterSettings>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = query
+        .verify(&secret)
+        .map_err(actix_web::error::ErrorUnauthorized)?;
+    let preferences = get_preferences(&pool, subscriber_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown subscriber."))?;
+    let topics = newsletter.topics().map_err(e500)?;
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    if preferences.status == "unsubscribed" {
+        writeln!(
+            msg_html,
+            "<p><i>You are unsubscribed and will not receive any further issues.</i></p>"
+        )
+        .unwrap();
+    }
+    let mut topics_html = String::new();
+    if !topics.is_empty() {
+        writeln!(topics_html, "<fieldset><legend>Topics</legend>").unwrap();
+        for topic in topics {
+            let checked = if preferences.tags.iter().any(|t| t == topic.as_ref()) {
+                "checked"
+            } else {
+                ""
+            };
+            writeln!(
+                topics_html,
+                r#"<label><input type="checkbox" name="topics" value="{topic}" {checked}> {topic}</label><br>"#,
+            )
+            .unwrap();
+        }
+        writeln!(topics_html, "</fieldset>").unwrap();
+    }
+    let email_format: EmailFormat = preferences.email_format.try_into().map_err(e500)?;
+    let checked = |format: EmailFormat| {
+        if email_format == format {
+            "checked"
+        } else {
+            ""
+        }
+    };
+    let html_checked = checked(EmailFormat::Html);
+    let plain_text_checked = checked(EmailFormat::PlainText);
+    let email = encode_minimal(&preferences.email);
+    let name = encode_attribute(&preferences.name);
+    let query = query.query_string();
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Email preferences</title>
+</head>
+<body>
+    <p>Email preferences for {email}</p>
+    {msg_html}
+    <form action="/subscriptions/preferences?{query}" method="post">
+        <label>Name
+            <input type="text" name="name" value="{name}">
+        </label>
+        <br>
+        {topics_html}
+        <fieldset>
+            <legend>Email format</legend>
+            <label><input type="radio" name="email_format" value="html" {html_checked}> HTML</label>
+            <label><input type="radio" name="email_format" value="plain_text" {plain_text_checked}> Plain text</label>
+        </fieldset>
+        <button type="submit">Save preferences</button>
+    </form>
+    <form action="/subscriptions/unsubscribe?{query}" method="post">
+        <button type="submit">Unsubscribe</button>
+    </form>
+</body>
+</html>"#,
+        )))
+}
+
+/// Unsubscribe links in emails lead here rather than unsubscribing right
+/// away, so that link checkers following them do not unsubscribe anybody.
+#[tracing::instrument(
+    name = "Show the unsubscribe page",
+    skip_all,
+    fields(subscriber_id=%query.subscriber_id)
+)]
+pub async fn unsubscribe_form(
+    query: web::Query<SignedSubscriber>,
+    secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    query
+        .verify(&secret)
+        .map_err(actix_web::error::ErrorUnauthorized)?;
+    let query = query.query_string();
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribe</title>
+</head>
+<body>
+    <p>Do you want to stop receiving our newsletter?</p>
+    <form action="/subscriptions/unsubscribe?{query}" method="post">
+        <button type="submit">Unsubscribe</button>
+    </form>
+    <p><a href="/subscriptions/preferences?{query}">Manage your email preferences instead</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_preferences(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<SubscriberPreferences>, anyhow::Error> {
+    let preferences = sqlx::query_as!(
+        SubscriberPreferences,
+        r#"
+        SELECT
+            s.email,
+            s.name,
+            s.status,
+            s.email_format,
+            COALESCE(
+                array_agg(t.tag) FILTER (WHERE t.tag IS NOT NULL),
+                '{}'
+            ) AS "tags!"
+        FROM subscriptions s
+        LEFT JOIN subscriber_tags t ON t.subscriber_id = s.id
+        WHERE s.id = $1
+        GROUP BY s.id
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the subscriber preferences.")?;
+    Ok(preferences)
+}
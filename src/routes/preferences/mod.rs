@@ -0,0 +1,61 @@
+mod get;
+mod post;
+
+pub use get::{preferences_form, unsubscribe_form};
+pub use post::{save_preferences, unsubscribe};
+
+use hmac::{Hmac, Mac};
+use secrecy::ExposeSecret;
+use uuid::Uuid;
+
+use crate::startup::HmacSecret;
+
+/// Build the link to a subscriber's preference center.
+/// The link carries an HMAC tag of the subscriber id, so that we can trust
+/// it without asking subscribers for a password.
+pub fn preferences_link(base_url: &str, subscriber_id: Uuid, secret: &HmacSecret) -> String {
+    let query = SignedSubscriber::new(subscriber_id, secret).query_string();
+    format!("{}/subscriptions/preferences?{}", base_url, query)
+}
+
+/// Build the link to the page where a subscriber confirms they want to
+/// unsubscribe, signed like `preferences_link`.
+pub fn unsubscribe_link(base_url: &str, subscriber_id: Uuid, secret: &HmacSecret) -> String {
+    let query = SignedSubscriber::new(subscriber_id, secret).query_string();
+    format!("{}/subscriptions/unsubscribe?{}", base_url, query)
+}
+
+#[derive(serde::Deserialize)]
+pub struct SignedSubscriber {
+    subscriber_id: Uuid,
+    tag: String,
+}
+
+impl SignedSubscriber {
+    fn new(subscriber_id: Uuid, secret: &HmacSecret) -> Self {
+        let mut mac = mac(secret);
+        mac.update(Self::message(subscriber_id).as_bytes());
+        let tag = hex::encode(mac.finalize().into_bytes());
+        Self { subscriber_id, tag }
+    }
+
+    fn verify(&self, secret: &HmacSecret) -> Result<Uuid, anyhow::Error> {
+        let tag = hex::decode(&self.tag)?;
+        let mut mac = mac(secret);
+        mac.update(Self::message(self.subscriber_id).as_bytes());
+        mac.verify_slice(&tag)?;
+        Ok(self.subscriber_id)
+    }
+
+    fn query_string(&self) -> String {
+        format!("subscriber_id={}&tag={}", self.subscriber_id, self.tag)
+    }
+
+    fn message(subscriber_id: Uuid) -> String {
+        format!("subscriber_id={}", subscriber_id)
+    }
+}
+
+fn mac(secret: &HmacSecret) -> Hmac<sha2::Sha256> {
+    Hmac::<sha2::Sha256>::new_from_slice(secret.0.expose_secret().as_bytes()).unwrap()
+}
@@ -0,0 +1,222 @@
+use actix_web::http::header::{ContentType, LOCATION};
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::SignedSubscriber;
+use crate::configuration::NewsletterSettings;
+use crate::domain::{EmailFormat, SubscriberName, SubscriberTag};
+use crate::routes::add_subscriber_tags;
+use crate::startup::HmacSecret;
+use crate::utils::e500;
+
+struct Preferences {
+    name: SubscriberName,
+    email_format: EmailFormat,
+    topics: Vec<SubscriberTag>,
+}
+
+impl Preferences {
+    // The form is parsed by hand because browsers send one `topics`
+    // pair per checked box, which `serde_urlencoded` can't map to a struct.
+    fn parse(form: Vec<(String, String)>, topics: &[SubscriberTag]) -> Result<Self, String> {
+        let mut name = None;
+        let mut email_format = None;
+        let mut selected = Vec::new();
+        for (key, value) in form {
+            match key.as_str() {
+                "name" => name = Some(SubscriberName::parse(value)?),
+                "email_format" => email_format = Some(EmailFormat::try_from(value)?),
+                "topics" => {
+                    let topic = SubscriberTag::parse(value)?;
+                    if !topics.contains(&topic) {
+                        return Err(format!("{} is not a topic you can subscribe to.", topic));
+                    }
+                    selected.push(topic);
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            name: name.ok_or("Please provide your name.")?,
+            email_format: email_format.ok_or("Please choose an email format.")?,
+            topics: selected,
+        })
+    }
+}
+
+#[tracing::instrument(
+    name = "Save subscriber preferences",
+    skip_all,
+    fields(subscriber_id=%query.subscriber_id)
+)]
+pub async fn save_preferences(
+    query: web::Query<SignedSubscriber>,
+    form: web::Form<Vec<(String, String)>>,
+    pool: web::Data<PgPool>,
+    secret: web::Data<HmacSecret>,
+    newsletter: web::Data<NewsletterSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = query
+        .verify(&secret)
+        .map_err(actix_web::error::ErrorUnauthorized)?;
+    let topics = newsletter.topics().map_err(e500)?;
+    let preferences = match Preferences::parse(form.into_inner(), &topics) {
+        Ok(p) => p,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(preferences_page(&query));
+        }
+    };
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    update_subscriber(&mut transaction, subscriber_id, &preferences)
+        .await
+        .context("Failed to update the subscriber details")
+        .map_err(e500)?;
+    // Only touch the tags subscribers are allowed to manage themselves.
+    let unselected: Vec<SubscriberTag> = topics
+        .into_iter()
+        .filter(|t| !preferences.topics.contains(t))
+        .collect();
+    remove_topics(&mut transaction, subscriber_id, &unselected)
+        .await
+        .context("Failed to remove unselected topics")
+        .map_err(e500)?;
+    add_subscriber_tags(&mut transaction, subscriber_id, &preferences.topics)
+        .await
+        .context("Failed to add selected topics")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to save preferences")
+        .map_err(e500)?;
+
+    FlashMessage::info("Your preferences have been saved.").send();
+    Ok(preferences_page(&query))
+}
+
+#[tracing::instrument(
+    name = "Unsubscribe a subscriber",
+    skip_all,
+    fields(subscriber_id=%query.subscriber_id)
+)]
+pub async fn unsubscribe(
+    query: web::Query<SignedSubscriber>,
+    pool: web::Data<PgPool>,
+    secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = query
+        .verify(&secret)
+        .map_err(actix_web::error::ErrorUnauthorized)?;
+    mark_as_unsubscribed(&pool, subscriber_id)
+        .await
+        .context("Failed to unsubscribe")
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribed</title>
+</head>
+<body>
+    <p>You have been unsubscribed. Sorry to see you go!</p>
+</body>
+</html>"#,
+    ))
+}
+
+fn preferences_page(query: &SignedSubscriber) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .insert_header((
+            LOCATION,
+            format!("/subscriptions/preferences?{}", query.query_string()),
+        ))
+        .finish()
+}
+
+#[tracing::instrument(skip_all)]
+async fn update_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    preferences: &Preferences,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET name = $2, email_format = $3
+        WHERE id = $1
+        "#,
+        subscriber_id,
+        preferences.name.as_ref(),
+        preferences.email_format.as_str(),
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn remove_topics(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    topics: &[SubscriberTag],
+) -> Result<(), sqlx::Error> {
+    let topics: Vec<String> = topics.iter().map(|t| t.to_string()).collect();
+    sqlx::query!(
+        r#"
+        DELETE FROM subscriber_tags
+        WHERE subscriber_id = $1 AND tag = ANY($2)
+        "#,
+        subscriber_id,
+        &topics[..],
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn mark_as_unsubscribed(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = 'unsubscribed'
+        WHERE id = $1
+        "#,
+        subscriber_id,
+    )
+    .execute(&mut transaction)
+    .await?;
+    // Issues that are still being delivered should not reach them either,
+    // nor should the rest of their welcome sequence.
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE subscriber_id = $1
+        "#,
+        subscriber_id,
+    )
+    .execute(&mut transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM welcome_email_queue
+        WHERE subscriber_id = $1
+        "#,
+        subscriber_id,
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
@@ -5,18 +5,24 @@ use chrono::Utc;
 use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
+use crate::routes::get_confirmation_email;
 use crate::startup::ApplicationBaseUrl;
 use crate::{
-    domain::{NewSubscriber, SubscriberEmail, SubscriberName},
+    domain::{ConfirmationEmail, NewSubscriber, SubscriberEmail, SubscriberName, SubscriberTag},
     email_client::EmailClient,
 };
 
 use super::{generate_subscription_token, StoreTokenError, SubscriberError};
 
-#[derive(Debug, Clone, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize, utoipa::ToSchema)]
+#[schema(as = SubscribeForm)]
 pub struct FormData {
     email: String,
     name: String,
+    /// Comma-separated list of tags, usually set through a hidden field
+    /// on the subscribe form (e.g. to know which page the signup came from).
+    #[serde(default)]
+    tags: String,
 }
 
 impl TryFrom<FormData> for NewSubscriber {
@@ -25,10 +31,22 @@ impl TryFrom<FormData> for NewSubscriber {
     fn try_from(value: FormData) -> Result<Self, Self::Error> {
         let name = SubscriberName::parse(value.name)?;
         let email = SubscriberEmail::parse(value.email)?;
-        Ok(Self { email, name })
+        let tags = SubscriberTag::parse_list(&value.tags)?;
+        Ok(Self { email, name, tags })
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/subscriptions",
+    summary = "Subscribe to the newsletter.",
+    request_body(content = FormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The confirmation email has been sent."),
+        (status = 400, description = "The name, email or tags are invalid.", body = String),
+        (status = 500, description = "Something went wrong.", body = String),
+    ),
+)]
 #[tracing::instrument(
     name = "Adding a new subscriber",
     skip(form, pool, email_client, base_url),
@@ -47,18 +65,41 @@ pub async fn subscribe(
         .0
         .try_into()
         .map_err(SubscriberError::ValidationError)?;
+    register_subscriber(&pool, &email_client, &base_url.0, new_subscriber).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Store a new subscriber, pending confirmation, and send them the
+/// confirmation email.
+#[tracing::instrument(
+    name = "Register a new subscriber",
+    skip(pool, email_client, base_url, new_subscriber)
+)]
+pub async fn register_subscriber(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    new_subscriber: NewSubscriber,
+) -> Result<Uuid, SubscriberError> {
+    let confirmation_email = get_confirmation_email(pool)
+        .await
+        .map_err(SubscriberError::ConfirmationEmailError)?;
     let mut transaction = pool.begin().await.map_err(SubscriberError::PoolError)?;
     let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
         .await
         .map_err(SubscriberError::InsertSubscriberError)?;
+    add_subscriber_tags(&mut transaction, subscriber_id, &new_subscriber.tags)
+        .await
+        .map_err(SubscriberError::InsertSubscriberError)?;
     let subscription_token = generate_subscription_token();
 
     store_token(&mut transaction, subscriber_id, &subscription_token).await?;
     send_confirmation_email(
-        &email_client,
+        email_client,
         new_subscriber,
-        &base_url.0,
+        base_url,
         &subscription_token,
+        &confirmation_email,
     )
     .await?;
 
@@ -67,39 +108,30 @@ pub async fn subscribe(
         .await
         .map_err(SubscriberError::TransactionCommitError)?;
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(subscriber_id)
 }
 
 #[tracing::instrument(
     name = "Send a confirmation email to a new subscriber",
-    skip(email_client, new_subscriber, base_url, subscription_token)
+    skip(email_client, new_subscriber, base_url, subscription_token, template)
 )]
 pub async fn send_confirmation_email(
     email_client: &EmailClient,
     new_subscriber: NewSubscriber,
     base_url: &str,
     subscription_token: &str,
+    template: &ConfirmationEmail,
 ) -> Result<(), reqwest::Error> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, subscription_token
     );
-    let html_body = format!(
-        r#"
-        Welcome to our newsletter!<br />
-        Click <a href="{}">here</a> to confirm your subscription.
-    "#,
-        confirmation_link
-    );
-    let plain_body = format!(
-        r#"
-        Welcome to our newsletter!
-        Visit {} to confirm your subscription.
-    "#,
-        confirmation_link
-    );
+    let name = new_subscriber.name.as_ref();
+    let subject = template.render_subject(name, &confirmation_link);
+    let html_body = template.render_html(name, &confirmation_link);
+    let plain_body = template.render_text(name, &confirmation_link);
     email_client
-        .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
+        .send_email(&new_subscriber.email, &subject, &html_body, &plain_body)
         .await
 }
 
@@ -135,6 +167,32 @@ pub async fn insert_subscriber(
     Ok(subscriber_id)
 }
 
+#[tracing::instrument(
+    name = "Tag a subscriber",
+    skip(transaction, tags),
+    fields(tags = ?tags)
+)]
+pub async fn add_subscriber_tags(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    tags: &[SubscriberTag],
+) -> Result<(), sqlx::Error> {
+    let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_tags (subscriber_id, tag, tagged_at)
+        SELECT $1, tag, now()
+        FROM UNNEST($2::text[]) AS tag
+        ON CONFLICT DO NOTHING
+        "#,
+        subscriber_id,
+        &tags[..],
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
 #[tracing::instrument(
     name = "Store subscription token in the database",
     skip(subscription_token, transaction)
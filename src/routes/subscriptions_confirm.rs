@@ -2,12 +2,27 @@ use actix_web::{HttpResponse, web};
 use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::welcome_email_worker::enqueue_welcome_emails;
 
-#[derive(serde::Deserialize)]
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct Parmaters {
 	subscription_token: String
 }
 
+#[utoipa::path(
+	get,
+	path = "/subscriptions/confirm",
+	summary = "Confirm a subscription, from the link of the confirmation email.",
+	params(Parmaters),
+	responses(
+		(status = 200, description = "The subscription is confirmed."),
+		(status = 400, description = "The token is missing.", body = String),
+		(status = 401, description = "The token is unknown."),
+		(status = 500, description = "Something went wrong."),
+	),
+)]
 #[tracing::instrument(
 	name = "Confirm a pending subscriber",
 	skip(parameters, pool)
@@ -41,17 +56,23 @@ pub async fn confirm_subscriber(
 	pool: &PgPool,
 	subscriber_id: Uuid,
 ) -> Result<(), sqlx::Error> {
-	sqlx::query!(r#"
-		UPDATE subscriptions SET status = 'confirmed' WHERE id = $1
+	let mut transaction = pool.begin().await?;
+	let confirmed = sqlx::query!(r#"
+		UPDATE subscriptions SET status = 'confirmed' WHERE id = $1 AND status <> 'confirmed'
 	"#, 
 	subscriber_id)
-		.execute(pool)
+		.execute(&mut transaction)
 		.await
 		.map_err(|e| {
 			tracing::error!("Failed to execute query: {:?}", e);
 			e
 		})?;
-	
+	// Following the confirmation link again must not restart the sequence.
+	if confirmed.rows_affected() > 0 {
+		enqueue_welcome_emails(&mut transaction, subscriber_id).await?;
+	}
+	transaction.commit().await?;
+
 	Ok(())
 }
 
@@ -75,4 +96,4 @@ pub async fn get_subscriber_id_from_token(
 		})?;
 	
 	Ok(result.map(|r| r.subscriber_id))
-}
\ No newline at end of file
+}
\ No newline at end of file
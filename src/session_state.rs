@@ -2,6 +2,9 @@ use actix_session::Session;
 use actix_session::SessionExt;
 use actix_web::dev::Payload;
 use actix_web::{FromRequest, HttpRequest};
+use chrono::Utc;
+use rand::distributions::{Alphanumeric, DistString};
+use secrecy::{ExposeSecret, Secret};
 use std::future::{ready, Ready};
 use uuid::Uuid;
 
@@ -9,6 +12,16 @@ pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
+    // The entry of the session in the `user_sessions` index.
+    const SESSION_ID_KEY: &'static str = "session_id";
+    // Set once the password has been checked, while waiting for the
+    // second factor of users who enabled it.
+    const PENDING_LOGIN_KEY: &'static str = "pending_login";
+    // The TOTP secret shown to a user who is enabling two-factor
+    // authentication, until they confirm it with a code.
+    const TOTP_ENROLMENT_KEY: &'static str = "totp_enrolment_secret";
+    // The synchronizer token the admin forms must send back.
+    const CSRF_TOKEN_KEY: &'static str = "csrf_token";
 
     pub fn renew(&self) {
         self.0.renew()
@@ -22,11 +35,96 @@ impl TypedSession {
         self.0.get(Self::USER_ID_KEY)
     }
 
+    pub fn insert_session_id(&self, session_id: Uuid) -> Result<(), serde_json::Error> {
+        self.0.insert(Self::SESSION_ID_KEY, session_id)
+    }
+
+    pub fn get_session_id(&self) -> Result<Option<Uuid>, serde_json::Error> {
+        self.0.get(Self::SESSION_ID_KEY)
+    }
+
+    pub fn insert_pending_user_id(&self, user_id: Uuid) -> Result<(), serde_json::Error> {
+        let pending = PendingLogin {
+            user_id,
+            expires_at: Utc::now().timestamp() + PENDING_LOGIN_TTL_SECONDS,
+            failures: 0,
+        };
+        self.0.insert(Self::PENDING_LOGIN_KEY, pending)
+    }
+
+    /// The user whose second factor is awaited, unless they took too long
+    /// to enter it.
+    pub fn get_pending_user_id(&self) -> Result<Option<Uuid>, serde_json::Error> {
+        Ok(self.get_pending_login()?.map(|pending| pending.user_id))
+    }
+
+    /// Count an invalid second factor. Returns how many have been entered
+    /// since the password was checked.
+    pub fn record_two_factor_failure(&self) -> Result<u32, serde_json::Error> {
+        let Some(mut pending) = self.get_pending_login()? else {
+            return Ok(0);
+        };
+        pending.failures += 1;
+        self.0.insert(Self::PENDING_LOGIN_KEY, &pending)?;
+        Ok(pending.failures)
+    }
+
+    pub fn remove_pending_user_id(&self) {
+        self.0.remove(Self::PENDING_LOGIN_KEY);
+    }
+
+    fn get_pending_login(&self) -> Result<Option<PendingLogin>, serde_json::Error> {
+        let pending: Option<PendingLogin> = self.0.get(Self::PENDING_LOGIN_KEY)?;
+        Ok(pending.filter(|p| p.expires_at > Utc::now().timestamp()))
+    }
+
+    pub fn insert_totp_enrolment_secret(
+        &self,
+        secret: &Secret<String>,
+    ) -> Result<(), serde_json::Error> {
+        self.0
+            .insert(Self::TOTP_ENROLMENT_KEY, secret.expose_secret())
+    }
+
+    pub fn get_totp_enrolment_secret(&self) -> Result<Option<Secret<String>>, serde_json::Error> {
+        self.0
+            .get::<String>(Self::TOTP_ENROLMENT_KEY)
+            .map(|s| s.map(Secret::new))
+    }
+
+    pub fn remove_totp_enrolment_secret(&self) {
+        self.0.remove(Self::TOTP_ENROLMENT_KEY);
+    }
+
+    /// Issued when the user logs in, so that pages loaded side by side
+    /// right afterwards all carry the same token.
+    pub fn insert_new_csrf_token(&self) -> Result<String, serde_json::Error> {
+        let token = Alphanumeric.sample_string(&mut rand::thread_rng(), 32);
+        self.0.insert(Self::CSRF_TOKEN_KEY, &token)?;
+        Ok(token)
+    }
+
+    pub fn get_csrf_token(&self) -> Result<Option<String>, serde_json::Error> {
+        self.0.get(Self::CSRF_TOKEN_KEY)
+    }
+
     pub fn logout(self) {
         self.0.purge()
     }
 }
 
+/// How long users have to enter their second factor once their password
+/// has been checked.
+const PENDING_LOGIN_TTL_SECONDS: i64 = 5 * 60;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PendingLogin {
+    user_id: Uuid,
+    // A Unix timestamp.
+    expires_at: i64,
+    failures: u32,
+}
+
 impl FromRequest for TypedSession {
     type Error = <Session as FromRequest>::Error;
 
@@ -1,11 +1,11 @@
-use std::net::TcpListener;
+use std::net::{IpAddr, TcpListener};
 
 use actix_session::storage::RedisSessionStore;
-use actix_session::SessionMiddleware;
-use actix_web::cookie::Key;
+use actix_session::{SessionLength, SessionMiddleware};
+use actix_web::cookie::{time::Duration, Key, SameSite};
 use actix_web::dev::Server;
 use actix_web::web::Data;
-use actix_web::{web, App, HttpServer};
+use actix_web::{guard, web, App, HttpServer};
 use actix_web_flash_messages::{storage::CookieMessageStore, FlashMessagesFramework};
 use actix_web_lab::middleware::from_fn;
 use secrecy::ExposeSecret;
@@ -14,12 +14,30 @@ use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use tracing_actix_web::TracingLogger;
 
-use crate::authentication::reject_anonymous_users;
-use crate::configuration::{DatabaseSettings, Settings};
+use crate::authentication::{
+    reject_anonymous_users, reject_forged_requests, reject_invalid_api_tokens, require_editor,
+    require_owner, LoginThrottle, TrustedProxies, SESSION_TTL_DAYS,
+};
+use crate::configuration::{DatabaseSettings, NewsletterSettings, Settings};
 use crate::email_client::EmailClient;
 use crate::routes::{
-    admin_dashboard, change_password, change_password_form, confirm, health_check, home, login,
-    login_form, logout, publish_newsletter, publish_newsletter_form, subscribe,
+    accept_invitation, accept_invitation_form, account_email_form, add_api_token, add_user,
+    admin_dashboard, api_routes, atom_feed, cancel_delivery, change_account_email, change_password,
+    change_password_form, change_user_role, confirm, confirmation_email_form,
+    count_newsletter_recipients, create_draft, create_template, create_welcome_email,
+    delete_welcome_email, delivery_status, edit_draft_form, edit_template_form,
+    edit_welcome_email_form, enrol_two_factor, forgot_password, forgot_password_form, health_check,
+    home, import_subscribers, invite_user, issue_archive, list_api_tokens, list_issues,
+    list_sessions, list_subscribers, list_templates, list_users, list_welcome_emails,
+    log_out_everywhere, login, login_form, login_two_factor, login_two_factor_form, logout,
+    new_template_form, new_welcome_email_form, openapi_document, openapi_json, pause_delivery,
+    preferences_form, preview_confirmation_email, preview_issue, public_issue, publish_newsletter,
+    publish_newsletter_form, publish_newsletter_outside_admin, reject_invalid_request,
+    remove_api_token, remove_session, remove_user, reset_password, reset_password_form,
+    resume_delivery, rss_feed, save_confirmation_email, save_preferences, schedule_issue,
+    send_draft, send_test_copy, set_issue_visibility, subscribe, tag_subscriber,
+    two_factor_settings, unenrol_two_factor, unschedule_issue, unsubscribe, unsubscribe_form,
+    update_draft, update_template, update_welcome_email, withdraw_invitation,
 };
 
 pub struct Application {
@@ -31,6 +49,10 @@ impl Application {
     pub async fn build(conf: Settings) -> Result<Self, anyhow::Error> {
         let conn_pool = get_connection_pool(&conf.database);
         let email_client = conf.email_client.client();
+        conf.newsletter.topics().map_err(anyhow::Error::msg)?;
+        conf.newsletter
+            .reviewer_emails()
+            .map_err(anyhow::Error::msg)?;
 
         let addr = format!("{}:{}", conf.application.host, conf.application.port);
         let lis = TcpListener::bind(addr)?;
@@ -41,7 +63,9 @@ impl Application {
             email_client,
             conf.application.base_url,
             conf.application.hmac_secret,
+            conf.application.trusted_proxies,
             conf.redis_uri,
+            conf.newsletter,
         )
         .await?;
 
@@ -73,13 +97,16 @@ pub fn get_connection_pool(conf: &DatabaseSettings) -> PgPool {
 #[derive(Debug, Clone)]
 pub struct HmacSecret(pub Secret<String>);
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     lis: TcpListener,
     conn_pool: PgPool,
     email_client: EmailClient,
     base_url: String,
     hmac_secret: Secret<String>,
+    trusted_proxies: Vec<IpAddr>,
     redis_uri: Secret<String>,
+    newsletter: NewsletterSettings,
 ) -> Result<Server, anyhow::Error> {
     // Wrap the pool using web::Data, which boils down to an Arc smart pointer
     let conn_pool = Data::new(conn_pool);
@@ -89,39 +116,233 @@ pub async fn run(
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
     let hmac_secret = Data::new(HmacSecret(hmac_secret));
+    let trusted_proxies = Data::new(TrustedProxies(trusted_proxies));
+    let newsletter = Data::new(newsletter);
     let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
+    let login_throttle = Data::new(LoginThrottle::new(redis_uri.expose_secret()).await?);
+    // Built now rather than on the first request, so that a mistake in it
+    // stops the server right away.
+    openapi_document();
     let srv = HttpServer::new(move || {
         App::new()
             // Middleware are added using the `wrap` method on `App`
             .wrap(message_framework.clone())
-            .wrap(SessionMiddleware::new(
-                redis_store.clone(),
-                secret_key.clone(),
-            ))
+            .wrap(
+                SessionMiddleware::builder(redis_store.clone(), secret_key.clone())
+                    // Never sent along with requests that other sites trigger.
+                    .cookie_same_site(SameSite::Strict)
+                    // Spelled out, as the index of sessions forgets them
+                    // after the same time.
+                    .session_length(SessionLength::BrowserSession {
+                        state_ttl: Some(Duration::days(SESSION_TTL_DAYS.into())),
+                    })
+                    .build(),
+            )
             .wrap(TracingLogger::default())
             .route("/", web::get().to(home))
             .route("/login", web::get().to(login_form))
             .route("/login", web::post().to(login))
+            .route("/login/two-factor", web::get().to(login_two_factor_form))
+            .route("/login/two-factor", web::post().to(login_two_factor))
+            .route(
+                "/login/forgot-password",
+                web::get().to(forgot_password_form),
+            )
+            .route("/login/forgot-password", web::post().to(forgot_password))
+            .route("/login/reset-password", web::get().to(reset_password_form))
+            .route("/login/reset-password", web::post().to(reset_password))
+            .route(
+                "/login/accept-invitation",
+                web::get().to(accept_invitation_form),
+            )
+            .route(
+                "/login/accept-invitation",
+                web::post().to(accept_invitation),
+            )
             .route("/health_check", web::get().to(health_check))
             // A new entry in our routing table for POST /subscriptions requests
             .route("/subscriptions", web::post().to(subscribe))
             // Register the connection as part of the application state
             .route("/subscriptions/confirm", web::get().to(confirm))
-            .route("/newsletters", web::post().to(publish_newsletter))
+            .route("/api/openapi.json", web::get().to(openapi_json))
+            .route(
+                "/subscriptions/preferences",
+                web::get().to(preferences_form),
+            )
+            .route(
+                "/subscriptions/preferences",
+                web::post().to(save_preferences),
+            )
+            .route(
+                "/subscriptions/unsubscribe",
+                web::get().to(unsubscribe_form),
+            )
+            .route("/subscriptions/unsubscribe", web::post().to(unsubscribe))
+            .route(
+                "/newsletters",
+                web::post().to(publish_newsletter_outside_admin),
+            )
+            .route("/issues", web::get().to(issue_archive))
+            .route("/feed.rss", web::get().to(rss_feed))
+            .route("/feed.atom", web::get().to(atom_feed))
+            .route("/issues/{slug}", web::get().to(public_issue))
+            .service(
+                api_routes().into_iter().fold(
+                    web::scope("/api/v1")
+                        .wrap(from_fn(reject_invalid_api_tokens))
+                        .app_data(web::JsonConfig::default().error_handler(reject_invalid_request))
+                        .app_data(web::PathConfig::default().error_handler(reject_invalid_request))
+                        .app_data(
+                            web::QueryConfig::default().error_handler(reject_invalid_request),
+                        ),
+                    |scope, (_, path, route)| scope.route(path, route),
+                ),
+            )
             .service(
                 web::scope("/admin")
+                    .wrap(from_fn(reject_forged_requests))
                     .wrap(from_fn(reject_anonymous_users))
                     .route("/dashboard", web::get().to(admin_dashboard))
                     .route("/password", web::get().to(change_password_form))
                     .route("/password", web::post().to(change_password))
+                    .route("/email", web::get().to(account_email_form))
+                    .route("/email", web::post().to(change_account_email))
+                    .route("/two-factor", web::get().to(two_factor_settings))
+                    .route("/two-factor", web::post().to(enrol_two_factor))
+                    .route("/two-factor/disable", web::post().to(unenrol_two_factor))
                     .route("/logout", web::post().to(logout))
+                    .route("/sessions", web::get().to(list_sessions))
+                    .route("/sessions/delete", web::post().to(log_out_everywhere))
+                    .route(
+                        "/sessions/{session_id}/delete",
+                        web::post().to(remove_session),
+                    )
+                    .route("/api-tokens", web::get().to(list_api_tokens))
+                    .route("/api-tokens", web::post().to(add_api_token))
+                    .route(
+                        "/api-tokens/{api_token_id}/delete",
+                        web::post().to(remove_api_token),
+                    )
+                    .service(
+                        web::scope("/users")
+                            .wrap(from_fn(require_owner))
+                            .route("", web::get().to(list_users))
+                            .route("", web::post().to(add_user))
+                            .route("/{user_id}/role", web::post().to(change_user_role))
+                            .route("/{user_id}/delete", web::post().to(remove_user))
+                            .route("/invitations", web::post().to(invite_user))
+                            .route(
+                                "/invitations/{invitation_id}/delete",
+                                web::post().to(withdraw_invitation),
+                            ),
+                    )
                     .route("/newsletters", web::get().to(publish_newsletter_form))
-                    .route("/newsletters", web::post().to(publish_newsletter)),
+                    .route(
+                        "/newsletters/recipients",
+                        web::post().to(count_newsletter_recipients),
+                    )
+                    .route("/newsletters/issues", web::get().to(list_issues))
+                    .route(
+                        "/newsletters/issues/{issue_id}",
+                        web::get().to(edit_draft_form),
+                    )
+                    .route(
+                        "/newsletters/issues/{issue_id}/preview",
+                        web::get().to(preview_issue),
+                    )
+                    .route(
+                        "/newsletters/issues/{issue_id}/status",
+                        web::get().to(delivery_status),
+                    )
+                    .route("/subscribers", web::get().to(list_subscribers))
+                    .route("/templates", web::get().to(list_templates))
+                    .route("/templates/new", web::get().to(new_template_form))
+                    .route(
+                        "/templates/{template_id}",
+                        web::get().to(edit_template_form),
+                    )
+                    .route(
+                        "/confirmation-email",
+                        web::get().to(confirmation_email_form),
+                    )
+                    .route(
+                        "/confirmation-email/preview",
+                        web::post().to(preview_confirmation_email),
+                    )
+                    .route("/welcome-emails", web::get().to(list_welcome_emails))
+                    .route("/welcome-emails/new", web::get().to(new_welcome_email_form))
+                    .route(
+                        "/welcome-emails/{welcome_email_id}",
+                        web::get().to(edit_welcome_email_form),
+                    )
+                    // Viewers can look at everything above, but only editors
+                    // can change it. Registered last, so that the routes
+                    // above are matched first.
+                    .service(
+                        web::scope("")
+                            .guard(guard::Post())
+                            .wrap(from_fn(require_editor))
+                            .route("/newsletters", web::post().to(publish_newsletter))
+                            .route("/newsletters/test", web::post().to(send_test_copy))
+                            .route("/newsletters/issues", web::post().to(create_draft))
+                            .route(
+                                "/newsletters/issues/{issue_id}",
+                                web::post().to(update_draft),
+                            )
+                            .route(
+                                "/newsletters/issues/{issue_id}/send",
+                                web::post().to(send_draft),
+                            )
+                            .route(
+                                "/newsletters/issues/{issue_id}/schedule",
+                                web::post().to(schedule_issue),
+                            )
+                            .route(
+                                "/newsletters/issues/{issue_id}/unschedule",
+                                web::post().to(unschedule_issue),
+                            )
+                            .route(
+                                "/newsletters/issues/{issue_id}/visibility",
+                                web::post().to(set_issue_visibility),
+                            )
+                            .route(
+                                "/newsletters/issues/{issue_id}/pause",
+                                web::post().to(pause_delivery),
+                            )
+                            .route(
+                                "/newsletters/issues/{issue_id}/resume",
+                                web::post().to(resume_delivery),
+                            )
+                            .route(
+                                "/newsletters/issues/{issue_id}/cancel",
+                                web::post().to(cancel_delivery),
+                            )
+                            .route("/subscribers/tags", web::post().to(tag_subscriber))
+                            .route("/subscribers/import", web::post().to(import_subscribers))
+                            .route("/templates", web::post().to(create_template))
+                            .route("/templates/{template_id}", web::post().to(update_template))
+                            .route(
+                                "/confirmation-email",
+                                web::post().to(save_confirmation_email),
+                            )
+                            .route("/welcome-emails", web::post().to(create_welcome_email))
+                            .route(
+                                "/welcome-emails/{welcome_email_id}",
+                                web::post().to(update_welcome_email),
+                            )
+                            .route(
+                                "/welcome-emails/{welcome_email_id}/delete",
+                                web::post().to(delete_welcome_email),
+                            ),
+                    ),
             )
             .app_data(conn_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
             .app_data(hmac_secret.clone())
+            .app_data(trusted_proxies.clone())
+            .app_data(newsletter.clone())
+            .app_data(login_throttle.clone())
     })
     .listen(lis)?
     .run();
@@ -1,5 +1,6 @@
 use actix_web::http::header::LOCATION;
 use actix_web::HttpResponse;
+use uuid::Uuid;
 
 // Return an opaque 500 while preserving the error root's cause for logging.
 pub fn e500<T>(e: T) -> actix_web::Error
@@ -11,7 +12,7 @@ where
 
 // Return a 400 with the user-representation of the validation error as body.
 // The error root cause is preserved for logging purposes.
-pub fn e400<T: std::fmt::Debug + std::fmt::Display>(e: T) -> actix_web::Error
+pub fn e400<T>(e: T) -> actix_web::Error
 where
     T: std::fmt::Debug + std::fmt::Display + 'static,
 {
@@ -28,6 +29,18 @@ pub fn login_page() -> HttpResponse {
     see_other("/login")
 }
 
+pub fn login_two_factor_page() -> HttpResponse {
+    see_other("/login/two-factor")
+}
+
+pub fn dashboard_page() -> HttpResponse {
+    see_other("/admin/dashboard")
+}
+
+pub fn users_page() -> HttpResponse {
+    see_other("/admin/users")
+}
+
 pub fn change_password_page() -> HttpResponse {
     see_other("/admin/password")
 }
@@ -35,3 +48,47 @@ pub fn change_password_page() -> HttpResponse {
 pub fn newsletters_page() -> HttpResponse {
     see_other("/admin/newsletters")
 }
+
+pub fn subscribers_page() -> HttpResponse {
+    see_other("/admin/subscribers")
+}
+
+pub fn issues_page() -> HttpResponse {
+    see_other("/admin/newsletters/issues")
+}
+
+pub fn issue_page(issue_id: Uuid) -> HttpResponse {
+    see_other(&format!("/admin/newsletters/issues/{}", issue_id))
+}
+
+pub fn issue_preview_page(issue_id: Uuid) -> HttpResponse {
+    see_other(&format!("/admin/newsletters/issues/{}/preview", issue_id))
+}
+
+pub fn templates_page() -> HttpResponse {
+    see_other("/admin/templates")
+}
+
+pub fn confirmation_email_page() -> HttpResponse {
+    see_other("/admin/confirmation-email")
+}
+
+pub fn welcome_emails_page() -> HttpResponse {
+    see_other("/admin/welcome-emails")
+}
+
+pub fn two_factor_page() -> HttpResponse {
+    see_other("/admin/two-factor")
+}
+
+pub fn account_email_page() -> HttpResponse {
+    see_other("/admin/email")
+}
+
+pub fn sessions_page() -> HttpResponse {
+    see_other("/admin/sessions")
+}
+
+pub fn api_tokens_page() -> HttpResponse {
+    see_other("/admin/api-tokens")
+}
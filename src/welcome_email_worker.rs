@@ -0,0 +1,211 @@
+use crate::configuration::Settings;
+use crate::domain::{EmailFormat, EmailLayout, SubscriberEmail};
+use crate::email_client::EmailClient;
+use crate::issue_delivery_worker::{get_recipient, personalise};
+use crate::startup::{get_connection_pool, HmacSecret};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::time::Duration;
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+pub async fn run_welcome_worker_until_stopped(
+    configuration: Settings,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    let email_client = configuration.email_client.client();
+    let base_url = configuration.application.base_url;
+    let hmac_secret = HmacSecret(configuration.application.hmac_secret);
+    welcome_loop(connection_pool, email_client, base_url, hmac_secret).await
+}
+
+async fn welcome_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    base_url: String,
+    hmac_secret: HmacSecret,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_send_welcome_email(&pool, &email_client, &base_url, &hmac_secret).await {
+            Ok(WelcomeOutcome::NothingDue) => {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(WelcomeOutcome::EmailSent | WelcomeOutcome::EmailPostponed) => {}
+        }
+    }
+}
+
+pub enum WelcomeOutcome {
+    EmailSent,
+    // The email could not be sent and will be tried again later.
+    EmailPostponed,
+    NothingDue,
+}
+
+/// How many times a welcome email that could not be sent is tried again,
+/// after 5 minutes, then 10, 20... before it is dropped.
+const MAX_RETRIES: i32 = 5;
+
+/// Send one email of the welcome sequence whose time has come, if any.
+/// Emails of subscribers who are no longer confirmed are skipped and left in
+/// the queue: unsubscribing is what removes them from it.
+#[tracing::instrument(
+    skip_all,
+    fields(
+        welcome_email_id=tracing::field::Empty,
+        subscriber_id=tracing::field::Empty
+    ),
+    err
+)]
+pub async fn try_send_welcome_email(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    hmac_secret: &HmacSecret,
+) -> Result<WelcomeOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let task = sqlx::query!(
+        r#"
+        SELECT
+            q.subscriber_id,
+            q.welcome_email_id,
+            q.n_retries,
+            s.email,
+            w.subject,
+            w.html_content,
+            w.text_content
+        FROM welcome_email_queue q
+        JOIN welcome_emails w ON w.welcome_email_id = q.welcome_email_id
+        JOIN subscriptions s ON s.id = q.subscriber_id
+        WHERE q.send_at <= now() AND s.status = 'confirmed'
+        ORDER BY q.send_at
+        FOR UPDATE OF q
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(&mut transaction)
+    .await?;
+    let task = match task {
+        Some(task) => task,
+        None => return Ok(WelcomeOutcome::NothingDue),
+    };
+    Span::current()
+        .record("welcome_email_id", display(task.welcome_email_id))
+        .record("subscriber_id", display(task.subscriber_id));
+    match SubscriberEmail::parse(task.email) {
+        Ok(email) => {
+            let recipient = get_recipient(pool, task.subscriber_id).await?;
+            let (html_content, text_content) = personalise(
+                &EmailLayout::default_layout(),
+                (&task.html_content, &task.text_content),
+                &recipient,
+                base_url,
+                hmac_secret,
+            );
+            let outcome = match recipient.email_format {
+                EmailFormat::Html => {
+                    email_client
+                        .send_email(&email, &task.subject, &html_content, &text_content)
+                        .await
+                }
+                EmailFormat::PlainText => {
+                    email_client
+                        .send_plain_text_email(&email, &task.subject, &text_content)
+                        .await
+                }
+            };
+            if let Err(e) = outcome {
+                if task.n_retries < MAX_RETRIES {
+                    tracing::warn!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to send a welcome email to a confirmed subscriber. \
+                            Trying again later.",
+                    );
+                    postpone_welcome_email(
+                        &mut transaction,
+                        task.subscriber_id,
+                        task.welcome_email_id,
+                    )
+                    .await?;
+                    transaction.commit().await?;
+                    return Ok(WelcomeOutcome::EmailPostponed);
+                }
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    n_retries = task.n_retries,
+                    "Failed to send a welcome email to a confirmed subscriber. \
+                        Giving up.",
+                );
+            }
+        }
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Skipping a confirmed subscriber. \
+                    Their stored contact details are invalid",
+            );
+        }
+    }
+    sqlx::query!(
+        r#"
+        DELETE FROM welcome_email_queue
+        WHERE subscriber_id = $1 AND welcome_email_id = $2
+        "#,
+        task.subscriber_id,
+        task.welcome_email_id,
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(WelcomeOutcome::EmailSent)
+}
+
+/// Try a welcome email again later, waiting twice as long as last time.
+#[tracing::instrument(skip(transaction))]
+async fn postpone_welcome_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    welcome_email_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE welcome_email_queue
+        SET
+            n_retries = n_retries + 1,
+            send_at = now() + interval '5 minutes' * power(2, n_retries)
+        WHERE subscriber_id = $1 AND welcome_email_id = $2
+        "#,
+        subscriber_id,
+        welcome_email_id,
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Queue the welcome sequence of a subscriber who just confirmed their
+/// subscription, each email after its delay.
+#[tracing::instrument(skip(transaction))]
+pub async fn enqueue_welcome_emails(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO welcome_email_queue (subscriber_id, welcome_email_id, send_at)
+        SELECT $1, welcome_email_id, now() + make_interval(days => delay_days)
+        FROM welcome_emails
+        ON CONFLICT DO NOTHING
+        "#,
+        subscriber_id,
+    )
+    .execute(transaction)
+    .await?;
+    Ok(result.rows_affected())
+}
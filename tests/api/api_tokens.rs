@@ -0,0 +1,190 @@
+use crate::helpers::{assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn newsletter_request_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    })
+}
+
+#[tokio::test]
+async fn an_api_token_can_publish_a_newsletter() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let token = app.create_api_token(&["newsletters:publish"]).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_api_publish_newsletter(&token, None, &newsletter_request_body())
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 202);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let issue_id = body["issue_id"].as_str().unwrap();
+    let issue = sqlx::query!(
+        "SELECT title FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        Uuid::parse_str(issue_id).unwrap()
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(issue.title, "Newsletter title");
+    let html_page = app.get_api_tokens_html().await;
+    assert!(html_page.contains("Test token"));
+    assert!(!html_page.contains("never"));
+    assert!(!html_page.contains(&token));
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email
+}
+
+#[tokio::test]
+async fn api_publishing_honours_the_idempotency_key() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let token = app.create_api_token(&["newsletters:publish"]).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let idempotency_key = Uuid::new_v4().to_string();
+
+    // Act
+    let first = app
+        .post_api_publish_newsletter(&token, Some(&idempotency_key), &newsletter_request_body())
+        .await;
+    let second = app
+        .post_api_publish_newsletter(&token, Some(&idempotency_key), &newsletter_request_body())
+        .await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 202);
+    assert_eq!(second.status().as_u16(), 202);
+    assert_eq!(first.text().await.unwrap(), second.text().await.unwrap());
+    let issues = sqlx::query!("SELECT count(*) AS \"n!\" FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issues.n, 1);
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email once
+}
+
+#[tokio::test]
+async fn missing_invalid_and_revoked_tokens_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = app.create_api_token(&["newsletters:publish"]).await;
+    let api_token_id = sqlx::query!("SELECT api_token_id FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .api_token_id;
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/api-tokens/{}/delete",
+            app.address, api_token_id
+        ))
+        .header("X-CSRF-Token", app.csrf_token().await)
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/api-tokens");
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - No token at all
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/v1/newsletters", &app.address))
+        .json(&newsletter_request_body())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.headers()["WWW-Authenticate"],
+        r#"Bearer realm="api""#
+    );
+
+    // Act - Part 2 - Unknown and revoked tokens
+    for token in ["not-a-real-token", token.as_str()] {
+        let response = app
+            .post_api_publish_newsletter(token, None, &newsletter_request_body())
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Assert
+    let issues = sqlx::query!("SELECT count(*) AS \"n!\" FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issues.n, 0);
+}
+
+#[tokio::test]
+async fn the_tokens_of_viewers_cannot_publish() {
+    // Arrange
+    let app = spawn_app().await;
+    app.set_test_user_role("viewer").await;
+    app.test_user.login(&app).await;
+    let token = app.create_api_token(&["newsletters:publish"]).await;
+
+    // Act
+    let response = app
+        .post_api_publish_newsletter(&token, None, &newsletter_request_body())
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "forbidden");
+}
+
+#[tokio::test]
+async fn tokens_need_a_name_and_a_scope() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/admin/api-tokens", &app.address))
+        .header("X-CSRF-Token", app.csrf_token().await)
+        .form(&[("name", "Test token")])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/api-tokens");
+    let html_page = app.get_api_tokens_html().await;
+    assert!(html_page.contains("<p><i>Pick at least one scope.</i></p>"));
+    let tokens = sqlx::query!("SELECT count(*) AS \"n!\" FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(tokens.n, 0);
+}
@@ -0,0 +1,139 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn confirmation_email_body() -> serde_json::Value {
+    serde_json::json!({
+        "subject": "Almost there, {{ name }}",
+        "html_body": r#"<p>Hi {{ name }}, <a href="{{ confirmation_link }}">confirm here</a>.</p>"#,
+        "text_body": "Hi {{ name }}, confirm at {{ confirmation_link }}",
+    })
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_edit_the_confirmation_email() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_confirmation_email(&confirmation_email_body())
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_default_confirmation_email_is_shown_until_customised() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let html_page = app.get_confirmation_email_html().await;
+
+    // Assert
+    assert!(html_page.contains("Visit {{ confirmation_link }} to confirm your subscription."));
+}
+
+#[tokio::test]
+async fn new_subscribers_receive_the_customised_confirmation_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let response = app
+        .post_confirmation_email(&confirmation_email_body())
+        .await;
+    assert_is_redirect_to(&response, "/admin/confirmation-email");
+    let html_page = app.get_confirmation_email_html().await;
+    assert!(html_page.contains("<p><i>The confirmation email has been saved.</i></p>"));
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["Subject"], "Almost there, le guin");
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .starts_with("Hi le guin, confirm at "));
+    let confirmation_links = app.get_confirmation_links(email_request);
+    assert_eq!(confirmation_links.html, confirmation_links.plain_text);
+}
+
+#[tokio::test]
+async fn the_confirmation_link_is_required() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let test_cases = vec![
+        (
+            "html_body",
+            "<p>Hi {{ name }}</p>",
+            "The HTML body must contain `{{ confirmation_link }}`.",
+        ),
+        (
+            "text_body",
+            "Hi {{ name }}",
+            "The plain text body must contain `{{ confirmation_link }}`.",
+        ),
+        (
+            "subject",
+            "Hi {{ preferences_url }}",
+            "The subject uses an unknown merge field: `{{ preferences_url }}`.",
+        ),
+    ];
+
+    for (field, value, error_message) in test_cases {
+        let mut body = confirmation_email_body();
+        body[field] = value.into();
+
+        // Act
+        let response = app.post_confirmation_email(&body).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 200);
+        let html_page = response.text().await.unwrap();
+        assert!(
+            html_page.contains(&htmlescape::encode_minimal(error_message)),
+            "The form did not explain that {}",
+            error_message
+        );
+    }
+    let html_page = app.get_confirmation_email_html().await;
+    assert!(html_page.contains("Visit {{ confirmation_link }} to confirm your subscription."));
+}
+
+#[tokio::test]
+async fn the_confirmation_email_can_be_previewed_without_saving_it() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_preview_confirmation_email(&confirmation_email_body())
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<h1>Almost there, Jane Doe</h1>"));
+    assert!(html_page.contains(&format!(
+        "Hi Jane Doe, confirm at {}/subscriptions/confirm?subscription_token=preview",
+        app.base_url
+    )));
+    let html_page = app.get_confirmation_email_html().await;
+    assert!(html_page.contains("Visit {{ confirmation_link }} to confirm your subscription."));
+}
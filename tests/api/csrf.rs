@@ -0,0 +1,101 @@
+use crate::helpers::{assert_is_redirect_to, draft_body, spawn_app};
+
+#[tokio::test]
+async fn admin_forms_without_the_csrf_token_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Logout
+    let response = app
+        .api_client
+        .post(format!("{}/admin/logout", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+
+    // Act - Part 2 - Save a draft, with a token that is not the session's
+    let response = app
+        .api_client
+        .post(format!("{}/admin/newsletters/issues", &app.address))
+        .header("X-CSRF-Token", "forged")
+        .form(&draft_body("Newsletter title"))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+    let issues = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(issues.is_empty());
+    let response = app.get_admin_dashborad().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn the_csrf_token_can_be_sent_as_a_form_field() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let csrf_token = app.csrf_token().await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/admin/logout", &app.address))
+        .form(&[("csrf_token", &csrf_token)])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_admin_dashborad().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn admin_forms_carry_the_csrf_token_of_the_session() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let csrf_token = app.csrf_token().await;
+    let html_page = app.get_change_password_html().await;
+
+    // Assert
+    assert_eq!(csrf_token.len(), 32);
+    assert!(html_page.contains(&format!(
+        r#"<input type="hidden" name="csrf_token" value="{}">"#,
+        csrf_token
+    )));
+}
+
+#[tokio::test]
+async fn the_session_cookie_is_never_sent_along_with_cross_site_requests() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+
+    // Assert
+    let session_cookie = response
+        .headers()
+        .get_all("Set-Cookie")
+        .iter()
+        .map(|h| h.to_str().unwrap())
+        .find(|h| h.starts_with("id="))
+        .expect("The session cookie was not set.");
+    assert!(session_cookie.contains("SameSite=Strict"));
+}
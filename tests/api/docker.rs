@@ -105,9 +105,9 @@ fn extract_ip_and_port(id: String, port: String) -> Result<NetworkSettings, anyh
     }
 
     let json_string = String::from_utf8(output.stdout)?;
-    let datas: Vec<NetworkSettings> = serde_json::from_str(&json_string.trim().trim_matches('\''))?;
+    let datas: Vec<NetworkSettings> = serde_json::from_str(json_string.trim().trim_matches('\''))?;
     assert!(
-        datas.len() >= 1,
+        !datas.is_empty(),
         "The container[{id}] cannnot find NetworkSettings.Ports"
     );
     let mut network_settings = NetworkSettings::default();
@@ -0,0 +1,232 @@
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, create_template, spawn_app, template_body,
+};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_templates() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_create_template(&template_body("Default")).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn templates_can_be_created_and_edited() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Create
+    let template_id = create_template(&app, "Default").await;
+    let html_page = app.get_templates_html().await;
+    assert!(html_page.contains("<p><i>The template has been saved.</i></p>"));
+    assert!(html_page.contains("<td>Default</td>"));
+
+    // Act - Part 2 - Edit
+    let mut body = template_body("Weekly");
+    body["text_layout"] = "{{ content }}\n\nBye: {{ unsubscribe_url }}".into();
+    let response = app.post_update_template(&template_id, &body).await;
+    assert_is_redirect_to(&response, "/admin/templates");
+
+    // Assert
+    let html_page = app.get_edit_template_html(&template_id).await;
+    assert!(html_page.contains(r#"value="Weekly""#));
+    assert!(html_page.contains("Bye: {{ unsubscribe_url }}</textarea>"));
+}
+
+#[tokio::test]
+async fn invalid_templates_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    create_template(&app, "Default").await;
+    let test_cases = vec![
+        (
+            serde_json::json!({
+                "name": "No content",
+                "html_layout": "<a href=\"{{ unsubscribe_url }}\">Unsubscribe</a>",
+                "text_layout": "{{ content }} {{ unsubscribe_url }}",
+            }),
+            "The HTML layout must contain `{{ content }}` exactly once.",
+        ),
+        (
+            serde_json::json!({
+                "name": "No way out",
+                "html_layout": "{{ content }} {{ unsubscribe_url }}",
+                "text_layout": "{{ content }}",
+            }),
+            "The plain text layout must link to",
+        ),
+        (
+            serde_json::json!({
+                "name": "Typo",
+                "html_layout": "{{ content }} {{ unsubscribe_url }} {{ nmae }}",
+                "text_layout": "{{ content }} {{ unsubscribe_url }}",
+            }),
+            "The HTML layout uses an unknown merge field: `{{ nmae }}`.",
+        ),
+        (
+            template_body("Default"),
+            "There is already a template named Default.",
+        ),
+    ];
+
+    for (body, error_message) in test_cases {
+        // Act
+        let response = app.post_create_template(&body).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 200);
+        let html_page = response.text().await.unwrap();
+        assert!(
+            html_page.contains(error_message),
+            "The page did not explain that: {}",
+            error_message
+        );
+    }
+    let n_templates = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM email_templates")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_templates, 1);
+}
+
+#[tokio::test]
+async fn issues_are_wrapped_in_their_template_for_each_recipient() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let template_id = create_template(&app, "Default").await;
+    let name = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .name;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Past issues: {{ archive_url }}",
+        "html_content": "<p>Past issues: {{ archive_url }}</p>",
+        "template_id": template_id,
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let html_body = body["HtmlBody"].as_str().unwrap();
+    let text_body = body["TextBody"].as_str().unwrap();
+    assert!(html_body.starts_with(&format!(
+        "<h1>Hi {}!</h1><p>Past issues: {}/issues</p>",
+        htmlescape::encode_minimal(&name),
+        app.base_url
+    )));
+    assert!(text_body.starts_with(&format!(
+        "Hi {}!\n\nPast issues: {}/issues\n\nUnsubscribe: ",
+        name, app.base_url
+    )));
+
+    // The unsubscribe link asks for a confirmation
+    let links: Vec<_> = linkify::LinkFinder::new()
+        .links(text_body)
+        .filter(|l| *l.kind() == linkify::LinkKind::Url)
+        .collect();
+    let mut unsubscribe_link = reqwest::Url::parse(links[1].as_str()).unwrap();
+    assert_eq!(unsubscribe_link.path(), "/subscriptions/unsubscribe");
+    unsubscribe_link.set_port(Some(app.port)).unwrap();
+    let response = app
+        .api_client
+        .get(unsubscribe_link.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("<button type=\"submit\">Unsubscribe</button>"));
+    let status = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .status;
+    assert_eq!(status, "confirmed");
+}
+
+#[tokio::test]
+async fn the_preview_shows_the_template_with_placeholders() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let template_id = create_template(&app, "Default").await;
+
+    // Act
+    let mut body = crate::helpers::draft_body("Templated");
+    body["template_id"] = template_id.clone().into();
+    let response = app.post_create_draft(&body).await;
+    let location = response.headers()["Location"].to_str().unwrap();
+    let issue_id = location.rsplit('/').next().unwrap();
+
+    // Assert
+    let html_page = app.get_edit_draft_html(issue_id).await;
+    assert!(html_page.contains(&format!(
+        r#"<option value="{}" selected>Default</option>"#,
+        template_id
+    )));
+    let html_page = app.get_preview_issue_html(issue_id).await;
+    assert!(html_page.contains(
+        "<pre style=\"white-space: pre-wrap;\">Hi {{ name }}!\n\nNewsletter body as plain text\n\nUnsubscribe: {{ unsubscribe_url }}</pre>"
+    ));
+}
+
+#[tokio::test]
+async fn issues_with_an_unknown_template_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = crate::helpers::create_draft(&app, "Templated").await;
+    let mut body = crate::helpers::draft_body("Templated");
+    body["template_id"] = uuid::Uuid::new_v4().to_string().into();
+
+    // Act
+    let create_response = app.post_create_draft(&body).await;
+    let update_response = app.post_update_draft(&issue_id, &body).await;
+    let publish_response = app.post_publish_newsletter(&body).await;
+
+    // Assert
+    for response in [create_response, update_response, publish_response] {
+        assert_eq!(response.status().as_u16(), 400);
+    }
+    let count = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(count, 1);
+}
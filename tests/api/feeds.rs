@@ -0,0 +1,150 @@
+use crate::helpers::{publish_issue, spawn_app, TestApp};
+
+async fn get_feed(app: &TestApp, feed: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!("{}/{}", &app.address, feed))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn the_rss_feed_lists_public_issues() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "Fish & chips", true).await;
+    publish_issue(&app, "Members only", false).await;
+
+    // Act
+    let response = get_feed(&app, "feed.rss").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/rss+xml; charset=utf-8"
+    );
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<title>Fish &amp; chips</title>"));
+    assert!(body.contains(&format!("<link>{}/issues/fish-chips</link>", app.base_url)));
+    assert!(body.contains("&lt;p&gt;Newsletter body as HTML&lt;/p&gt;"));
+    assert!(!body.contains("Members only"));
+}
+
+#[tokio::test]
+async fn the_atom_feed_lists_public_issues() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "Fish & chips", true).await;
+    publish_issue(&app, "Members only", false).await;
+
+    // Act
+    let response = get_feed(&app, "feed.atom").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/atom+xml; charset=utf-8"
+    );
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<title>Fish &amp; chips</title>"));
+    assert!(body.contains(&format!(
+        r#"<link href="{}/issues/fish-chips"/>"#,
+        app.base_url
+    )));
+    assert!(!body.contains("Members only"));
+}
+
+#[tokio::test]
+async fn feeds_are_not_sent_again_if_the_client_has_them_already() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "Fish & chips", true).await;
+
+    for feed in ["feed.rss", "feed.atom"] {
+        let response = get_feed(&app, feed).await;
+        let etag = response.headers()["ETag"].to_str().unwrap().to_owned();
+        let last_modified = response.headers()["Last-Modified"]
+            .to_str()
+            .unwrap()
+            .to_owned();
+
+        // Act
+        let etag_response = app
+            .api_client
+            .get(format!("{}/{}", &app.address, feed))
+            .header("If-None-Match", &etag)
+            .send()
+            .await
+            .unwrap();
+        let date_response = app
+            .api_client
+            .get(format!("{}/{}", &app.address, feed))
+            .header("If-Modified-Since", &last_modified)
+            .send()
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(etag_response.status().as_u16(), 304);
+        assert_eq!(date_response.status().as_u16(), 304);
+    }
+}
+
+#[tokio::test]
+async fn feeds_change_when_an_issue_is_published() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "Fish & chips", true).await;
+    let response = get_feed(&app, "feed.rss").await;
+    let etag = response.headers()["ETag"].to_str().unwrap().to_owned();
+
+    // Act
+    publish_issue(&app, "Mushy peas", true).await;
+    let response = app
+        .api_client
+        .get(format!("{}/feed.rss", &app.address))
+        .header("If-None-Match", &etag)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Mushy peas"));
+}
+
+#[tokio::test]
+async fn feeds_change_when_an_issue_is_removed_from_the_archive() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "Fish & chips", true).await;
+    let issue_id = publish_issue(&app, "Mushy peas", true).await;
+    let response = get_feed(&app, "feed.rss").await;
+    let last_modified = response.headers()["Last-Modified"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+    // HTTP dates have a one second resolution.
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    // Act
+    app.post_issue_visibility(&issue_id, false).await;
+    let response = app
+        .api_client
+        .get(format!("{}/feed.rss", &app.address))
+        .header("If-Modified-Since", &last_modified)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!response.text().await.unwrap().contains("Mushy peas"));
+}
@@ -3,16 +3,23 @@ use std::{thread, time};
 
 use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
 use argon2::{Algorithm, Params, Version};
+use fake::faker::internet::en::SafeEmail;
+use fake::faker::name::en::Name;
+use fake::Fake;
 use once_cell::sync::Lazy;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 use zero2prod::configuration::{get_configuration, DatabaseSettings};
 use zero2prod::email_client::EmailClient;
 use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
-use zero2prod::startup::Application;
+use zero2prod::issue_scheduler::{try_publish_scheduled_issue, SchedulerOutcome};
+use zero2prod::routes::preferences_link;
+use zero2prod::startup::{Application, HmacSecret};
 use zero2prod::telemetry;
+use zero2prod::welcome_email_worker::{try_send_welcome_email, WelcomeOutcome};
 
 use crate::docker::{start_container, stop_container, Container};
 
@@ -45,6 +52,8 @@ pub struct TestApp {
     pub test_user: TestUser,
     pub api_client: reqwest::Client,
     pub email_client: EmailClient,
+    pub base_url: String,
+    pub hmac_secret: HmacSecret,
 }
 
 /// Confirmation links embedded in the request to the email API.
@@ -57,7 +66,7 @@ pub struct ConfirmationLinks {
 impl TestApp {
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/subscriptions", &self.address))
+            .post(format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body)
             .send()
@@ -94,8 +103,8 @@ impl TestApp {
             confirmation_link
         };
 
-        let html = get_link(&body["HtmlBody"].as_str().unwrap());
-        let plain_text = get_link(&body["TextBody"].as_str().unwrap());
+        let html = get_link(body["HtmlBody"].as_str().unwrap());
+        let plain_text = get_link(body["TextBody"].as_str().unwrap());
         ConfirmationLinks { html, plain_text }
     }
 
@@ -104,16 +113,179 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .form(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    /// Test users are owners unless a test demotes them.
+    pub async fn set_test_user_role(&self, role: &str) {
+        sqlx::query!(
+            "UPDATE users SET role = $1 WHERE user_id = $2",
+            role,
+            self.test_user.user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .expect("Failed to change the role of the test user.");
+    }
+
+    pub async fn get_users_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/users", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_add_user<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/users", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_user_role(&self, user_id: Uuid, role: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/users/{}/role", &self.address, user_id))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(&[("role", role)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_remove_user(&self, user_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/users/{}/delete", &self.address, user_id))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_invite_user(&self, email: &str, role: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/users/invitations", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(&[("email", email), ("role", role)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_accept_invitation<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/login/accept-invitation", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_login_two_factor(&self, code: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/login/two-factor", &self.address))
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_two_factor_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/two-factor", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_enrol_two_factor(&self, code: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/two-factor", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_disable_two_factor(&self, code: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/two-factor/disable", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_forgot_password(&self, username: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/login/forgot-password", &self.address))
+            .form(&[("username", username)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/login/reset-password", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_account_email(
+        &self,
+        email: &str,
+        current_password: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/email", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(&[("email", email), ("current_password", current_password)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_account_email_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/email", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
     pub async fn get_login_html(&self) -> String {
         self.api_client
-            .get(&format!("{}/login", &self.address))
+            .get(format!("{}/login", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -124,7 +296,7 @@ impl TestApp {
 
     pub async fn get_admin_dashborad(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/dashboard", &self.address))
+            .get(format!("{}/admin/dashboard", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -134,9 +306,19 @@ impl TestApp {
         self.get_admin_dashborad().await.text().await.unwrap()
     }
 
+    /// The token the admin forms carry, empty when nobody is logged in.
+    pub async fn csrf_token(&self) -> String {
+        let html = self.get_admin_dashborad_html().await;
+        html.split(r#"name="csrf_token" value=""#)
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap_or_default()
+            .to_owned()
+    }
+
     pub async fn get_change_password(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/password", &self.address))
+            .get(format!("{}/admin/password", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -151,7 +333,8 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/password", &self.address))
+            .post(format!("{}/admin/password", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
             .form(body)
             .send()
             .await
@@ -160,7 +343,8 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/admin/logout", &self.address))
+            .post(format!("{}/admin/logout", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
             .send()
             .await
             .expect("Failed to execute request.")
@@ -171,16 +355,122 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/newsletters", &self.address))
+            .post(format!("{}/admin/newsletters", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
             .form(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    /// Another browser, logging in from another address.
+    pub fn other_browser(&self, user_agent: &str) -> reqwest::Client {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("X-Forwarded-For", "192.0.2.1".parse().unwrap());
+        reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .cookie_store(true)
+            .user_agent(user_agent)
+            .default_headers(headers)
+            .build()
+            .unwrap()
+    }
+
+    pub async fn get_sessions_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_revoke_session(&self, session_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/sessions/{}/delete",
+                &self.address, session_id
+            ))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_log_out_everywhere(&self) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/sessions/delete", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_tokens_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/api-tokens", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// Create an API token with these scopes and return it.
+    pub async fn create_api_token(&self, scopes: &[&str]) -> String {
+        let mut form = vec![("name", "Test token")];
+        form.extend(scopes.iter().map(|scope| (*scope, "on")));
+        let html_page = self
+            .api_client
+            .post(format!("{}/admin/api-tokens", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(&form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap();
+        let start = html_page.find("<code>").unwrap() + "<code>".len();
+        let end = html_page.find("</code>").unwrap();
+        html_page[start..end].to_string()
+    }
+
+    /// Publish through the JSON API, without a session cookie.
+    pub async fn post_api_publish_newsletter(
+        &self,
+        token: &str,
+        idempotency_key: Option<&str>,
+        body: &serde_json::Value,
+    ) -> reqwest::Response {
+        let mut request = reqwest::Client::new()
+            .post(format!("{}/api/v1/newsletters", &self.address))
+            .bearer_auth(token)
+            .json(body);
+        if let Some(idempotency_key) = idempotency_key {
+            request = request.header("Idempotency-Key", idempotency_key);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    /// A request to `/api/v1{path}` carrying this token, without a session cookie.
+    pub fn api_request(
+        &self,
+        method: reqwest::Method,
+        token: &str,
+        path: &str,
+    ) -> reqwest::RequestBuilder {
+        reqwest::Client::new()
+            .request(method, format!("{}/api/v1{}", &self.address, path))
+            .bearer_auth(token)
+    }
+
     pub async fn get_publish_newsletter(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/newsletters", &self.address))
+            .get(format!("{}/admin/newsletters", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -190,12 +480,450 @@ impl TestApp {
         self.get_publish_newsletter().await.text().await.unwrap()
     }
 
+    pub async fn post_count_newsletter_recipients<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/newsletters/recipients", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_send_test_copy<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/newsletters/test", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_templates_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/templates", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_create_template<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/templates", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_edit_template_html(&self, template_id: &str) -> String {
+        self.api_client
+            .get(format!("{}/admin/templates/{}", &self.address, template_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_update_template<Body>(
+        &self,
+        template_id: &str,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/templates/{}", &self.address, template_id))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_confirmation_email_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/confirmation-email", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_confirmation_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/confirmation-email", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_preview_confirmation_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!(
+                "{}/admin/confirmation-email/preview",
+                &self.address
+            ))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_create_draft<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/newsletters/issues", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_issues(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/newsletters/issues", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_issues_html(&self) -> String {
+        self.get_issues().await.text().await.unwrap()
+    }
+
+    pub async fn get_edit_draft(&self, issue_id: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/issues/{}",
+                &self.address, issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_edit_draft_html(&self, issue_id: &str) -> String {
+        self.get_edit_draft(issue_id).await.text().await.unwrap()
+    }
+
+    pub async fn post_update_draft<Body>(&self, issue_id: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}",
+                &self.address, issue_id
+            ))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_preview_issue_html(&self, issue_id: &str) -> String {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/issues/{}/preview",
+                &self.address, issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_send_draft(&self, issue_id: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}/send",
+                &self.address, issue_id
+            ))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscribers(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscribers_html(&self) -> String {
+        self.get_subscribers().await.text().await.unwrap()
+    }
+
+    pub async fn post_subscriber_tags<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/subscribers/tags", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_import_subscribers<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/subscribers/import", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Build a signed link to the preference center of the subscriber
+    /// registered with `email`, pointing at the test application.
+    pub async fn get_preferences_link(&self, email: &str) -> reqwest::Url {
+        let subscriber_id = sqlx::query!("SELECT id FROM subscriptions WHERE email = $1", email)
+            .fetch_one(&self.db_pool)
+            .await
+            .expect("Failed to fetch subscriber id.")
+            .id;
+        let raw_link = preferences_link(&self.base_url, subscriber_id, &self.hmac_secret);
+        let mut link = reqwest::Url::parse(&raw_link).unwrap();
+        link.set_port(Some(self.port)).unwrap();
+        link
+    }
+
+    pub async fn get_preferences(&self, link: &reqwest::Url) -> reqwest::Response {
+        self.api_client
+            .get(link.clone())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_preferences_html(&self, link: &reqwest::Url) -> String {
+        self.get_preferences(link).await.text().await.unwrap()
+    }
+
+    pub async fn post_preferences(
+        &self,
+        link: &reqwest::Url,
+        body: &[(&str, &str)],
+    ) -> reqwest::Response {
+        self.api_client
+            .post(link.clone())
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_unsubscribe(&self, link: &reqwest::Url) -> reqwest::Response {
+        let mut link = link.clone();
+        link.set_path("/subscriptions/unsubscribe");
+        self.api_client
+            .post(link)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn publish_due_issues(&self) {
+        while let SchedulerOutcome::IssuePublished =
+            try_publish_scheduled_issue(&self.db_pool).await.unwrap()
+        {}
+    }
+
+    pub async fn send_due_welcome_emails(&self) {
+        while let WelcomeOutcome::EmailSent | WelcomeOutcome::EmailPostponed =
+            try_send_welcome_email(
+                &self.db_pool,
+                &self.email_client,
+                &self.base_url,
+                &self.hmac_secret,
+            )
+            .await
+            .unwrap()
+        {}
+    }
+
+    pub async fn get_welcome_emails_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/welcome-emails", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_create_welcome_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/welcome-emails", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_delete_welcome_email(&self, welcome_email_id: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/welcome-emails/{}/delete",
+                &self.address, welcome_email_id
+            ))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_schedule_issue(
+        &self,
+        issue_id: &str,
+        scheduled_for: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}/schedule",
+                &self.address, issue_id
+            ))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(&serde_json::json!({ "scheduled_for": scheduled_for }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_unschedule_issue(&self, issue_id: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}/unschedule",
+                &self.address, issue_id
+            ))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// POST to one of the delivery controls of an issue: `pause`, `resume` or `cancel`.
+    pub async fn post_issue_action(&self, issue_id: &str, action: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}/{}",
+                &self.address, issue_id, action
+            ))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_delivery_status_html(&self, issue_id: &str) -> String {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/issues/{}/status",
+                &self.address, issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_issue_visibility(
+        &self,
+        issue_id: &str,
+        is_public: bool,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}/visibility",
+                &self.address, issue_id
+            ))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(&serde_json::json!({ "is_public": is_public }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_issue_archive_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/issues", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_public_issue(&self, slug: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/issues/{}", &self.address, slug))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn dispatch_all_pending_emails(&self) {
         loop {
-            if let ExecutionOutcome::EmptyQueue =
-                try_execute_task(&self.db_pool, &self.email_client)
-                    .await
-                    .unwrap()
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                &self.db_pool,
+                &self.email_client,
+                &self.base_url,
+                &self.hmac_secret,
+            )
+            .await
+            .unwrap()
             {
                 break;
             }
@@ -238,8 +966,8 @@ impl TestUser {
         .to_string();
         sqlx::query!(
             r#"
-            INSERT INTO users (user_id, username, password_hash)
-            VALUES ($1, $2, $3)
+            INSERT INTO users (user_id, username, password_hash, role)
+            VALUES ($1, $2, $3, 'owner')
         "#,
             self.user_id,
             self.username,
@@ -280,6 +1008,12 @@ pub async fn spawn_app() -> TestApp {
 
         // Use the mock server as email API
         c.email_client.base_url = email_server.uri();
+        // Topics subscribers can manage from their preference center
+        c.newsletter.topics = vec!["announcements".into(), "tutorials".into()];
+        c.newsletter.reviewer_emails = vec!["editor@example.com".into()];
+        // The test client stands in for a reverse proxy, so that it can tell
+        // which address each request comes from.
+        c.application.trusted_proxies = vec!["127.0.0.1".parse().unwrap()];
         c
     };
 
@@ -289,11 +1023,20 @@ pub async fn spawn_app() -> TestApp {
         .await
         .expect("Failed to build application.");
     let application_port = app.port();
-    let _ = tokio::spawn(app.run_until_stopped());
+    tokio::spawn(app.run_until_stopped());
 
+    // Every test app logs in from its own address, forwarded by the trusted
+    // proxy, so that the failed logins of one test do not lock out the others.
+    let [_, a, b, c, ..] = *Uuid::new_v4().as_bytes();
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+        "X-Forwarded-For",
+        format!("10.{}.{}.{}", a, b, c).parse().unwrap(),
+    );
     let client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::none())
         .cookie_store(true)
+        .default_headers(headers)
         .build()
         .unwrap();
     let test_app = TestApp {
@@ -304,7 +1047,9 @@ pub async fn spawn_app() -> TestApp {
         container_id: container.id,
         test_user: TestUser::generate(),
         api_client: client,
-        email_client: conf.email_client.client(),
+        email_client: conf.email_client.clone().client(),
+        base_url: conf.application.base_url.clone(),
+        hmac_secret: HmacSecret(conf.application.hmac_secret.clone()),
     };
     test_app.test_user.store(&test_app.db_pool).await;
     test_app
@@ -390,6 +1135,140 @@ pub async fn configure_database(
 //     dbg!(resp);
 // }
 
+pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
+    create_unconfirmed_subscriber_with_tags(app, "").await
+}
+
+pub async fn create_unconfirmed_subscriber_with_tags(
+    app: &TestApp,
+    tags: &str,
+) -> ConfirmationLinks {
+    // We are working with multiple subscribers now,
+    // their details must be randomised to avoid conflicts!
+    let name: String = Name().fake();
+    let email: String = SafeEmail().fake();
+    let body = serde_urlencoded::to_string(serde_json::json!({
+        "name": name,
+        "email": email,
+        "tags": tags
+    }))
+    .unwrap();
+
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .named("Create unconfirmed subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    app.get_confirmation_links(email_request)
+}
+
+pub async fn create_confirmed_subscriber(app: &TestApp) {
+    create_confirmed_subscriber_with_tags(app, "").await
+}
+
+pub async fn create_confirmed_subscriber_with_tags(app: &TestApp, tags: &str) {
+    let confirmation_link = create_unconfirmed_subscriber_with_tags(app, tags)
+        .await
+        .html;
+    reqwest::get(confirmation_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+pub fn draft_body(title: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": title,
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    })
+}
+
+pub fn template_body(name: &str) -> serde_json::Value {
+    serde_json::json!({
+        "name": name,
+        "html_layout": r#"<h1>Hi {{ name }}!</h1>{{ content }}<a href="{{ unsubscribe_url }}">Unsubscribe</a>"#,
+        "text_layout": "Hi {{ name }}!\n\n{{ content }}\n\nUnsubscribe: {{ unsubscribe_url }}",
+    })
+}
+
+/// Save a new email template and return its id.
+pub async fn create_template(app: &TestApp, name: &str) -> String {
+    let response = app.post_create_template(&template_body(name)).await;
+    assert_is_redirect_to(&response, "/admin/templates");
+    sqlx::query!(
+        "SELECT template_id FROM email_templates WHERE name = $1",
+        name
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .template_id
+    .to_string()
+}
+
+/// Save a new draft and return its id.
+pub async fn create_draft(app: &TestApp, title: &str) -> String {
+    let response = app.post_create_draft(&draft_body(title)).await;
+    assert_eq!(response.status().as_u16(), 303);
+    let location = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    location
+        .strip_prefix("/admin/newsletters/issues/")
+        .unwrap()
+        .to_string()
+}
+
+pub async fn issue_status(app: &TestApp, issue_id: &str) -> String {
+    sqlx::query!(
+        "SELECT status FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        uuid::Uuid::parse_str(issue_id).unwrap()
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .status
+}
+
+/// Write and send an issue, listing it in the web archive or not.
+pub async fn publish_issue(app: &TestApp, title: &str, is_public: bool) -> String {
+    let mut body = draft_body(title);
+    body["is_public"] = is_public.into();
+    let response = app.post_create_draft(&body).await;
+    let location = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let issue_id = location
+        .strip_prefix("/admin/newsletters/issues/")
+        .unwrap()
+        .to_string();
+    app.post_send_draft(&issue_id).await;
+    issue_id
+}
+
 // Little helper function - we will be doing this check several times throughout
 // this chapter and the next one.
 pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
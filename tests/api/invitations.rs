@@ -0,0 +1,212 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const PASSWORD: &str = "a long enough password";
+
+/// Invite `email` as the test user and return the token of the invitation.
+async fn invite(app: &TestApp, email: &str, role: &str) -> String {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let response = app.post_invite_user(email, role).await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let email_request = app.email_server.received_requests().await.unwrap().pop();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.unwrap().body).unwrap();
+    assert_eq!(body["To"], email);
+    let text = body["TextBody"].as_str().unwrap();
+    let start = text.find("?token=").unwrap() + 7;
+    let end = start + text[start..].find(' ').unwrap();
+    text[start..end].to_string()
+}
+
+async fn get_accept_invitation_form(app: &TestApp, token: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!(
+            "{}/login/accept-invitation?token={}",
+            app.address, token
+        ))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn accept(app: &TestApp, token: &str, username: &str) -> reqwest::Response {
+    app.post_accept_invitation(&serde_json::json!({
+        "token": token,
+        "username": username,
+        "password": PASSWORD,
+        "password_check": PASSWORD,
+    }))
+    .await
+}
+
+async fn find_user(app: &TestApp, username: &str) -> Option<(String, Option<String>)> {
+    sqlx::query!(
+        "SELECT role, email FROM users WHERE username = $1",
+        username
+    )
+    .fetch_optional(&app.db_pool)
+    .await
+    .unwrap()
+    .map(|r| (r.role, r.email))
+}
+
+#[tokio::test]
+async fn an_invitation_creates_an_account_with_the_invited_role() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = invite(&app, "new.editor@example.com", "editor").await;
+    assert!(app
+        .get_users_html()
+        .await
+        .contains("new.editor@example.com"));
+    app.post_logout().await;
+
+    // Act - Part 1 - Open the link
+    let response = get_accept_invitation_form(&app, &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("You have been invited to join as editor."));
+
+    // Act - Part 2 - Create the account
+    let username = Uuid::new_v4().to_string();
+    let response = accept(&app, &token, &username).await;
+    assert_is_redirect_to(&response, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("<p><i>Your account has been created. You can now log in.</i></p>"));
+
+    // Assert
+    assert_eq!(
+        find_user(&app, &username).await,
+        Some(("editor".into(), Some("new.editor@example.com".into())))
+    );
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &username,
+            "password": PASSWORD,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn invitations_can_only_be_accepted_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = invite(&app, "new.viewer@example.com", "viewer").await;
+    accept(&app, &token, &Uuid::new_v4().to_string()).await;
+
+    // Act
+    let username = Uuid::new_v4().to_string();
+    let response = accept(&app, &token, &username).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("<p><i>This invitation is invalid or has expired.</i></p>"));
+    assert_eq!(find_user(&app, &username).await, None);
+}
+
+#[tokio::test]
+async fn a_taken_username_does_not_use_up_the_invitation() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = invite(&app, "new.viewer@example.com", "viewer").await;
+
+    // Act - Part 1 - Pick a username that is taken
+    let response = accept(&app, &token, &app.test_user.username).await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/login/accept-invitation?token={}", token),
+    );
+    let html_page = get_accept_invitation_form(&app, &token)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<p><i>This username is already taken.</i></p>"));
+
+    // Act - Part 2 - Pick another one
+    let username = Uuid::new_v4().to_string();
+    let response = accept(&app, &token, &username).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(find_user(&app, &username).await.is_some());
+}
+
+#[tokio::test]
+async fn revoked_and_expired_invitations_cannot_be_accepted() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let revoked = invite(&app, "revoked@example.com", "viewer").await;
+    let expired = invite(&app, "expired@example.com", "viewer").await;
+    let invitation_id =
+        sqlx::query!("SELECT invitation_id FROM invitations WHERE email = 'revoked@example.com'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .invitation_id;
+    app.api_client
+        .post(format!(
+            "{}/admin/users/invitations/{}/delete",
+            app.address, invitation_id
+        ))
+        .header("X-CSRF-Token", app.csrf_token().await)
+        .send()
+        .await
+        .unwrap();
+    sqlx::query!(
+        "UPDATE invitations SET expires_at = now() - interval '1 minute' \
+        WHERE email = 'expired@example.com'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    for token in [revoked, expired] {
+        // Act
+        let response = get_accept_invitation_form(&app, &token).await;
+        assert_is_redirect_to(&response, "/login");
+        let username = Uuid::new_v4().to_string();
+        let response = accept(&app, &token, &username).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/login");
+        assert_eq!(find_user(&app, &username).await, None);
+    }
+}
+
+#[tokio::test]
+async fn only_owners_can_invite_users() {
+    // Arrange
+    let app = spawn_app().await;
+    app.set_test_user_role("editor").await;
+    app.test_user.login(&app).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_invite_user("new.owner@example.com", "owner").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
@@ -0,0 +1,190 @@
+use crate::helpers::{assert_is_redirect_to, create_draft, draft_body, publish_issue, spawn_app};
+
+#[tokio::test]
+async fn public_issues_are_listed_in_the_archive() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "Rust 2022: a retrospective!", true).await;
+
+    // Act - Part 1 - List past issues
+    let html_page = app.get_issue_archive_html().await;
+    assert!(html_page.contains(
+        r#"<a href="/issues/rust-2022-a-retrospective">Rust 2022: a retrospective!</a>"#
+    ));
+
+    // Act - Part 2 - Read the issue
+    let response = app.get_public_issue("rust-2022-a-retrospective").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<p>Newsletter body as HTML</p>"));
+}
+
+#[tokio::test]
+async fn private_issues_and_drafts_are_not_in_the_archive() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "Members only", false).await;
+    let mut body = draft_body("Work in progress");
+    body["is_public"] = true.into();
+    app.post_create_draft(&body).await;
+
+    // Act
+    let html_page = app.get_issue_archive_html().await;
+    let private_response = app.get_public_issue("members-only").await;
+    let draft_response = app.get_public_issue("work-in-progress").await;
+
+    // Assert
+    assert!(!html_page.contains("Members only"));
+    assert!(!html_page.contains("Work in progress"));
+    assert_eq!(private_response.status().as_u16(), 404);
+    assert_eq!(draft_response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn issues_with_the_same_title_get_different_slugs() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    publish_issue(&app, "Weekly digest", true).await;
+    publish_issue(&app, "Weekly digest", true).await;
+
+    // Assert
+    assert_eq!(
+        app.get_public_issue("weekly-digest")
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+    assert_eq!(
+        app.get_public_issue("weekly-digest-2")
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+}
+
+#[tokio::test]
+async fn drafts_saved_at_the_same_time_with_the_same_title_get_different_slugs() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let body = draft_body("Breaking news");
+
+    // Act
+    let (response1, response2, response3) = tokio::join!(
+        app.post_create_draft(&body),
+        app.post_create_draft(&body),
+        app.post_create_draft(&body),
+    );
+
+    // Assert
+    for response in [response1, response2, response3] {
+        assert_eq!(response.status().as_u16(), 303);
+    }
+    let slugs = sqlx::query!(r#"SELECT slug AS "slug!" FROM newsletter_issues ORDER BY slug"#)
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    let slugs: Vec<String> = slugs.into_iter().map(|r| r.slug).collect();
+    assert_eq!(
+        slugs,
+        vec!["breaking-news", "breaking-news-2", "breaking-news-3"]
+    );
+}
+
+#[tokio::test]
+async fn public_issues_are_served_in_a_sandbox() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "Sandboxed", true).await;
+
+    // Act
+    let response = app.get_public_issue("sandboxed").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let csp = response.headers()["Content-Security-Policy"]
+        .to_str()
+        .unwrap();
+    assert!(csp.starts_with("sandbox "));
+    assert!(csp.contains("default-src 'none'"));
+}
+
+#[tokio::test]
+async fn published_issues_can_be_added_to_the_archive_later() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = publish_issue(&app, "Worth sharing", false).await;
+
+    // Act
+    let response = app.post_issue_visibility(&issue_id, true).await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/issues/{}/preview", issue_id),
+    );
+
+    // Assert
+    assert_eq!(
+        app.get_public_issue("worth-sharing")
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+
+    // Act - Remove it again
+    app.post_issue_visibility(&issue_id, false).await;
+    assert_eq!(
+        app.get_public_issue("worth-sharing")
+            .await
+            .status()
+            .as_u16(),
+        404
+    );
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_change_the_visibility_of_an_issue() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "Not yours").await;
+    app.post_logout().await;
+
+    // Act
+    let response = app.post_issue_visibility(&issue_id, true).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_home_page_links_to_the_archive() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let html_page = app
+        .api_client
+        .get(&app.address)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html_page.contains(r#"<form action="/subscriptions" method="post">"#));
+    assert!(html_page.contains(r#"<a href="/issues">Read past issues</a>"#));
+}
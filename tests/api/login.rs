@@ -1,4 +1,7 @@
 use crate::helpers::{assert_is_redirect_to, spawn_app};
+use secrecy::ExposeSecret;
+use zero2prod::authentication::LoginThrottle;
+use zero2prod::configuration::get_configuration;
 
 #[tokio::test]
 async fn an_error_flash_message_is_set_on_failure() {
@@ -7,7 +10,7 @@ async fn an_error_flash_message_is_set_on_failure() {
 
     // Act - Part 1 - Try to login
     let login_body = serde_json::json!({
-        "username": "random-username",
+        "username": uuid::Uuid::new_v4().to_string(),
         "password": "random-password",
     });
     let resp = app.post_login(&login_body).await;
@@ -42,3 +45,121 @@ async fn redirect_to_admin_dashboard_after_login_success() {
     let html_page = app.get_admin_dashborad_html().await;
     assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
 }
+
+#[tokio::test]
+async fn a_username_is_locked_out_after_repeated_failures() {
+    // Arrange
+    let app = spawn_app().await;
+    let wrong_login = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": "wrong-password",
+    });
+    for _ in 0..4 {
+        app.post_login(&wrong_login).await;
+        let html_page = app.get_login_html().await;
+        assert!(html_page.contains(r#"<p><i>Authentication failed</i></p>"#));
+    }
+
+    // Act - Part 1 - Fail once more
+    let resp = app.post_login(&wrong_login).await;
+    assert_is_redirect_to(&resp, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(
+        html_page.contains("<p><i>Too many failed login attempts. Try again in 1 minute.</i></p>")
+    );
+
+    // Act - Part 2 - The right password is refused as well
+    let resp = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&resp, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts."));
+}
+
+#[tokio::test]
+async fn a_successful_login_resets_the_failures_of_a_username() {
+    // Arrange
+    let app = spawn_app().await;
+    let wrong_login = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": "wrong-password",
+    });
+    for _ in 0..4 {
+        app.post_login(&wrong_login).await;
+    }
+    let resp = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&resp, "/admin/dashboard");
+
+    // Act
+    for _ in 0..4 {
+        app.post_login(&wrong_login).await;
+    }
+
+    // Assert
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains(r#"<p><i>Authentication failed</i></p>"#));
+}
+
+#[tokio::test]
+async fn an_address_is_locked_out_after_failures_on_many_usernames() {
+    // Arrange
+    let app = spawn_app().await;
+    for _ in 0..20 {
+        app.post_login(&serde_json::json!({
+            "username": uuid::Uuid::new_v4().to_string(),
+            "password": "wrong-password",
+        }))
+        .await;
+    }
+
+    // Act
+    let resp = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&resp, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts."));
+}
+
+#[tokio::test]
+async fn attempts_sent_at_once_cannot_get_past_the_limit() {
+    // Arrange
+    let conf = get_configuration().expect("Failed to read configuration.");
+    let throttle = LoginThrottle::new(conf.redis_uri.expose_secret())
+        .await
+        .unwrap();
+    let username = uuid::Uuid::new_v4().to_string();
+    let ip = uuid::Uuid::new_v4().to_string();
+
+    // Act
+    let attempts: Vec<_> = (0..10)
+        .map(|_| {
+            let throttle = throttle.clone();
+            let (username, ip) = (username.clone(), ip.clone());
+            tokio::spawn(async move { throttle.record_attempt(&username, &ip).await.unwrap() })
+        })
+        .collect();
+    let mut let_through = 0;
+    for attempt in attempts {
+        if attempt.await.unwrap().is_none() {
+            let_through += 1;
+        }
+    }
+
+    // Assert - four failures, then the attempt that reached the limit
+    assert_eq!(let_through, 5);
+}
@@ -1,10 +1,30 @@
 mod admin_dashboard;
+mod api_tokens;
 mod change_password;
+mod confirmation_email;
+mod csrf;
+mod email_templates;
+mod feeds;
 mod health_check;
 mod helpers;
+mod invitations;
+mod issue_archive;
 mod login;
 mod newsletter;
+mod newsletter_delivery_control;
+mod newsletter_delivery_status;
+mod newsletter_drafts;
+mod newsletter_scheduling;
+mod openapi;
+mod password_reset;
+mod preferences;
+mod rest_api;
+mod sessions;
+mod subscribers;
 mod subscriptions;
 mod subscriptions_confirm;
+mod two_factor;
+mod users;
+mod welcome_emails;
 
 pub mod docker;
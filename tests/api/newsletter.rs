@@ -1,53 +1,11 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app, ConfirmationLinks, TestApp};
-use fake::faker::internet::en::SafeEmail;
-use fake::faker::name::en::Name;
-use fake::Fake;
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, create_confirmed_subscriber_with_tags,
+    create_unconfirmed_subscriber, create_unconfirmed_subscriber_with_tags, spawn_app,
+};
 use std::time::Duration;
 use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, ResponseTemplate};
 
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    // We are working with multiple subscribers now,
-    // their details must be randomised to avoid conflicts!
-    let name: String = Name().fake();
-    let email: String = SafeEmail().fake();
-    let body = serde_urlencoded::to_string(&serde_json::json!({
-        "name": name,
-        "email": email
-    }))
-    .unwrap();
-
-    let _mock_guard = Mock::given(path("/email"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .named("Create unconfirmed subscriber")
-        .expect(1)
-        .mount_as_scoped(&app.email_server)
-        .await;
-    app.post_subscriptions(body.into())
-        .await
-        .error_for_status()
-        .unwrap();
-
-    let email_request = &app
-        .email_server
-        .received_requests()
-        .await
-        .unwrap()
-        .pop()
-        .unwrap();
-    app.get_confirmation_links(email_request)
-}
-
-async fn create_confirmed_subscriber(app: &TestApp) {
-    let confirmation_link = create_unconfirmed_subscriber(app).await.html;
-    reqwest::get(confirmation_link)
-        .await
-        .unwrap()
-        .error_for_status()
-        .unwrap();
-}
-
 #[tokio::test]
 async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
     // Arrange
@@ -228,3 +186,206 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     app.dispatch_all_pending_emails().await;
     // Mock verifies on Drop that we have sent the newsletter email **once**
 }
+
+#[tokio::test]
+async fn newsletters_are_only_delivered_to_the_selected_segment() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber_with_tags(&app, "rust").await;
+    create_confirmed_subscriber_with_tags(&app, "rust, unengaged").await;
+    create_confirmed_subscriber_with_tags(&app, "go").await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "include_tags": "rust",
+        "exclude_tags": "unengaged",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that only the subscriber in the segment got the issue
+}
+
+#[tokio::test]
+async fn the_publish_form_shows_how_many_subscribers_match_the_segment() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber_with_tags(&app, "rust").await;
+    create_confirmed_subscriber_with_tags(&app, "go").await;
+    create_unconfirmed_subscriber_with_tags(&app, "rust").await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Every confirmed subscriber by default
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("delivered to <b>2</b> confirmed subscriber(s)"));
+
+    // Act - Part 2 - Narrow it down to a tag, keeping the draft content
+    let response = app
+        .post_count_newsletter_recipients(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "include_tags": "rust",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("delivered to <b>1</b> confirmed subscriber(s)"));
+    assert!(html_page.contains("&lt;p&gt;Newsletter body as HTML&lt;/p&gt;"));
+}
+
+#[tokio::test]
+async fn publishing_with_an_invalid_segment_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "include_tags": "not a tag",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_copies_are_sent_to_reviewers_only() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_send_test_copy(&newsletter_request_body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<p><i>A test copy has been sent to editor@example.com.</i></p>"));
+    // The form keeps what the author typed
+    assert!(html_page.contains("Newsletter body as plain text</textarea>"));
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "editor@example.com");
+    assert_eq!(body["Subject"], "[TEST] Newsletter title");
+
+    let n_issues = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_issues, 0);
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that only the test copy has been sent
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_send_a_test_copy() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_send_test_copy(&newsletter_request_body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn newsletters_written_in_markdown_are_delivered_as_html_and_plain_text() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "markdown_content": "Read [the book](https://zero2prod.com) **now**.\n\n<script>alert(1)</script>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let html_body = body["HtmlBody"].as_str().unwrap();
+    assert!(html_body.contains(
+        "<p>Read <a href=\"https://zero2prod.com\">the book</a> <strong>now</strong>.</p>"
+    ));
+    assert!(html_body.contains("<p>&lt;script&gt;alert(1)&lt;/script&gt;</p>"));
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("Read the book (https://zero2prod.com) now."));
+
+    let markdown_content = sqlx::query!("SELECT markdown_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .markdown_content;
+    assert_eq!(
+        markdown_content.as_deref(),
+        Some("Read [the book](https://zero2prod.com) **now**.\n\n<script>alert(1)</script>")
+    );
+}
@@ -0,0 +1,126 @@
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, create_draft, issue_status, spawn_app,
+    TestApp,
+};
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Create two confirmed subscribers and start sending them an issue.
+async fn start_sending(app: &TestApp) -> String {
+    create_confirmed_subscriber(app).await;
+    create_confirmed_subscriber(app).await;
+    app.test_user.login(app).await;
+    let issue_id = create_draft(app, "Broken link inside").await;
+    app.post_send_draft(&issue_id).await;
+    issue_id
+}
+
+async fn queued_deliveries(app: &TestApp) -> i64 {
+    sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count
+}
+
+async fn mount_email_mock(email_server: &MockServer, n_emails: u64) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(n_emails)
+        .mount(email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn paused_issues_are_not_delivered_until_resumed() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = start_sending(&app).await;
+
+    // Act - Part 1 - Pause
+    let response = app.post_issue_action(&issue_id, "pause").await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/issues/{}/preview", issue_id),
+    );
+    {
+        let _mock_guard = Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount_as_scoped(&app.email_server)
+            .await;
+        app.dispatch_all_pending_emails().await;
+    }
+    assert_eq!(issue_status(&app, &issue_id).await, "paused");
+    assert_eq!(queued_deliveries(&app).await, 2);
+
+    // Act - Part 2 - Resume
+    mount_email_mock(&app.email_server, 2).await;
+    app.post_issue_action(&issue_id, "resume").await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(issue_status(&app, &issue_id).await, "sent");
+    // Mock verifies on Drop that both emails have been sent
+}
+
+#[tokio::test]
+async fn cancelling_an_issue_drops_its_remaining_deliveries() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = start_sending(&app).await;
+    mount_email_mock(&app.email_server, 0).await;
+
+    // Act
+    let response = app.post_issue_action(&issue_id, "cancel").await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/issues/{}/preview", issue_id),
+    );
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(issue_status(&app, &issue_id).await, "cancelled");
+    assert_eq!(queued_deliveries(&app).await, 0);
+    let html_page = app.get_preview_issue_html(&issue_id).await;
+    assert!(html_page
+        .contains("<p><i>The delivery has been cancelled. 2 email(s) will not be sent.</i></p>"));
+    assert!(html_page.contains("<p>2 email(s) were never sent.</p>"));
+    // Mock verifies on Drop that we haven't sent the newsletter email
+}
+
+#[tokio::test]
+async fn paused_issues_can_be_cancelled() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = start_sending(&app).await;
+    mount_email_mock(&app.email_server, 0).await;
+
+    // Act
+    app.post_issue_action(&issue_id, "pause").await;
+    app.post_issue_action(&issue_id, "cancel").await;
+    app.post_issue_action(&issue_id, "resume").await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(issue_status(&app, &issue_id).await, "cancelled");
+    let html_page = app.get_preview_issue_html(&issue_id).await;
+    assert!(html_page.contains("<p><i>Only paused issues can be resumed.</i></p>"));
+}
+
+#[tokio::test]
+async fn drafts_cannot_be_paused() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "Not sent yet").await;
+
+    // Act
+    app.post_issue_action(&issue_id, "pause").await;
+
+    // Assert
+    assert_eq!(issue_status(&app, &issue_id).await, "draft");
+    let html_page = app.get_preview_issue_html(&issue_id).await;
+    assert!(html_page.contains("<p><i>Only issues that are being sent can be paused.</i></p>"));
+}
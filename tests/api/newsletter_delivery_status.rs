@@ -0,0 +1,121 @@
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, create_draft, spawn_app, TestApp,
+};
+use wiremock::matchers::{any, body_partial_json, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn confirmed_emails(app: &TestApp) -> Vec<String> {
+    sqlx::query!("SELECT email FROM subscriptions ORDER BY email")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.email)
+        .collect()
+}
+
+/// A confirmed subscriber whose stored email can't be parsed anymore.
+async fn insert_subscriber_with_invalid_email(app: &TestApp) {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, 'not-an-email', 'Ghost', now(), 'confirmed')
+        "#,
+        uuid::Uuid::new_v4()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn the_delivery_status_page_reports_every_outcome() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let emails = confirmed_emails(&app).await;
+    insert_subscriber_with_invalid_email(&app).await;
+    app.test_user.login(&app).await;
+
+    // The first subscriber's mailbox is broken, the second one is fine.
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "To": emails[0] })))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let issue_id = create_draft(&app, "Status report").await;
+    app.post_send_draft(&issue_id).await;
+    app.dispatch_all_pending_emails().await;
+    let html_page = app.get_delivery_status_html(&issue_id).await;
+
+    // Assert
+    assert!(html_page.contains("<p>Status: sent."));
+    assert!(html_page.contains("<p>Progress: 100% of 3 email(s).</p>"));
+    assert!(html_page.contains("<li>Sent: 1</li>"));
+    assert!(html_page.contains("<li>Failed: 1</li>"));
+    assert!(html_page.contains("<li>Skipped (invalid email): 1</li>"));
+    assert!(html_page.contains(&format!("<tr><td>{}</td><td>failed</td>", emails[0])));
+    assert!(html_page.contains("500 Internal Server Error"));
+    assert!(html_page.contains(
+        "<tr><td>not-an-email</td><td>skipped_invalid_email</td>\
+        <td>not-an-email is not a valid subscriber email.</td></tr>"
+    ));
+}
+
+#[tokio::test]
+async fn the_delivery_status_page_shows_queued_deliveries() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let issue_id = create_draft(&app, "Status report").await;
+    app.post_send_draft(&issue_id).await;
+    app.post_issue_action(&issue_id, "pause").await;
+    let html_page = app.get_delivery_status_html(&issue_id).await;
+
+    // Assert
+    assert!(html_page.contains("<p>Status: paused."));
+    assert!(html_page.contains("<p>Progress: 0% of 2 email(s).</p>"));
+    assert!(html_page.contains("<li>Queued: 2</li>"));
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_delivery_status() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!(
+            "{}/admin/newsletters/issues/{}/status",
+            &app.address,
+            uuid::Uuid::new_v4()
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
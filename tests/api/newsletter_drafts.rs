@@ -0,0 +1,263 @@
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, create_draft, draft_body, issue_status,
+    spawn_app,
+};
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_drafts() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = uuid::Uuid::new_v4().to_string();
+
+    // Act
+    let list_response = app.get_issues().await;
+    let create_response = app.post_create_draft(&draft_body("Draft")).await;
+    let send_response = app.post_send_draft(&issue_id).await;
+
+    // Assert
+    assert_is_redirect_to(&list_response, "/login");
+    assert_is_redirect_to(&create_response, "/login");
+    assert_is_redirect_to(&send_response, "/login");
+}
+
+#[tokio::test]
+async fn drafts_are_not_delivered() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let issue_id = create_draft(&app, "My first draft").await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(issue_status(&app, &issue_id).await, "draft");
+    let html_page = app.get_issues_html().await;
+    assert!(html_page.contains("<td>My first draft</td><td>draft</td>"));
+    // Mock verifies on Drop that we haven't sent the newsletter email
+}
+
+#[tokio::test]
+async fn drafts_can_be_edited_and_previewed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "Typo in the titel").await;
+
+    // Act - Part 1 - Fix the title
+    let mut body = draft_body("No typo in the title");
+    body["include_tags"] = "rust".into();
+    let response = app.post_update_draft(&issue_id, &body).await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/issues/{}", issue_id),
+    );
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_edit_draft_html(&issue_id).await;
+    assert!(html_page.contains("<p><i>The draft has been saved.</i></p>"));
+    assert!(html_page.contains(r#"value="rust""#));
+
+    // Act - Part 3 - Preview
+    let html_page = app.get_preview_issue_html(&issue_id).await;
+
+    // Assert
+    assert!(html_page.contains("<h1>No typo in the title</h1>"));
+    let srcdoc = htmlescape::encode_attribute("<p>Newsletter body as HTML</p>");
+    assert!(html_page.contains(&format!(r#"srcdoc="{}""#, srcdoc)));
+    assert!(html_page
+        .contains("<pre style=\"white-space: pre-wrap;\">Newsletter body as plain text</pre>"));
+}
+
+#[tokio::test]
+async fn sending_a_draft_delivers_it_to_confirmed_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "Ready to go").await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Send the draft
+    let response = app.post_send_draft(&issue_id).await;
+    assert_is_redirect_to(&response, "/admin/newsletters/issues");
+    assert_eq!(issue_status(&app, &issue_id).await, "sending");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_issues_html().await;
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - \
+        emails will go out shortly.</i></p>"
+    ));
+
+    // Act - Part 3 - Deliver
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(issue_status(&app, &issue_id).await, "sent");
+    // Mock verifies on Drop that we have sent the newsletter email
+}
+
+#[tokio::test]
+async fn a_draft_is_only_sent_once() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "Ready to go").await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Send the draft twice
+    app.post_send_draft(&issue_id).await;
+    let response = app.post_send_draft(&issue_id).await;
+    assert_is_redirect_to(&response, "/admin/newsletters/issues");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let html_page = app.get_issues_html().await;
+    assert!(html_page.contains("<p><i>Only drafts and scheduled issues can be sent.</i></p>"));
+    // Mock verifies on Drop that we have sent the newsletter email once
+}
+
+#[tokio::test]
+async fn sent_issues_cannot_be_edited() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "Ready to go").await;
+    app.post_send_draft(&issue_id).await;
+
+    // Act
+    let response = app
+        .post_update_draft(&issue_id, &draft_body("Too late"))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters/issues");
+    let html_page = app.get_issues_html().await;
+    assert!(html_page.contains("<p><i>Only drafts can be edited.</i></p>"));
+    assert!(html_page.contains("<td>Ready to go</td><td>sent</td>"));
+}
+
+#[tokio::test]
+async fn the_markdown_source_of_a_draft_is_kept_for_editing() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "Markdown draft").await;
+
+    // Act
+    let mut body = draft_body("Markdown draft");
+    body["markdown_content"] = "# Hello\n\nSome *emphasis*".into();
+    body["html_content"] = "".into();
+    let response = app.post_update_draft(&issue_id, &body).await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/issues/{}", issue_id),
+    );
+
+    // Assert
+    let html_page = app.get_edit_draft_html(&issue_id).await;
+    assert!(html_page.contains("# Hello\n\nSome *emphasis*</textarea>"));
+    let html_page = app.get_preview_issue_html(&issue_id).await;
+    let srcdoc = htmlescape::encode_attribute("<h1>Hello</h1>\n<p>Some <em>emphasis</em></p>");
+    assert!(html_page.contains(&format!(r#"srcdoc="{}""#, srcdoc)));
+    assert!(html_page
+        .contains("<pre style=\"white-space: pre-wrap;\">Hello\n=====\n\nSome emphasis</pre>"));
+}
+
+#[tokio::test]
+async fn markdown_does_not_silently_replace_html_written_by_hand() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "Hand-written").await;
+
+    // Act - Part 1 - Add Markdown to an issue written in HTML
+    let mut body = draft_body("Hand-written");
+    body["markdown_content"] = "# Hello".into();
+    let response = app.post_update_draft(&issue_id, &body).await;
+
+    // Assert - The form is shown again, and the HTML is kept
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("The HTML content has been written by hand"));
+    assert!(html_page.contains("# Hello</textarea>"));
+    let html_page = app.get_preview_issue_html(&issue_id).await;
+    assert!(html_page.contains(&htmlescape::encode_attribute(
+        "<p>Newsletter body as HTML</p>"
+    )));
+
+    // Act - Part 2 - Clear the HTML
+    body["markdown_content"] = "# Hello\n\nSome *emphasis*".into();
+    body["html_content"] = "".into();
+    let response = app.post_update_draft(&issue_id, &body).await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/issues/{}", issue_id),
+    );
+
+    // Act - Part 3 - The generated HTML is sent back unchanged, with the
+    // line breaks of a browser
+    body["markdown_content"] = "# Hello again".into();
+    body["html_content"] = "<h1>Hello</h1>\r\n<p>Some <em>emphasis</em></p>".into();
+    let response = app.post_update_draft(&issue_id, &body).await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/issues/{}", issue_id),
+    );
+
+    // Act - Part 4 - The generated HTML has been edited
+    body["html_content"] = "<h1>Hello, edited</h1>".into();
+    let response = app.post_update_draft(&issue_id, &body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn a_blank_plain_text_version_is_generated_from_the_html() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let mut body = draft_body("HTML only");
+    body["text_content"] = "".into();
+    body["html_content"] =
+        r#"<h1>Hello</h1><p>Read <a href="https://zero2prod.com">the book</a>.</p><ul><li>One</li><li>Two</li></ul>"#
+            .into();
+    let response = app.post_create_draft(&body).await;
+    let issue_id = response.headers()["Location"]
+        .to_str()
+        .unwrap()
+        .rsplit('/')
+        .next()
+        .unwrap()
+        .to_owned();
+
+    // Assert
+    let html_page = app.get_preview_issue_html(&issue_id).await;
+    assert!(html_page.contains(
+        "<pre style=\"white-space: pre-wrap;\"># Hello\n\nRead [the book][1].\n* One\n* Two\n\n[1]: https://zero2prod.com</pre>"
+    ));
+}
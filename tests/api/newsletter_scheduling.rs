@@ -0,0 +1,157 @@
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, create_draft, issue_status, spawn_app,
+    TestApp,
+};
+use chrono::{Duration, Utc};
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn tomorrow() -> String {
+    (Utc::now() + Duration::days(1))
+        .format("%Y-%m-%dT%H:%M")
+        .to_string()
+}
+
+/// Pretend the scheduled time of an issue has come.
+async fn make_due(app: &TestApp, issue_id: &str) {
+    sqlx::query!(
+        "UPDATE newsletter_issues SET scheduled_for = now() - interval '1 minute' \
+        WHERE newsletter_issue_id = $1",
+        uuid::Uuid::parse_str(issue_id).unwrap()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_not_published_before_their_time() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "See you on Monday").await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_schedule_issue(&issue_id, &tomorrow()).await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/issues/{}/preview", issue_id),
+    );
+    app.publish_due_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(issue_status(&app, &issue_id).await, "scheduled");
+    let html_page = app.get_preview_issue_html(&issue_id).await;
+    assert!(html_page.contains("<p><i>The issue will be published on"));
+    // Mock verifies on Drop that we haven't sent the newsletter email
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_published_when_their_time_comes() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "See you on Monday").await;
+    app.post_schedule_issue(&issue_id, &tomorrow()).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    make_due(&app, &issue_id).await;
+    app.publish_due_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(issue_status(&app, &issue_id).await, "sent");
+    // Mock verifies on Drop that we have sent the newsletter email
+}
+
+#[tokio::test]
+async fn cancelled_schedules_are_not_published() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "See you on Monday").await;
+    app.post_schedule_issue(&issue_id, &tomorrow()).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_unschedule_issue(&issue_id).await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/issues/{}/preview", issue_id),
+    );
+    make_due(&app, &issue_id).await;
+    app.publish_due_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(issue_status(&app, &issue_id).await, "draft");
+    // Mock verifies on Drop that we haven't sent the newsletter email
+}
+
+#[tokio::test]
+async fn scheduled_issues_can_be_rescheduled() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "See you on Monday").await;
+    app.post_schedule_issue(&issue_id, &tomorrow()).await;
+
+    // Act
+    let next_week = (Utc::now() + Duration::days(7)).date_naive();
+    app.post_schedule_issue(&issue_id, &format!("{}T08:00", next_week))
+        .await;
+
+    // Assert
+    let scheduled_for = sqlx::query!(
+        "SELECT scheduled_for FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        uuid::Uuid::parse_str(&issue_id).unwrap()
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .scheduled_for
+    .unwrap();
+    assert_eq!(
+        scheduled_for,
+        next_week.and_hms_opt(8, 0, 0).unwrap().and_utc()
+    );
+    assert_eq!(issue_status(&app, &issue_id).await, "scheduled");
+}
+
+#[tokio::test]
+async fn issues_cannot_be_scheduled_in_the_past() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = create_draft(&app, "See you on Monday").await;
+
+    // Act
+    app.post_schedule_issue(&issue_id, "2022-10-17T08:00").await;
+
+    // Assert
+    let html_page = app.get_preview_issue_html(&issue_id).await;
+    assert!(html_page.contains("<p><i>Issues can only be scheduled in the future.</i></p>"));
+    assert_eq!(issue_status(&app, &issue_id).await, "draft");
+}
@@ -0,0 +1,160 @@
+use crate::helpers::spawn_app;
+use reqwest::Method;
+use uuid::Uuid;
+use zero2prod::routes::{api_routes, openapi_document};
+
+#[tokio::test]
+async fn the_openapi_document_is_served() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::get(format!("{}/api/openapi.json", &app.address))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let document: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(&document, openapi_document());
+    assert_eq!(document["openapi"], "3.1.0");
+    for path in [
+        "/subscriptions",
+        "/subscriptions/confirm",
+        "/newsletters",
+        "/admin/newsletters",
+        "/api/v1/newsletters",
+    ] {
+        assert!(document["paths"][path].is_object(), "{} is missing", path);
+    }
+    assert_eq!(
+        document["paths"]["/newsletters"]["post"]["deprecated"],
+        true
+    );
+    let subscribe_form = &document["paths"]["/subscriptions"]["post"]["requestBody"]["content"]
+        ["application/x-www-form-urlencoded"]["schema"];
+    assert_eq!(subscribe_form["$ref"], "#/components/schemas/SubscribeForm");
+    let subscribe_form = &document["components"]["schemas"]["SubscribeForm"];
+    assert_eq!(
+        subscribe_form["required"],
+        serde_json::json!(["email", "name"])
+    );
+    for field in ["email", "name", "tags"] {
+        assert!(subscribe_form["properties"][field].is_object());
+    }
+    let error_codes = &document["components"]["schemas"]["ErrorCode"]["enum"];
+    assert!(error_codes
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!("validation_error")));
+}
+
+#[test]
+fn every_security_requirement_names_a_security_scheme() {
+    let document = openapi_document();
+    let schemes = &document["components"]["securitySchemes"];
+
+    for (path, operations) in document["paths"].as_object().unwrap() {
+        for (method, operation) in operations.as_object().unwrap() {
+            let Some(requirements) = operation["security"].as_array() else {
+                continue;
+            };
+            for name in requirements
+                .iter()
+                .flat_map(|requirement| requirement.as_object().unwrap().keys())
+            {
+                assert!(
+                    schemes[name].is_object(),
+                    "{} {} needs the unknown {} scheme.",
+                    method,
+                    path,
+                    name
+                );
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn every_documented_operation_is_served_by_the_router() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = app
+        .create_api_token(&[
+            "newsletters:publish",
+            "subscribers:read",
+            "subscribers:write",
+            "issues:read",
+            "issues:write",
+        ])
+        .await;
+    let document = openapi_document();
+
+    for (path, operations) in document["paths"].as_object().unwrap() {
+        for (method, operation) in operations.as_object().unwrap() {
+            // Unknown ids, and bodies missing every field: only the router
+            // and the validation of the request are exercised.
+            let url = path
+                .split('/')
+                .map(|segment| match segment.starts_with('{') {
+                    true => Uuid::new_v4().to_string(),
+                    false => segment.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+            let mut request = app
+                .api_client
+                .request(method.clone(), format!("{}{}", &app.address, url))
+                .bearer_auth(&token);
+            let content = &operation["requestBody"]["content"];
+            if content["application/json"].is_object() {
+                request = request.json(&serde_json::json!({}));
+            } else if content["application/x-www-form-urlencoded"].is_object() {
+                request = request.form(&serde_json::json!({}));
+            }
+
+            // Act
+            let response = request.send().await.unwrap();
+
+            // Assert
+            let status = response.status().as_u16().to_string();
+            let documented = &operation["responses"][&status];
+            assert!(
+                documented.is_object(),
+                "{} {} answered with an undocumented {}.",
+                method,
+                path,
+                status
+            );
+            if documented["content"]["application/json"].is_object() {
+                let body: Result<serde_json::Value, _> = response.json().await;
+                assert!(
+                    body.is_ok(),
+                    "{} {} did not answer {} with JSON.",
+                    method,
+                    path,
+                    status
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn every_route_of_the_api_is_documented() {
+    let document = openapi_document();
+
+    // The router serves exactly these routes under `/api/v1`.
+    for (method, path, _) in api_routes() {
+        let path = format!("/api/v1{}", path);
+        let method = method.as_str().to_lowercase();
+        assert!(
+            document["paths"][&path][&method].is_object(),
+            "{} {} is not documented.",
+            method,
+            path
+        );
+    }
+}
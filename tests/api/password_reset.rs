@@ -0,0 +1,304 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use std::time::Duration;
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const NEW_PASSWORD: &str = "a brand new password";
+
+async fn set_test_user_email(app: &TestApp) {
+    sqlx::query!(
+        "UPDATE users SET email = 'admin@example.com' WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+/// Ask for a reset link and return the token it contains.
+/// The email is sent in the background, so we wait for it to arrive.
+async fn request_reset_token(app: &TestApp) -> String {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let response = app.post_forgot_password(&app.test_user.username).await;
+    assert_is_redirect_to(&response, "/login");
+    for _ in 0..50 {
+        if let Some(email_request) = app.email_server.received_requests().await.unwrap().pop() {
+            let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+            assert_eq!(body["To"], "admin@example.com");
+            let text = body["TextBody"].as_str().unwrap();
+            let start = text.find("?token=").unwrap() + 7;
+            let end = start + text[start..].find(' ').unwrap();
+            return text[start..end].to_string();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("The reset email was never sent.");
+}
+
+async fn get_reset_password_form(app: &TestApp, token: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!(
+            "{}/login/reset-password?token={}",
+            app.address, token
+        ))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn reset_password(app: &TestApp, token: &str) -> reqwest::Response {
+    app.post_reset_password(&serde_json::json!({
+        "token": token,
+        "new_password": NEW_PASSWORD,
+        "new_password_check": NEW_PASSWORD,
+    }))
+    .await
+}
+
+async fn login_with(app: &TestApp, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": &app.test_user.username,
+        "password": password
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn the_response_does_not_reveal_whether_a_user_exists() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let message =
+        "If this account has an email address, we have sent it a link to reset your password.";
+
+    // Act - the test user exists but has no email address yet
+    for username in [Uuid::new_v4().to_string(), app.test_user.username.clone()] {
+        let response = app.post_forgot_password(&username).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/login");
+        assert!(app.get_login_html().await.contains(message));
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+}
+
+#[tokio::test]
+async fn reset_requests_are_throttled() {
+    // Arrange
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+    for _ in 0..3 {
+        app.post_forgot_password(&app.test_user.username).await;
+    }
+
+    // Act
+    let response = app.post_forgot_password(&app.test_user.username).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("Too many password reset requests. Try again in an hour."));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+}
+
+#[tokio::test]
+async fn a_reset_link_sets_a_new_password() {
+    // Arrange
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+    let token = request_reset_token(&app).await;
+
+    // Act
+    let response = get_reset_password_form(&app, &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = reset_password(&app, &token).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("Your password has been reset. You can now log in."));
+    let response = login_with(&app, &app.test_user.password).await;
+    assert_is_redirect_to(&response, "/login");
+    let response = login_with(&app, NEW_PASSWORD).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn reset_links_can_only_be_used_once() {
+    // Arrange
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+    let token = request_reset_token(&app).await;
+    reset_password(&app, &token).await;
+
+    // Act
+    let response = reset_password(&app, &token).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("This password reset link is invalid or has expired."));
+    let response = get_reset_password_form(&app, &token).await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn expired_reset_links_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+    let token = request_reset_token(&app).await;
+    sqlx::query!("UPDATE password_reset_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = reset_password(&app, &token).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let response = login_with(&app, NEW_PASSWORD).await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn mismatched_passwords_keep_the_link_usable() {
+    // Arrange
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+    let token = request_reset_token(&app).await;
+
+    // Act
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token,
+            "new_password": NEW_PASSWORD,
+            "new_password_check": "another new password",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/login/reset-password?token={}", token));
+    let html_page = get_reset_password_form(&app, &token)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page
+        .contains("You entered two different new passwords - the field values must match."));
+    let response = reset_password(&app, &token).await;
+    assert_is_redirect_to(&response, "/login");
+    let response = login_with(&app, NEW_PASSWORD).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn admins_can_set_their_account_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Invalid address
+    let response = app
+        .post_account_email("not an email", &app.test_user.password)
+        .await;
+    assert_is_redirect_to(&response, "/admin/email");
+    assert!(app
+        .get_account_email_html()
+        .await
+        .contains("not an email is not a valid subscriber email."));
+
+    // Act - Part 2 - Valid address
+    let response = app
+        .post_account_email("admin@example.com", &app.test_user.password)
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/email");
+    let html_page = app.get_account_email_html().await;
+    assert!(html_page.contains("<p><i>Your email address has been saved.</i></p>"));
+    let saved = sqlx::query!(
+        "SELECT email FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.email.as_deref(), Some("admin@example.com"));
+}
+
+#[tokio::test]
+async fn changing_the_account_email_requires_the_current_password() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_account_email("attacker@example.com", "wrong-password")
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/email");
+    assert!(app
+        .get_account_email_html()
+        .await
+        .contains("<p><i>The current password is incorrect.</i></p>"));
+    let saved = sqlx::query!(
+        "SELECT email FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.email, None);
+}
+
+#[tokio::test]
+async fn resetting_the_password_logs_out_every_session() {
+    // Arrange
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+    app.test_user.login(&app).await;
+    let response = app.get_admin_dashborad().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = request_reset_token(&app).await;
+
+    // Act
+    let response = reset_password(&app, &token).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_admin_dashborad().await;
+    assert_is_redirect_to(&response, "/login");
+    let sessions = sqlx::query!(
+        "SELECT count(*) AS \"n!\" FROM user_sessions WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(sessions.n, 0);
+}
@@ -0,0 +1,304 @@
+use crate::helpers::{assert_is_redirect_to, create_confirmed_subscriber, spawn_app, TestApp};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn subscriber_email(app: &TestApp) -> String {
+    sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .email
+}
+
+async fn publish_newsletter(app: &TestApp) {
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn newsletter_emails_link_to_the_preference_center() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let link = app
+        .get_preferences_link(&subscriber_email(&app).await)
+        .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    publish_newsletter(&app).await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let query = link.query().unwrap();
+    // `&` is escaped in HTML attributes
+    let html_query = htmlescape::encode_minimal(query);
+    assert!(body["HtmlBody"].as_str().unwrap().contains(&html_query));
+    assert!(body["TextBody"].as_str().unwrap().contains(query));
+}
+
+#[tokio::test]
+async fn the_preference_center_rejects_links_with_an_invalid_tag() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let mut link = app
+        .get_preferences_link(&subscriber_email(&app).await)
+        .await;
+    let subscriber_id = link
+        .query_pairs()
+        .find(|(k, _)| k == "subscriber_id")
+        .unwrap()
+        .1
+        .into_owned();
+    link.query_pairs_mut()
+        .clear()
+        .append_pair("subscriber_id", &subscriber_id)
+        .append_pair("tag", "deadbeef");
+
+    // Act
+    let get_response = app.get_preferences(&link).await;
+    let post_response = app
+        .post_preferences(&link, &[("name", "Ursula"), ("email_format", "html")])
+        .await;
+    let unsubscribe_response = app.post_unsubscribe(&link).await;
+
+    // Assert
+    assert_eq!(get_response.status().as_u16(), 401);
+    assert_eq!(post_response.status().as_u16(), 401);
+    assert_eq!(unsubscribe_response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn subscribers_can_update_their_name_topics_and_email_format() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+    let link = app.get_preferences_link(&email).await;
+
+    // Act - Part 1 - Save the preferences
+    let response = app
+        .post_preferences(
+            &link,
+            &[
+                ("name", "Ursula Le Guin"),
+                ("topics", "announcements"),
+                ("topics", "tutorials"),
+                ("email_format", "plain_text"),
+            ],
+        )
+        .await;
+    assert_is_redirect_to(
+        &response,
+        &format!("{}?{}", link.path(), link.query().unwrap()),
+    );
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_preferences_html(&link).await;
+    assert!(html_page.contains("<p><i>Your preferences have been saved.</i></p>"));
+
+    // Assert
+    let saved = sqlx::query!("SELECT name, email_format FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "Ursula Le Guin");
+    assert_eq!(saved.email_format, "plain_text");
+    let tags: Vec<String> = sqlx::query!("SELECT tag FROM subscriber_tags ORDER BY tag")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.tag)
+        .collect();
+    assert_eq!(tags, vec!["announcements", "tutorials"]);
+
+    // Act - Part 3 - Opt out of a topic
+    app.post_preferences(
+        &link,
+        &[
+            ("name", "Ursula Le Guin"),
+            ("topics", "tutorials"),
+            ("email_format", "plain_text"),
+        ],
+    )
+    .await;
+    let tags: Vec<String> = sqlx::query!("SELECT tag FROM subscriber_tags")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.tag)
+        .collect();
+    assert_eq!(tags, vec!["tutorials"]);
+}
+
+#[tokio::test]
+async fn subscribers_cannot_opt_into_tags_that_are_not_topics() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let link = app
+        .get_preferences_link(&subscriber_email(&app).await)
+        .await;
+
+    // Act
+    app.post_preferences(
+        &link,
+        &[
+            ("name", "Ursula Le Guin"),
+            ("topics", "beta-readers"),
+            ("email_format", "html"),
+        ],
+    )
+    .await;
+
+    // Assert
+    let html_page = app.get_preferences_html(&link).await;
+    assert!(html_page.contains("beta-readers is not a topic you can subscribe to."));
+    let saved = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM subscriber_tags")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.count, 0);
+}
+
+#[tokio::test]
+async fn plain_text_subscribers_do_not_receive_an_html_body() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let link = app
+        .get_preferences_link(&subscriber_email(&app).await)
+        .await;
+    app.post_preferences(&link, &[("name", "Ursula"), ("email_format", "plain_text")])
+        .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    publish_newsletter(&app).await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert!(body.get("HtmlBody").is_none());
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("Newsletter body as plain text"));
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_do_not_receive_newsletters() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let link = app
+        .get_preferences_link(&subscriber_email(&app).await)
+        .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_unsubscribe(&link).await;
+    assert_eq!(response.status().as_u16(), 200);
+    publish_newsletter(&app).await;
+
+    // Assert
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "unsubscribed");
+    // Mock verifies on Drop that we haven't sent the newsletter email
+}
+
+#[tokio::test]
+async fn deliveries_use_the_preferences_of_the_subscriber_at_send_time() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let link = app
+        .get_preferences_link(&subscriber_email(&app).await)
+        .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Publish, then change preferences before the worker gets to it
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.post_preferences(
+        &link,
+        &[("name", "Octavia"), ("email_format", "plain_text")],
+    )
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert!(body.get("HtmlBody").is_none());
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .starts_with("Hi Octavia,\n\nNewsletter body as plain text"));
+}
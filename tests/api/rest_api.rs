@@ -0,0 +1,469 @@
+use crate::helpers::{create_confirmed_subscriber, draft_body, spawn_app, TestApp};
+use reqwest::Method;
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn subscribers_can_be_created_read_updated_and_deleted() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = app
+        .create_api_token(&["subscribers:read", "subscribers:write"])
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Create
+    let response = app
+        .api_request(Method::POST, &token, "/subscribers")
+        .json(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+            "tags": ["fiction"]
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    let location = response.headers()["Location"].to_str().unwrap().to_owned();
+    let created: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(created["status"], "pending_confirmation");
+    assert_eq!(created["tags"], serde_json::json!(["fiction"]));
+    let id = created["id"].as_str().unwrap();
+    assert_eq!(location, format!("/api/v1/subscribers/{}", id));
+
+    // Act - Part 2 - Update
+    let response = app
+        .api_request(Method::PATCH, &token, &format!("/subscribers/{}", id))
+        .json(&serde_json::json!({
+            "name": "Ursula K. Le Guin",
+            "email_format": "plain_text",
+            "tags": ["fantasy", "sci-fi"]
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Act - Part 3 - Read
+    let response = app
+        .api_request(Method::GET, &token, &format!("/subscribers/{}", id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let subscriber: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(subscriber["name"], "Ursula K. Le Guin");
+    assert_eq!(subscriber["email"], "ursula_le_guin@gmail.com");
+    assert_eq!(subscriber["email_format"], "plain_text");
+    assert_eq!(subscriber["tags"], serde_json::json!(["fantasy", "sci-fi"]));
+
+    // Act - Part 4 - Delete
+    let response = app
+        .api_request(Method::DELETE, &token, &format!("/subscribers/{}", id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Assert
+    let response = app
+        .api_request(Method::GET, &token, &format!("/subscribers/{}", id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "not_found");
+    assert_eq!(body["error"]["message"], "Unknown subscriber.");
+}
+
+/// How many issue deliveries and welcome emails are queued for the subscriber.
+async fn queued_emails(app: &TestApp, subscriber_id: Uuid) -> (i64, i64) {
+    let queued = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT count(*) FROM issue_delivery_queue WHERE subscriber_id = $1) AS "issues!",
+            (SELECT count(*) FROM welcome_email_queue WHERE subscriber_id = $1) AS "welcome_emails!"
+        "#,
+        subscriber_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    (queued.issues, queued.welcome_emails)
+}
+
+#[tokio::test]
+async fn subscribers_with_queued_emails_can_be_deleted() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let response = app
+        .post_create_welcome_email(&serde_json::json!({
+            "delay_days": "3",
+            "subject": "Welcome aboard",
+            "html_content": "<p>Thanks for joining.</p>",
+            "text_content": "",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+    create_confirmed_subscriber(&app).await;
+    app.post_publish_newsletter(&draft_body("Newsletter title"))
+        .await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    assert_eq!(queued_emails(&app, subscriber_id).await, (1, 1));
+    let token = app.create_api_token(&["subscribers:write"]).await;
+
+    // Act
+    let response = app
+        .api_request(
+            Method::DELETE,
+            &token,
+            &format!("/subscribers/{}", subscriber_id),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(queued_emails(&app, subscriber_id).await, (0, 0));
+}
+
+#[tokio::test]
+async fn a_subscriber_created_twice_at_once_is_a_conflict() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = app.create_api_token(&["subscribers:write"]).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let create = || {
+        app.api_request(Method::POST, &token, "/subscribers")
+            .json(&serde_json::json!({
+                "name": "le guin",
+                "email": "ursula_le_guin@gmail.com"
+            }))
+            .send()
+    };
+
+    // Act
+    let (response1, response2) = tokio::join!(create(), create());
+
+    // Assert
+    let mut statuses = [
+        response1.unwrap().status().as_u16(),
+        response2.unwrap().status().as_u16(),
+    ];
+    statuses.sort();
+    assert_eq!(statuses, [201, 409]);
+}
+
+#[tokio::test]
+async fn invalid_and_duplicate_subscribers_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = app.create_api_token(&["subscribers:write"]).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let subscriber = serde_json::json!({
+        "name": "le guin",
+        "email": "ursula_le_guin@gmail.com"
+    });
+    app.api_request(Method::POST, &token, "/subscribers")
+        .json(&subscriber)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let test_cases = vec![
+        (subscriber, 409, "conflict", "an existing email"),
+        (
+            serde_json::json!({"name": "le guin", "email": "definitely-not-an-email"}),
+            400,
+            "validation_error",
+            "an invalid email",
+        ),
+        (
+            serde_json::json!({"name": "le guin"}),
+            400,
+            "validation_error",
+            "a missing email",
+        ),
+    ];
+
+    for (body, status, code, description) in test_cases {
+        // Act
+        let response = app
+            .api_request(Method::POST, &token, "/subscribers")
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            status,
+            "The API did not fail with {} when the payload had {}.",
+            status,
+            description
+        );
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["code"], code);
+    }
+}
+
+#[tokio::test]
+async fn subscribers_are_listed_one_page_at_a_time() {
+    // Arrange
+    let app = spawn_app().await;
+    for _ in 0..3 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+    let token = app.create_api_token(&["subscribers:read"]).await;
+
+    // Act - Part 1 - The second page
+    let response = app
+        .api_request(Method::GET, &token, "/subscribers?page=2&per_page=2")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["items"].as_array().unwrap().len(), 1);
+    assert_eq!(body["items"][0]["status"], "confirmed");
+    assert_eq!(body["page"], 2);
+    assert_eq!(body["per_page"], 2);
+    assert_eq!(body["total"], 3);
+
+    // Act - Part 2 - Invalid pagination
+    for query in [
+        "page=0",
+        "per_page=0",
+        "per_page=101",
+        "page=first",
+        "page=9223372036854775807",
+    ] {
+        let response = app
+            .api_request(Method::GET, &token, &format!("/subscribers?{}", query))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 400, "Accepted {}", query);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "validation_error");
+    }
+}
+
+#[tokio::test]
+async fn issues_can_be_created_listed_and_followed() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let token = app
+        .create_api_token(&["issues:read", "issues:write", "newsletters:publish"])
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Create a draft
+    let response = app
+        .api_request(Method::POST, &token, "/issues")
+        .json(&serde_json::json!({
+            "title": "Draft title",
+            "text_content": "Draft body as plain text",
+            "html_content": "<p>Draft body as HTML</p>",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    let draft: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(draft["status"], "draft");
+    assert_eq!(draft["text_content"], "Draft body as plain text");
+
+    // Act - Part 2 - Publish another issue
+    let response = app
+        .post_api_publish_newsletter(
+            &token,
+            None,
+            &serde_json::json!({
+                "title": "Newsletter title",
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Newsletter body as HTML</p>",
+            }),
+        )
+        .await;
+    let body: serde_json::Value = response.json().await.unwrap();
+    let issue_id = body["issue_id"].as_str().unwrap().to_owned();
+    app.dispatch_all_pending_emails().await;
+
+    // Act - Part 3 - List
+    let response = app
+        .api_request(Method::GET, &token, "/issues")
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["total"], 2);
+    let titles: Vec<_> = body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|issue| issue["title"].as_str().unwrap())
+        .collect();
+    assert!(titles.contains(&"Draft title"));
+    assert!(titles.contains(&"Newsletter title"));
+
+    // Act - Part 4 - Delivery stats
+    let response = app
+        .api_request(
+            Method::GET,
+            &token,
+            &format!("/issues/{}/delivery", issue_id),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let delivery: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(delivery["status"], "sent");
+    assert_eq!(delivery["sent"], 1);
+    assert_eq!(delivery["queued"], 0);
+    assert_eq!(delivery["total"], 1);
+    assert_eq!(delivery["percentage"], 100);
+}
+
+#[tokio::test]
+async fn issues_need_a_title() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = app.create_api_token(&["issues:write"]).await;
+
+    // Act
+    let response = app
+        .api_request(Method::POST, &token, "/issues")
+        .json(&serde_json::json!({
+            "title": "",
+            "text_content": "Draft body as plain text",
+            "html_content": "<p>Draft body as HTML</p>",
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "validation_error");
+    assert_eq!(body["error"]["message"], "The issue needs a title.");
+}
+
+#[tokio::test]
+async fn issues_with_an_unknown_template_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = app.create_api_token(&["issues:write"]).await;
+
+    // Act
+    let response = app
+        .api_request(Method::POST, &token, "/issues")
+        .json(&serde_json::json!({
+            "title": "Templated",
+            "text_content": "Draft body as plain text",
+            "html_content": "<p>Draft body as HTML</p>",
+            "template_id": uuid::Uuid::new_v4().to_string(),
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "validation_error");
+}
+
+#[tokio::test]
+async fn tokens_are_limited_to_their_scopes_and_to_the_role_of_their_user() {
+    // Arrange
+    let app = spawn_app().await;
+    app.set_test_user_role("viewer").await;
+    app.test_user.login(&app).await;
+    let token = app
+        .create_api_token(&["subscribers:read", "subscribers:write"])
+        .await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Viewers can read
+    let response = app
+        .api_request(Method::GET, &token, "/subscribers")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Act - Part 2 - But not write
+    let response = app
+        .api_request(Method::POST, &token, "/subscribers")
+        .json(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "forbidden");
+
+    // Act - Part 3 - Nor use scopes the token was not given
+    let response = app
+        .api_request(Method::GET, &token, "/issues")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body["error"]["message"],
+        "The token does not have the `issues:read` scope."
+    );
+}
@@ -0,0 +1,172 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use uuid::Uuid;
+
+const FIREFOX: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0";
+
+/// Log the test user in from another browser.
+async fn login_from(app: &TestApp, browser: &reqwest::Client) {
+    let response = browser
+        .post(format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+async fn get_dashboard_from(app: &TestApp, browser: &reqwest::Client) -> reqwest::Response {
+    browser
+        .get(format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn firefox_session_id(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT session_id FROM user_sessions WHERE device = 'Firefox on Linux'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .session_id
+}
+
+#[tokio::test]
+async fn every_login_is_listed_with_its_device_and_address() {
+    // Arrange
+    let app = spawn_app().await;
+    let firefox = app.other_browser(FIREFOX);
+    login_from(&app, &firefox).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let html_page = app.get_sessions_html().await;
+
+    // Assert
+    assert!(html_page.contains("This browser"));
+    assert!(html_page.contains("Firefox on Linux"));
+    assert!(html_page.contains("192.0.2.1"));
+    assert!(html_page.contains(&format!(
+        r#"action="/admin/sessions/{}/delete""#,
+        firefox_session_id(&app).await
+    )));
+}
+
+#[tokio::test]
+async fn revoked_sessions_are_logged_out() {
+    // Arrange
+    let app = spawn_app().await;
+    let firefox = app.other_browser(FIREFOX);
+    login_from(&app, &firefox).await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Revoke
+    let response = app
+        .post_revoke_session(firefox_session_id(&app).await)
+        .await;
+    assert_is_redirect_to(&response, "/admin/sessions");
+    let html_page = app.get_sessions_html().await;
+    assert!(html_page.contains("<p><i>The session has been revoked.</i></p>"));
+    assert!(!html_page.contains("Firefox on Linux"));
+
+    // Act - Part 2 - The revoked browser
+    let response = get_dashboard_from(&app, &firefox).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html_page = firefox
+        .get(format!("{}/login", &app.address))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<p><i>Your session has ended. Please log in again.</i></p>"));
+    let response = app.get_admin_dashborad().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn logging_out_everywhere_ends_every_session() {
+    // Arrange
+    let app = spawn_app().await;
+    let firefox = app.other_browser(FIREFOX);
+    login_from(&app, &firefox).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_log_out_everywhere().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("<p><i>You have been logged out everywhere.</i></p>"));
+    assert_is_redirect_to(&app.get_admin_dashborad().await, "/login");
+    assert_is_redirect_to(&get_dashboard_from(&app, &firefox).await, "/login");
+    let sessions = sqlx::query!("SELECT session_id FROM user_sessions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(sessions.is_empty());
+}
+
+#[tokio::test]
+async fn changing_the_password_logs_out_the_other_sessions() {
+    // Arrange
+    let app = spawn_app().await;
+    let firefox = app.other_browser(FIREFOX);
+    login_from(&app, &firefox).await;
+    app.test_user.login(&app).await;
+    let new_password = Uuid::new_v4().to_string();
+
+    // Act
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    // Assert
+    assert_is_redirect_to(&get_dashboard_from(&app, &firefox).await, "/login");
+    let response = app.get_admin_dashborad().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn expired_sessions_are_forgotten() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let firefox = app.other_browser(FIREFOX);
+    login_from(&app, &firefox).await;
+    // Its state has expired from Redis by now.
+    sqlx::query!(
+        "UPDATE user_sessions SET last_seen_at = now() - interval '2 days' WHERE session_id = $1",
+        firefox_session_id(&app).await
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act - Part 1 - It is no longer listed
+    let html_page = app.get_sessions_html().await;
+    assert!(!html_page.contains("Firefox on Linux"));
+
+    // Act - Part 2 - The next login prunes it
+    app.test_user.login(&app).await;
+
+    // Assert
+    let remaining = sqlx::query!(
+        "SELECT count(*) AS \"n!\" FROM user_sessions WHERE device = 'Firefox on Linux'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(remaining.n, 0);
+}
@@ -0,0 +1,174 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_subscribers().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn tags_submitted_with_the_subscribe_form_are_stored() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let body = "name=benjamin&email=benjamin%40gmail.com&tags=Rust%2C%20landing-page";
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let tags = sqlx::query!("SELECT tag FROM subscriber_tags ORDER BY tag")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    let tags: Vec<String> = tags.into_iter().map(|r| r.tag).collect();
+    assert_eq!(tags, vec!["landing-page", "rust"]);
+}
+
+#[tokio::test]
+async fn invalid_tags_on_the_subscribe_form_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let body = "name=benjamin&email=benjamin%40gmail.com&tags=not%20a%20tag";
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_tagged_and_untagged_by_an_admin() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=benjamin&email=benjamin%40gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Add tags
+    let response = app
+        .post_subscriber_tags(&serde_json::json!({
+            "email": "benjamin@gmail.com",
+            "tags": "vip, beta-readers",
+            "action": "add"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let html_page = app.get_subscribers_html().await;
+    assert!(html_page.contains("<p><i>The tags have been added.</i></p>"));
+    assert!(html_page.contains("<td>beta-readers, vip</td>"));
+
+    // Act - Part 2 - Remove one of them
+    let response = app
+        .post_subscriber_tags(&serde_json::json!({
+            "email": "benjamin@gmail.com",
+            "tags": "vip",
+            "action": "remove"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let html_page = app.get_subscribers_html().await;
+    assert!(html_page.contains("<td>beta-readers</td>"));
+}
+
+#[tokio::test]
+async fn tagging_an_unknown_subscriber_shows_an_error() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_subscriber_tags(&serde_json::json!({
+            "email": "nobody@gmail.com",
+            "tags": "vip",
+            "action": "add"
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let html_page = app.get_subscribers_html().await;
+    assert!(html_page.contains("There is no subscriber with email nobody@gmail.com."));
+}
+
+#[tokio::test]
+async fn imported_subscribers_are_tagged_and_asked_to_confirm() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        // Only the new subscriber gets a confirmation email
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=benjamin&email=benjamin%40gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "subscribers": "ursula@gmail.com, Ursula\nbenjamin@gmail.com, Benjamin\n\nnot-an-email, Nobody",
+            "tags": "conference"
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let html_page = app.get_subscribers_html().await;
+    assert!(html_page
+        .contains("<p><i>1 subscribers have been imported and 1 existing ones tagged.</i></p>"));
+    assert!(html_page.contains("Line 4: not-an-email is not a valid subscriber email."));
+    let rows = sqlx::query!(
+        r#"
+        SELECT s.email, s.status
+        FROM subscriptions s
+        JOIN subscriber_tags t ON t.subscriber_id = s.id
+        WHERE t.tag = 'conference'
+        ORDER BY s.email
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    let rows: Vec<(String, String)> = rows.into_iter().map(|r| (r.email, r.status)).collect();
+    assert_eq!(
+        rows,
+        vec![
+            (
+                "benjamin@gmail.com".to_string(),
+                "pending_confirmation".to_string()
+            ),
+            (
+                "ursula@gmail.com".to_string(),
+                "pending_confirmation".to_string()
+            ),
+        ]
+    );
+}
@@ -35,7 +35,7 @@ async fn the_link_returned_by_subscribe_returns_a_200_if_called() {
     // Assert
     assert_eq!(200, response.status().as_u16());
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
-    let confirmation_links = app.get_confirmation_links(&email_request);
+    let confirmation_links = app.get_confirmation_links(email_request);
     let resp = reqwest::get(confirmation_links.html).await.unwrap();
 
     assert_eq!(resp.status().as_u16(), 200);
@@ -56,7 +56,7 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     let response = app.post_subscriptions(body.into()).await;
     assert_eq!(200, response.status().as_u16());
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
-    let confirmation_links = app.get_confirmation_links(&email_request);
+    let confirmation_links = app.get_confirmation_links(email_request);
 
     // Act
     reqwest::get(confirmation_links.html)
@@ -0,0 +1,255 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use secrecy::{ExposeSecret, Secret};
+use zero2prod::authentication::totp_code;
+
+struct Enrolment {
+    secret: Secret<String>,
+    recovery_codes: Vec<String>,
+}
+
+/// The code an authenticator app would show `offset` seconds from now.
+fn code_at(secret: &Secret<String>, offset: i64) -> String {
+    let now = chrono::Utc::now().timestamp() + offset;
+    totp_code(secret, now as u64).unwrap()
+}
+
+fn extract_secret(html_page: &str) -> Secret<String> {
+    let start = html_page.find(r#"<code id="totp-secret">"#).unwrap() + 23;
+    let end = start + html_page[start..].find("</code>").unwrap();
+    Secret::new(html_page[start..end].to_string())
+}
+
+/// Log in and enable two-factor authentication, then log out.
+async fn enrol(app: &TestApp) -> Enrolment {
+    app.test_user.login(app).await;
+    let secret = extract_secret(&app.get_two_factor_html().await);
+    let response = app.post_enrol_two_factor(&code_at(&secret, 0)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    let recovery_codes: Vec<String> = html_page
+        .split("<li><code>")
+        .skip(1)
+        .map(|s| s.split("</code>").next().unwrap().to_string())
+        .collect();
+    assert_eq!(recovery_codes.len(), 10);
+    app.post_logout().await;
+    Enrolment {
+        secret,
+        recovery_codes,
+    }
+}
+
+async fn log_in_with_password(app: &TestApp) {
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn enrolment_requires_a_valid_code() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let secret = extract_secret(&app.get_two_factor_html().await);
+
+    // Act
+    let response = app.post_enrol_two_factor("000000x").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    let html_page = app.get_two_factor_html().await;
+    assert!(html_page.contains("<p><i>The code is invalid, please try again.</i></p>"));
+    // The same secret is kept until it is confirmed.
+    assert_eq!(
+        extract_secret(&html_page).expose_secret(),
+        secret.expose_secret()
+    );
+}
+
+#[tokio::test]
+async fn the_secret_is_not_stored_in_plain_text() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let enrolment = enrol(&app).await;
+
+    // Assert
+    let stored = sqlx::query!(
+        "SELECT totp_secret AS \"totp_secret!\" FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .totp_secret;
+    assert!(!stored.contains(enrolment.secret.expose_secret()));
+}
+
+#[tokio::test]
+async fn the_password_is_not_enough_once_two_factor_is_enabled() {
+    // Arrange
+    let app = spawn_app().await;
+    let enrolment = enrol(&app).await;
+
+    // Act - Part 1 - Password only
+    log_in_with_password(&app).await;
+    let response = app.get_admin_dashborad().await;
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 2 - Wrong code
+    let response = app.post_login_two_factor("123456").await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+
+    // Act - Part 3 - The next code of the app
+    let response = app
+        .post_login_two_factor(&code_at(&enrolment.secret, 30))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // Assert
+    let response = app.get_admin_dashborad().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn codes_cannot_be_used_twice() {
+    // Arrange
+    let app = spawn_app().await;
+    let enrolment = enrol(&app).await;
+    let code = code_at(&enrolment.secret, 30);
+    log_in_with_password(&app).await;
+    let response = app.post_login_two_factor(&code).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    app.post_logout().await;
+
+    // Act
+    log_in_with_password(&app).await;
+    let response = app.post_login_two_factor(&code).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn recovery_codes_can_be_used_once() {
+    // Arrange
+    let app = spawn_app().await;
+    let enrolment = enrol(&app).await;
+    let recovery_code = enrolment.recovery_codes[3].to_uppercase();
+
+    // Act - Part 1 - Use the code
+    log_in_with_password(&app).await;
+    let response = app.post_login_two_factor(&recovery_code).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_two_factor_html().await;
+    assert!(html_page.contains("You have 9 unused recovery code(s)."));
+    app.post_logout().await;
+
+    // Act - Part 2 - Use it again
+    log_in_with_password(&app).await;
+    let response = app.post_login_two_factor(&recovery_code).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn the_password_must_be_entered_again_after_too_many_invalid_codes() {
+    // Arrange
+    let app = spawn_app().await;
+    let enrolment = enrol(&app).await;
+    log_in_with_password(&app).await;
+
+    // Act - Part 1 - Guess
+    for _ in 0..4 {
+        let response = app.post_login_two_factor("000000").await;
+        assert_is_redirect_to(&response, "/login/two-factor");
+    }
+    let response = app.post_login_two_factor("000000").await;
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many invalid authentication codes. Please log in again."));
+
+    // Act - Part 2 - A valid code is no longer enough
+    let response = app
+        .post_login_two_factor(&code_at(&enrolment.secret, 30))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_admin_dashborad().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_second_step_requires_a_valid_password_first() {
+    // Arrange
+    let app = spawn_app().await;
+    let enrolment = enrol(&app).await;
+
+    // Act
+    let response = app
+        .post_login_two_factor(&code_at(&enrolment.secret, 30))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_admin_dashborad().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn two_factor_can_be_disabled() {
+    // Arrange
+    let app = spawn_app().await;
+    let enrolment = enrol(&app).await;
+    log_in_with_password(&app).await;
+    app.post_login_two_factor(&enrolment.recovery_codes[0])
+        .await;
+
+    // Act
+    let response = app
+        .post_disable_two_factor(&enrolment.recovery_codes[1])
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    let html_page = app.get_two_factor_html().await;
+    assert!(html_page.contains("<p><i>Two-factor authentication has been disabled.</i></p>"));
+    app.post_logout().await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn the_password_alone_does_not_reset_the_login_failures() {
+    // Arrange
+    let app = spawn_app().await;
+    enrol(&app).await;
+    let wrong_login = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": "wrong-password",
+    });
+    for _ in 0..3 {
+        app.post_login(&wrong_login).await;
+    }
+    log_in_with_password(&app).await;
+
+    // Act - the second factor is never entered
+    let response = app.post_login(&wrong_login).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts."));
+}
@@ -0,0 +1,222 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use uuid::Uuid;
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+
+async fn role_of(app: &TestApp, username: &str) -> Option<String> {
+    sqlx::query!("SELECT role FROM users WHERE username = $1", username)
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap()
+        .map(|r| r.role)
+}
+
+async fn add_user(app: &TestApp, username: &str, role: &str) -> Uuid {
+    app.post_add_user(&serde_json::json!({
+        "username": username,
+        "password": "a-long-enough-password",
+        "role": role,
+    }))
+    .await;
+    sqlx::query!("SELECT user_id FROM users WHERE username = $1", username)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .user_id
+}
+
+#[tokio::test]
+async fn viewers_cannot_publish_a_newsletter() {
+    // Arrange
+    let app = spawn_app().await;
+    app.set_test_user_role("viewer").await;
+    app.test_user.login(&app).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashborad_html().await;
+    assert!(html_page.contains("<p><i>You do not have permission to do that.</i></p>"));
+    let issues = sqlx::query!("SELECT count(*) AS \"n!\" FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issues.n, 0);
+}
+
+#[tokio::test]
+async fn viewers_can_still_look_at_the_admin_pages() {
+    // Arrange
+    let app = spawn_app().await;
+    app.set_test_user_role("viewer").await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_publish_newsletter().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = app.get_admin_dashborad_html().await;
+    assert!(html_page.contains("You are signed in as viewer."));
+    assert!(!html_page.contains("/admin/users"));
+}
+
+#[tokio::test]
+async fn only_owners_can_manage_users() {
+    // Arrange
+    let app = spawn_app().await;
+    app.set_test_user_role("editor").await;
+    app.test_user.login(&app).await;
+    let username = Uuid::new_v4().to_string();
+
+    // Act
+    let response = app
+        .post_add_user(&serde_json::json!({
+            "username": &username,
+            "password": "a-long-enough-password",
+            "role": "owner",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    assert_eq!(role_of(&app, &username).await, None);
+}
+
+#[tokio::test]
+async fn an_added_user_can_log_in_with_their_role() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let username = Uuid::new_v4().to_string();
+
+    // Act - Part 1 - Add the user
+    add_user(&app, &username, "viewer").await;
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains(&format!("<p><i>{} has been added.</i></p>", username)));
+
+    // Act - Part 2 - Log in as them
+    app.post_logout().await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &username,
+            "password": "a-long-enough-password",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashborad_html().await;
+    assert!(html_page.contains("You are signed in as viewer."));
+}
+
+#[tokio::test]
+async fn owners_can_change_roles_and_remove_users() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let username = Uuid::new_v4().to_string();
+    let user_id = add_user(&app, &username, "viewer").await;
+
+    // Act - Part 1 - Promote them
+    let response = app.post_change_user_role(user_id, "editor").await;
+    assert_is_redirect_to(&response, "/admin/users");
+    assert_eq!(role_of(&app, &username).await.as_deref(), Some("editor"));
+
+    // Act - Part 2 - Remove them
+    let response = app.post_remove_user(user_id).await;
+    assert_is_redirect_to(&response, "/admin/users");
+    assert_eq!(role_of(&app, &username).await, None);
+}
+
+#[tokio::test]
+async fn owners_cannot_demote_or_remove_themselves() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.post_change_user_role(app.test_user.user_id, "viewer")
+        .await;
+    app.post_remove_user(app.test_user.user_id).await;
+
+    // Assert
+    assert_eq!(
+        role_of(&app, &app.test_user.username).await.as_deref(),
+        Some("owner")
+    );
+}
+
+#[tokio::test]
+async fn user_input_is_escaped_in_flash_messages() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let username = format!("<b>{}</b>", Uuid::new_v4());
+
+    // Act - Part 1 - Username
+    add_user(&app, &username, "viewer").await;
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains(&format!(
+        "<p><i>{} has been added.</i></p>",
+        htmlescape::encode_minimal(&username)
+    )));
+
+    // Act - Part 2 - Role
+    app.post_add_user(&serde_json::json!({
+        "username": Uuid::new_v4().to_string(),
+        "password": "a-long-enough-password",
+        "role": "<script>",
+    }))
+    .await;
+
+    // Assert
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("`&lt;script&gt;` is not a valid role."));
+    assert!(!html_page.contains("<script>"));
+}
+
+#[tokio::test]
+async fn removed_users_are_logged_out() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    sqlx::query!(
+        "DELETE FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Your session has ended. Please log in again."));
+    let response = app.get_admin_dashborad().await;
+    assert_is_redirect_to(&response, "/login");
+}
@@ -0,0 +1,277 @@
+use crate::helpers::{assert_is_redirect_to, create_unconfirmed_subscriber, spawn_app, TestApp};
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn welcome_email_body(delay_days: u32, subject: &str) -> serde_json::Value {
+    serde_json::json!({
+        "delay_days": delay_days.to_string(),
+        "subject": subject,
+        "html_content": "<p>Thanks for joining, here is where to start.</p>",
+        "text_content": "",
+    })
+}
+
+async fn create_welcome_email(app: &TestApp, delay_days: u32, subject: &str) -> String {
+    let response = app
+        .post_create_welcome_email(&welcome_email_body(delay_days, subject))
+        .await;
+    assert_is_redirect_to(&response, "/admin/welcome-emails");
+    sqlx::query!(
+        "SELECT welcome_email_id FROM welcome_emails WHERE subject = $1",
+        subject
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .welcome_email_id
+    .to_string()
+}
+
+/// Pretend the given number of days went by since the sequence was queued.
+async fn travel_in_time(app: &TestApp, days: i32) {
+    sqlx::query!(
+        "UPDATE welcome_email_queue SET send_at = send_at - make_interval(days => $1)",
+        days
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+/// The subjects of the emails sent after the confirmation email.
+async fn received_subjects(app: &TestApp) -> Vec<String> {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .skip(1)
+        .map(|r| {
+            let body: serde_json::Value = serde_json::from_slice(&r.body).unwrap();
+            body["Subject"].as_str().unwrap().to_string()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_the_welcome_sequence() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_create_welcome_email(&welcome_email_body(0, "Welcome"))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_welcome_sequence_is_sent_after_confirmation() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    create_welcome_email(&app, 0, "Welcome aboard").await;
+    create_welcome_email(&app, 3, "Our best issues").await;
+    let html_page = app.get_welcome_emails_html().await;
+    assert!(html_page.contains("<td>3</td><td>Our best issues</td>"));
+
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Nothing happens before confirmation
+    app.send_due_welcome_emails().await;
+    assert!(received_subjects(&app).await.is_empty());
+
+    // Act - Part 2 - Confirm, twice
+    for _ in 0..2 {
+        reqwest::get(confirmation_links.html.clone())
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+    }
+    app.send_due_welcome_emails().await;
+    assert_eq!(received_subjects(&app).await, vec!["Welcome aboard"]);
+
+    // Act - Part 3 - Three days later
+    travel_in_time(&app, 3).await;
+    app.send_due_welcome_emails().await;
+
+    // Assert
+    assert_eq!(
+        received_subjects(&app).await,
+        vec!["Welcome aboard", "Our best issues"]
+    );
+    let email_request = app.email_server.received_requests().await.unwrap().pop();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.unwrap().body).unwrap();
+    let text_body = body["TextBody"].as_str().unwrap();
+    assert!(text_body.contains("Thanks for joining, here is where to start."));
+    assert!(text_body.contains("/subscriptions/preferences?"));
+}
+
+#[tokio::test]
+async fn the_welcome_sequence_stops_when_subscribers_unsubscribe() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    create_welcome_email(&app, 1, "Tips and tricks").await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .email;
+    let link = app.get_preferences_link(&email).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_unsubscribe(&link).await;
+    assert_eq!(response.status().as_u16(), 200);
+    travel_in_time(&app, 1).await;
+    app.send_due_welcome_emails().await;
+
+    // Assert
+    let queued = sqlx::query!("SELECT count(*) AS \"n!\" FROM welcome_email_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(queued, 0);
+    // Mock verifies on Drop that we haven't sent the welcome email
+}
+
+#[tokio::test]
+async fn deleted_welcome_emails_are_not_sent() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let welcome_email_id = create_welcome_email(&app, 0, "Welcome aboard").await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_delete_welcome_email(&welcome_email_id).await;
+    assert_is_redirect_to(&response, "/admin/welcome-emails");
+    app.send_due_welcome_emails().await;
+
+    // Assert
+    let html_page = app.get_welcome_emails_html().await;
+    assert!(html_page.contains("<p><i>The welcome email has been deleted.</i></p>"));
+    assert!(!html_page.contains("Welcome aboard"));
+}
+
+#[tokio::test]
+async fn invalid_welcome_emails_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let test_cases = vec![
+        (welcome_email_body(0, ""), "The subject cannot be empty."),
+        (
+            serde_json::json!({
+                "delay_days": "-1",
+                "subject": "Welcome",
+                "html_content": "<p>Hi!</p>",
+            }),
+            "The delay must be a whole number of days.",
+        ),
+        (
+            serde_json::json!({
+                "delay_days": "0",
+                "subject": "Welcome",
+                "html_content": " ",
+            }),
+            "The HTML content cannot be empty.",
+        ),
+    ];
+
+    for (body, error_message) in test_cases {
+        // Act
+        let response = app.post_create_welcome_email(&body).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 200);
+        let html_page = response.text().await.unwrap();
+        assert!(
+            html_page.contains(error_message),
+            "The form did not explain that {}",
+            error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn welcome_emails_that_could_not_be_sent_are_tried_again_later() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    create_welcome_email(&app, 0, "Welcome aboard").await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - The email server fails
+    app.send_due_welcome_emails().await;
+    let n_retries = sqlx::query!("SELECT n_retries FROM welcome_email_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n_retries;
+    assert_eq!(n_retries, 1);
+
+    // Act - Part 2 - It is tried again later
+    travel_in_time(&app, 1).await;
+    app.send_due_welcome_emails().await;
+
+    // Assert
+    assert_eq!(
+        received_subjects(&app).await,
+        vec!["Welcome aboard", "Welcome aboard"]
+    );
+    let queued = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM welcome_email_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(queued, 0);
+}